@@ -1,42 +1,355 @@
-use std::{collections::HashMap, rc::Rc};
+use std::fmt;
 
-use crate::backend::value::Value;
+use crate::node::{Node, NodeId, NodeLogic};
 
-pub struct SignalId(u32);
+/// Index of a net (a signal shared by every pin attached to it)
+pub type NetId = u32;
 
-// Directed graph of components
-pub struct Circuit {
-    nodes: Vec<Box<dyn Node>>,
-    signals: Vec<Value>,
-    edges_to: HashMap<SignalId, Vec<Rc<dyn Node>>>, // one to many
-    edges_from: HashMap<SignalId, Rc<dyn Node>>,    // many to one
-    inputs: Vec<Box<dyn Node>>,
-    outputs: Vec<Box<dyn Node>>,
+/// Output pin `pin` of node `node`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OutputPin {
+    pub node: NodeId,
+    pub pin: usize,
+}
+
+/// Input pin `pin` of node `node`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InputPin {
+    pub node: NodeId,
+    pub pin: usize,
+}
+
+impl OutputPin {
+    pub fn new(node: NodeId, pin: usize) -> Self {
+        Self { node, pin }
+    }
+}
+
+impl InputPin {
+    pub fn new(node: NodeId, pin: usize) -> Self {
+        Self { node, pin }
+    }
 }
 
-struct NodeState {
-    output_signals: Vec<SignalId>,
-    outputs: Vec<(SignalId, Value)>,
+/// A signal of fixed width. Several drivers are resolved with `Value::resolve`.
+#[derive(Debug, Clone)]
+pub struct Net {
+    pub width: u8,
+    pub drivers: Vec<OutputPin>,
+    pub readers: Vec<InputPin>,
 }
 
-struct AndGate {
-    num_inputs: u8,
-    bit_width: u8,
-    node_state: NodeState,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitError {
+    UnknownNode(NodeId),
+    UnknownNet(NetId),
+    UnknownPin { node: NodeId, pin: usize },
+    /// A pin of width `found` was attached to a net of width `expected`
+    WidthMismatch { expected: u8, found: u8 },
+    /// The pin is already attached to another net
+    AlreadyConnected { node: NodeId, pin: usize },
 }
 
-impl Node for AndGate {
-    fn read_values(&self) {
-        let inputs = ...
+impl fmt::Display for CircuitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownNode(node) => write!(f, "no node with id {}", node),
+            Self::UnknownNet(net) => write!(f, "no net with id {}", net),
+            Self::UnknownPin { node, pin } => write!(f, "node {} has no pin {}", node, pin),
+            Self::WidthMismatch { expected, found } => write!(
+                f,
+                "width mismatch: expected {} bits, found {} bits",
+                expected, found
+            ),
+            Self::AlreadyConnected { node, pin } => {
+                write!(f, "pin {} of node {} is already connected", pin, node)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CircuitError {}
+
+/// Netlist of component instances joined by nets
+///
+/// Node and net ids are indices and stay valid for the lifetime of the circuit. Merging two
+/// nets leaves the absorbed one empty rather than renumbering.
+#[derive(Default)]
+pub struct Circuit {
+    nodes: Vec<Node>,
+    nets: Vec<Net>,
+    inputs: Vec<NodeId>,  // nodes forming the circuit's external inputs
+    outputs: Vec<NodeId>, // nodes forming the circuit's external outputs
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, logic: Box<dyn NodeLogic>) -> NodeId {
+        let id = self.nodes.len() as NodeId;
+        self.nodes.push(Node::new(id, logic));
+        id
+    }
+
+    /// Add a node that is part of the circuit's external inputs
+    pub fn add_input(&mut self, logic: Box<dyn NodeLogic>) -> NodeId {
+        let id = self.add_node(logic);
+        self.inputs.push(id);
+        id
+    }
+
+    /// Add a node that is part of the circuit's external outputs
+    pub fn add_output(&mut self, logic: Box<dyn NodeLogic>) -> NodeId {
+        let id = self.add_node(logic);
+        self.outputs.push(id);
+        id
+    }
+
+    pub fn add_net(&mut self, width: u8) -> NetId {
+        let id = self.nets.len() as NetId;
+        self.nets.push(Net {
+            width,
+            drivers: Vec::new(),
+            readers: Vec::new(),
+        });
+        id
+    }
+
+    pub fn node(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id as usize)
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id as usize)
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn nodes_mut(&mut self) -> &mut [Node] {
+        &mut self.nodes
+    }
+
+    pub fn net(&self, id: NetId) -> Option<&Net> {
+        self.nets.get(id as usize)
     }
 
-    fn get_output(&self) -> &[( SignalId , Value )] {
-        &self.node_state.outputs
+    pub fn nets(&self) -> &[Net] {
+        &self.nets
+    }
+
+    pub fn inputs(&self) -> &[NodeId] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[NodeId] {
+        &self.outputs
+    }
+
+    /// Net an output pin drives, if any
+    pub fn output_net(&self, pin: OutputPin) -> Option<NetId> {
+        self.node(pin.node)?
+            .output_connections
+            .get(pin.pin)
+            .copied()
+            .flatten()
+    }
+
+    /// Net an input pin reads, if any
+    pub fn input_net(&self, pin: InputPin) -> Option<NetId> {
+        self.node(pin.node)?
+            .input_connections
+            .get(pin.pin)
+            .copied()
+            .flatten()
+    }
+
+    fn output_width(&self, pin: OutputPin) -> Result<u8, CircuitError> {
+        let node = self.node(pin.node).ok_or(CircuitError::UnknownNode(pin.node))?;
+        if pin.pin >= node.logic.output_count() {
+            return Err(CircuitError::UnknownPin {
+                node: pin.node,
+                pin: pin.pin,
+            });
+        }
+        Ok(node.logic.output_width(pin.pin))
+    }
+
+    fn input_width(&self, pin: InputPin) -> Result<u8, CircuitError> {
+        let node = self.node(pin.node).ok_or(CircuitError::UnknownNode(pin.node))?;
+        if pin.pin >= node.logic.input_count() {
+            return Err(CircuitError::UnknownPin {
+                node: pin.node,
+                pin: pin.pin,
+            });
+        }
+        Ok(node.logic.input_width(pin.pin))
+    }
+
+    fn check_width(&self, net: NetId, width: u8) -> Result<(), CircuitError> {
+        let expected = self.net(net).ok_or(CircuitError::UnknownNet(net))?.width;
+        if expected != width {
+            return Err(CircuitError::WidthMismatch {
+                expected,
+                found: width,
+            });
+        }
+        Ok(())
+    }
+
+    /// Make `pin` one of the drivers of `net`
+    pub fn attach_output(&mut self, net: NetId, pin: OutputPin) -> Result<(), CircuitError> {
+        let width = self.output_width(pin)?;
+        self.check_width(net, width)?;
+        match self.output_net(pin) {
+            Some(existing) if existing == net => return Ok(()),
+            Some(_) => {
+                return Err(CircuitError::AlreadyConnected {
+                    node: pin.node,
+                    pin: pin.pin,
+                });
+            }
+            None => {}
+        }
+
+        self.nodes[pin.node as usize].output_connections[pin.pin] = Some(net);
+        self.nets[net as usize].drivers.push(pin);
+        Ok(())
+    }
+
+    /// Make `pin` read the value of `net`
+    pub fn attach_input(&mut self, net: NetId, pin: InputPin) -> Result<(), CircuitError> {
+        let width = self.input_width(pin)?;
+        self.check_width(net, width)?;
+        match self.input_net(pin) {
+            Some(existing) if existing == net => return Ok(()),
+            Some(_) => {
+                return Err(CircuitError::AlreadyConnected {
+                    node: pin.node,
+                    pin: pin.pin,
+                });
+            }
+            None => {}
+        }
+
+        self.nodes[pin.node as usize].input_connections[pin.pin] = Some(net);
+        self.nets[net as usize].readers.push(pin);
+        Ok(())
+    }
+
+    /// Connect an output pin to an input pin, creating a net or extending and merging the nets
+    /// they are already attached to. Returns the net joining them.
+    pub fn connect(&mut self, from: OutputPin, to: InputPin) -> Result<NetId, CircuitError> {
+        let width = self.output_width(from)?;
+        let to_width = self.input_width(to)?;
+        if width != to_width {
+            return Err(CircuitError::WidthMismatch {
+                expected: width,
+                found: to_width,
+            });
+        }
+
+        match (self.output_net(from), self.input_net(to)) {
+            (Some(a), Some(b)) => {
+                self.merge_nets(a, b)?;
+                Ok(a)
+            }
+            (Some(net), None) => {
+                self.attach_input(net, to)?;
+                Ok(net)
+            }
+            (None, Some(net)) => {
+                self.attach_output(net, from)?;
+                Ok(net)
+            }
+            (None, None) => {
+                let net = self.add_net(width);
+                self.attach_output(net, from)?;
+                self.attach_input(net, to)?;
+                Ok(net)
+            }
+        }
+    }
+
+    /// Move every pin of net `from` onto net `into`. `from` is left empty.
+    pub fn merge_nets(&mut self, into: NetId, from: NetId) -> Result<(), CircuitError> {
+        if into == from {
+            return Ok(());
+        }
+        let width = self.net(from).ok_or(CircuitError::UnknownNet(from))?.width;
+        self.check_width(into, width)?;
+
+        let drivers = std::mem::take(&mut self.nets[from as usize].drivers);
+        let readers = std::mem::take(&mut self.nets[from as usize].readers);
+        for pin in &drivers {
+            self.nodes[pin.node as usize].output_connections[pin.pin] = Some(into);
+        }
+        for pin in &readers {
+            self.nodes[pin.node as usize].input_connections[pin.pin] = Some(into);
+        }
+        let net = &mut self.nets[into as usize];
+        net.drivers.extend(drivers);
+        net.readers.extend(readers);
+        Ok(())
     }
 }
 
-trait Node {
-    // fn create(&self, );
-    fn read_values(&self);
-    fn get_output(&self) -> &[( SignalId , Value )];
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::gates::{Gate, GateKind};
+
+    #[test]
+    fn connect_builds_and_merges_nets() {
+        let mut circuit = Circuit::new();
+        let a = circuit.add_node(Box::new(Gate::new(GateKind::And, 2, 1)));
+        let b = circuit.add_node(Box::new(Gate::new(GateKind::Or, 2, 1)));
+        let c = circuit.add_node(Box::new(Gate::new(GateKind::Not, 1, 1)));
+
+        let first = circuit.connect(OutputPin::new(a, 0), InputPin::new(c, 0)).unwrap();
+        let second = circuit.connect(OutputPin::new(b, 0), InputPin::new(a, 1)).unwrap();
+        assert_ne!(first, second);
+
+        // A second driver on the same input joins the existing net
+        let shared = circuit.connect(OutputPin::new(b, 0), InputPin::new(c, 0)).unwrap();
+        assert_eq!(shared, second);
+        assert_eq!(circuit.input_net(InputPin::new(c, 0)), Some(second));
+        assert_eq!(circuit.output_net(OutputPin::new(a, 0)), Some(second));
+        assert_eq!(circuit.net(second).unwrap().drivers.len(), 2);
+        assert!(circuit.net(first).unwrap().readers.is_empty());
+    }
+
+    #[test]
+    fn connect_rejects_bad_pins() {
+        let mut circuit = Circuit::new();
+        let narrow = circuit.add_node(Box::new(Gate::new(GateKind::And, 2, 1)));
+        let wide = circuit.add_node(Box::new(Gate::new(GateKind::And, 2, 8)));
+
+        assert_eq!(
+            circuit.connect(OutputPin::new(narrow, 0), InputPin::new(wide, 0)),
+            Err(CircuitError::WidthMismatch {
+                expected: 1,
+                found: 8
+            })
+        );
+        assert_eq!(
+            circuit.connect(OutputPin::new(narrow, 1), InputPin::new(wide, 0)),
+            Err(CircuitError::UnknownPin { node: narrow, pin: 1 })
+        );
+        assert_eq!(
+            circuit.connect(OutputPin::new(7, 0), InputPin::new(wide, 0)),
+            Err(CircuitError::UnknownNode(7))
+        );
+
+        let net = circuit.add_net(1);
+        circuit.attach_input(net, InputPin::new(narrow, 0)).unwrap();
+        let other = circuit.add_net(1);
+        assert_eq!(
+            circuit.attach_input(other, InputPin::new(narrow, 0)),
+            Err(CircuitError::AlreadyConnected { node: narrow, pin: 0 })
+        );
+    }
 }
@@ -0,0 +1,609 @@
+use serde::{Deserialize, Serialize};
+
+use crate::component::plexers::select_width_for;
+use crate::node::{NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Widest operand arithmetic components take
+pub const MAX_WIDTH: u8 = 64;
+
+// Poisoning rules shared by every component here: an operand bit that is X or Z makes the
+// result X. Adders, subtractors and negators only lose the bits a carry or borrow could reach,
+// from the lowest unknown operand bit upwards; the other components lose every output bit.
+// Carry and borrow inputs are the exception to Z: unconnected, they count as 0.
+
+fn check_width(width: u8) {
+    assert!(
+        (1..=MAX_WIDTH).contains(&width),
+        "arithmetic width must be 1..={MAX_WIDTH}"
+    );
+}
+
+fn mask(width: u8) -> u64 {
+    u64::MAX >> (64 - width as u32)
+}
+
+/// Lowest bit that is X or Z in any of `operands`
+fn lowest_unknown(operands: &[&Value]) -> Option<u8> {
+    operands
+        .iter()
+        .filter_map(|operand| {
+            (0..operand.width()).find(|&bit| {
+                matches!(
+                    operand.get_bit_state(bit),
+                    BitState::HighZ | BitState::Unknown
+                )
+            })
+        })
+        .min()
+}
+
+/// The 1 bits of `operand`, reading X and Z as 0
+fn logic_bits(operand: &Value) -> u64 {
+    (0..operand.width().min(64))
+        .filter(|&bit| operand.get_bit_state(bit) == BitState::Logic1)
+        .fold(0, |bits, bit| bits | 1 << bit)
+}
+
+/// Carry or borrow input, or `None` when unknown
+fn carry_in(carry: &Value) -> Option<u64> {
+    match carry.get_bit_state(0) {
+        BitState::Logic0 | BitState::HighZ => Some(0),
+        BitState::Logic1 => Some(1),
+        BitState::Unknown => None,
+    }
+}
+
+/// Result of a carry chain: exact below bit `unknown`, X from it upwards
+fn ripple(result: u64, width: u8, unknown: Option<u8>) -> Value {
+    let mut value = Value::from_u64(result & mask(width), width);
+    for bit in unknown.unwrap_or(width)..width {
+        value.set_unknown_bit(bit);
+    }
+    value
+}
+
+/// Adds two operands and a carry
+///
+/// Inputs are the two operands and carry in; outputs are the sum and carry out.
+#[derive(Debug, Clone)]
+pub struct Adder {
+    width: u8,
+}
+
+impl Adder {
+    pub fn new(width: u8) -> Self {
+        check_width(width);
+        Self { width }
+    }
+}
+
+impl NodeLogic for Adder {
+    fn compute(&self, state: &mut NodeState) {
+        let (a, b) = (&state.inputs[0], &state.inputs[1]);
+        let carry = carry_in(&state.inputs[2]);
+        let unknown = match carry {
+            Some(_) => lowest_unknown(&[a, b]),
+            None => Some(0),
+        };
+        let sum = logic_bits(a) as u128 + logic_bits(b) as u128 + carry.unwrap_or(0) as u128;
+        state.outputs[0] = ripple(sum as u64, self.width, unknown);
+        let carry_out = (sum >> self.width) as u64;
+        state.outputs[1] = ripple(carry_out, 1, unknown.map(|_| 0));
+    }
+
+    fn name(&self) -> &str {
+        "Adder"
+    }
+
+    fn input_count(&self) -> usize {
+        3
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin < 2 { self.width } else { 1 }
+    }
+
+    fn output_width(&self, pin: usize) -> u8 {
+        if pin == 0 { self.width } else { 1 }
+    }
+}
+
+/// Subtracts its second operand and a borrow from its first
+///
+/// Inputs are the two operands and borrow in; outputs are the difference and borrow out,
+/// which is 1 when the result wrapped below zero.
+#[derive(Debug, Clone)]
+pub struct Subtractor {
+    width: u8,
+}
+
+impl Subtractor {
+    pub fn new(width: u8) -> Self {
+        check_width(width);
+        Self { width }
+    }
+}
+
+impl NodeLogic for Subtractor {
+    fn compute(&self, state: &mut NodeState) {
+        let (a, b) = (&state.inputs[0], &state.inputs[1]);
+        let borrow = carry_in(&state.inputs[2]);
+        let unknown = match borrow {
+            Some(_) => lowest_unknown(&[a, b]),
+            None => Some(0),
+        };
+        let subtrahend = logic_bits(b) as u128 + borrow.unwrap_or(0) as u128;
+        let minuend = logic_bits(a) as u128;
+        let difference = minuend.wrapping_sub(subtrahend) as u64;
+        state.outputs[0] = ripple(difference, self.width, unknown);
+        let borrow_out = (minuend < subtrahend) as u64;
+        state.outputs[1] = ripple(borrow_out, 1, unknown.map(|_| 0));
+    }
+
+    fn name(&self) -> &str {
+        "Subtractor"
+    }
+
+    fn input_count(&self) -> usize {
+        3
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin < 2 { self.width } else { 1 }
+    }
+
+    fn output_width(&self, pin: usize) -> u8 {
+        if pin == 0 { self.width } else { 1 }
+    }
+}
+
+/// Multiplies two unsigned operands
+///
+/// Outputs are the low and high words of the double-width product.
+#[derive(Debug, Clone)]
+pub struct Multiplier {
+    width: u8,
+}
+
+impl Multiplier {
+    pub fn new(width: u8) -> Self {
+        check_width(width);
+        Self { width }
+    }
+}
+
+impl NodeLogic for Multiplier {
+    fn compute(&self, state: &mut NodeState) {
+        let (a, b) = (&state.inputs[0], &state.inputs[1]);
+        if lowest_unknown(&[a, b]).is_some() {
+            state.outputs = vec![Value::unknown(self.width); 2];
+            return;
+        }
+        let product = logic_bits(a) as u128 * logic_bits(b) as u128;
+        let high = (product >> self.width) as u64 & mask(self.width);
+        state.outputs[0] = Value::from_u64(product as u64 & mask(self.width), self.width);
+        state.outputs[1] = Value::from_u64(high, self.width);
+    }
+
+    fn name(&self) -> &str {
+        "Multiplier"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+}
+
+/// Divides its first unsigned operand by its second
+///
+/// Outputs are the quotient and remainder. Dividing by zero makes both X.
+#[derive(Debug, Clone)]
+pub struct Divider {
+    width: u8,
+}
+
+impl Divider {
+    pub fn new(width: u8) -> Self {
+        check_width(width);
+        Self { width }
+    }
+}
+
+impl NodeLogic for Divider {
+    fn compute(&self, state: &mut NodeState) {
+        let (a, b) = (&state.inputs[0], &state.inputs[1]);
+        let divisor = logic_bits(b);
+        if lowest_unknown(&[a, b]).is_some() || divisor == 0 {
+            state.outputs = vec![Value::unknown(self.width); 2];
+            return;
+        }
+        let dividend = logic_bits(a);
+        state.outputs[0] = Value::from_u64(dividend / divisor, self.width);
+        state.outputs[1] = Value::from_u64(dividend % divisor, self.width);
+    }
+
+    fn name(&self) -> &str {
+        "Divider"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+}
+
+/// Compares two operands, as two's complement numbers when signed
+///
+/// Outputs are less than, equal and greater than, each 1 bit.
+#[derive(Debug, Clone)]
+pub struct Comparator {
+    width: u8,
+    signed: bool,
+}
+
+impl Comparator {
+    pub fn new(width: u8, signed: bool) -> Self {
+        check_width(width);
+        Self { width, signed }
+    }
+
+    fn number(&self, operand: &Value) -> i128 {
+        let bits = logic_bits(operand);
+        if self.signed {
+            let shift = 64 - self.width as u32;
+            (((bits << shift) as i64) >> shift) as i128
+        } else {
+            bits as i128
+        }
+    }
+}
+
+impl NodeLogic for Comparator {
+    fn compute(&self, state: &mut NodeState) {
+        let (a, b) = (&state.inputs[0], &state.inputs[1]);
+        if lowest_unknown(&[a, b]).is_some() {
+            state.outputs = vec![Value::unknown(1); 3];
+            return;
+        }
+        let ordering = self.number(a).cmp(&self.number(b));
+        state.outputs = [ordering.is_lt(), ordering.is_eq(), ordering.is_gt()]
+            .map(|result| Value::new(result as u32, 1))
+            .to_vec();
+    }
+
+    fn name(&self) -> &str {
+        "Comparator"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        3
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        1
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShiftKind {
+    #[default]
+    LogicalLeft,
+    LogicalRight,
+    /// Right shift copying the sign bit into the vacated bits
+    ArithmeticRight,
+    RotateLeft,
+    RotateRight,
+}
+
+impl ShiftKind {
+    pub const ALL: [ShiftKind; 5] = [
+        Self::LogicalLeft,
+        Self::LogicalRight,
+        Self::ArithmeticRight,
+        Self::RotateLeft,
+        Self::RotateRight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::LogicalLeft => "Logical left",
+            Self::LogicalRight => "Logical right",
+            Self::ArithmeticRight => "Arithmetic right",
+            Self::RotateLeft => "Rotate left",
+            Self::RotateRight => "Rotate right",
+        }
+    }
+}
+
+/// Barrel shifter moving its data input by a distance
+///
+/// Inputs are data and distance, which is as wide as it takes to number every data bit.
+/// Unknown data bits move with the shift and floating ones become X; an unknown distance
+/// makes the whole output X.
+#[derive(Debug, Clone)]
+pub struct Shifter {
+    width: u8,
+    kind: ShiftKind,
+}
+
+impl Shifter {
+    pub fn new(width: u8, kind: ShiftKind) -> Self {
+        check_width(width);
+        Self { width, kind }
+    }
+
+    /// Data bit that ends up in bit `bit`, or `None` for a vacated bit that becomes 0
+    fn source(&self, bit: u64, distance: u64) -> Option<u64> {
+        let width = self.width as u64;
+        match self.kind {
+            ShiftKind::LogicalLeft => bit.checked_sub(distance),
+            ShiftKind::LogicalRight => Some(bit + distance).filter(|&source| source < width),
+            ShiftKind::ArithmeticRight => Some((bit + distance).min(width - 1)),
+            ShiftKind::RotateLeft => Some((bit + width - distance % width) % width),
+            ShiftKind::RotateRight => Some((bit + distance) % width),
+        }
+    }
+}
+
+impl NodeLogic for Shifter {
+    fn compute(&self, state: &mut NodeState) {
+        let Some(distance) = state.inputs[1].as_u64() else {
+            state.outputs[0] = Value::unknown(self.width);
+            return;
+        };
+        let data = &state.inputs[0];
+        let mut shifted = Value::new(0, self.width);
+        for bit in 0..self.width {
+            let source = self.source(bit as u64, distance);
+            match source.map(|source| data.get_bit_state(source as u8)) {
+                None | Some(BitState::Logic0) => {}
+                Some(BitState::Logic1) => shifted.set_logic_bit(bit, true),
+                Some(BitState::HighZ | BitState::Unknown) => shifted.set_unknown_bit(bit),
+            }
+        }
+        state.outputs[0] = shifted;
+    }
+
+    fn name(&self) -> &str {
+        "Shifter"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 {
+            self.width
+        } else {
+            select_width_for(self.width)
+        }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+}
+
+/// Two's complement negation of its input
+#[derive(Debug, Clone)]
+pub struct Negator {
+    width: u8,
+}
+
+impl Negator {
+    pub fn new(width: u8) -> Self {
+        check_width(width);
+        Self { width }
+    }
+}
+
+impl NodeLogic for Negator {
+    fn compute(&self, state: &mut NodeState) {
+        let operand = &state.inputs[0];
+        let negated = logic_bits(operand).wrapping_neg();
+        state.outputs[0] = ripple(negated, self.width, lowest_unknown(&[operand]));
+    }
+
+    fn name(&self) -> &str {
+        "Negator"
+    }
+
+    fn input_count(&self) -> usize {
+        1
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+}
+
+/// Counts the 1 bits of its input
+#[derive(Debug, Clone)]
+pub struct BitCounter {
+    width: u8,
+}
+
+impl BitCounter {
+    pub fn new(width: u8) -> Self {
+        check_width(width);
+        Self { width }
+    }
+
+    /// Bits needed to hold every count from 0 to the input width
+    pub fn count_width(&self) -> u8 {
+        (u8::BITS - self.width.leading_zeros()) as u8
+    }
+}
+
+impl NodeLogic for BitCounter {
+    fn compute(&self, state: &mut NodeState) {
+        let operand = &state.inputs[0];
+        state.outputs[0] = if lowest_unknown(&[operand]).is_some() {
+            Value::unknown(self.count_width())
+        } else {
+            Value::new(logic_bits(operand).count_ones(), self.count_width())
+        };
+    }
+
+    fn name(&self) -> &str {
+        "Bit Counter"
+    }
+
+    fn input_count(&self) -> usize {
+        1
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.count_width()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(logic: &dyn NodeLogic, inputs: Vec<Value>) -> Vec<Value> {
+        let mut state = NodeState::new(logic);
+        state.inputs = inputs;
+        logic.compute(&mut state);
+        state.outputs
+    }
+
+    #[test]
+    fn carries_ripple_from_the_lowest_unknown_bit() {
+        let adder = Adder::new(8);
+        let outputs = evaluate(
+            &adder,
+            vec![Value::new(200, 8), Value::new(100, 8), Value::high_z(1)],
+        );
+        assert_eq!(outputs, vec![Value::new(44, 8), Value::new(1, 1)]);
+
+        let mut a = Value::new(0b0000_0011, 8);
+        a.set_unknown_bit(4);
+        let outputs = evaluate(&adder, vec![a, Value::new(1, 8), Value::new(0, 1)]);
+        assert_eq!(outputs[0].to_string(), "XXXX0100");
+        assert_eq!(outputs[1], Value::unknown(1));
+
+        let subtractor = Subtractor::new(8);
+        let outputs = evaluate(
+            &subtractor,
+            vec![Value::new(3, 8), Value::new(3, 8), Value::new(1, 1)],
+        );
+        assert_eq!(outputs, vec![Value::new(255, 8), Value::new(1, 1)]);
+
+        let outputs = evaluate(&Negator::new(4), vec![Value::new(1, 4)]);
+        assert_eq!(outputs[0], Value::new(0b1111, 4));
+    }
+
+    #[test]
+    fn products_and_quotients() {
+        let outputs = evaluate(
+            &Multiplier::new(8),
+            vec![Value::new(200, 8), Value::new(100, 8)],
+        );
+        assert_eq!(outputs, vec![Value::new(0x20, 8), Value::new(0x4E, 8)]);
+
+        let divider = Divider::new(8);
+        let outputs = evaluate(&divider, vec![Value::new(200, 8), Value::new(7, 8)]);
+        assert_eq!(outputs, vec![Value::new(28, 8), Value::new(4, 8)]);
+        let outputs = evaluate(&divider, vec![Value::new(200, 8), Value::new(0, 8)]);
+        assert_eq!(outputs, vec![Value::unknown(8); 2]);
+        let outputs = evaluate(&divider, vec![Value::new(200, 8), Value::high_z(8)]);
+        assert_eq!(outputs, vec![Value::unknown(8); 2]);
+    }
+
+    #[test]
+    fn comparisons_respect_sign() {
+        let inputs = vec![Value::new(0xFF, 8), Value::new(1, 8)];
+        let bits = |outputs: Vec<Value>| outputs.iter().map(Value::to_string).collect::<String>();
+        assert_eq!(
+            bits(evaluate(&Comparator::new(8, false), inputs.clone())),
+            "001"
+        );
+        assert_eq!(bits(evaluate(&Comparator::new(8, true), inputs)), "100");
+    }
+
+    #[test]
+    fn shifts_move_unknown_bits_along() {
+        let mut data = Value::new(0b1000_0010, 8);
+        data.set_unknown_bit(0);
+        let shift = |kind, distance| {
+            let outputs = evaluate(
+                &Shifter::new(8, kind),
+                vec![data.clone(), Value::new(distance, 3)],
+            );
+            outputs[0].to_string()
+        };
+        assert_eq!(shift(ShiftKind::LogicalLeft, 2), "00001X00");
+        assert_eq!(shift(ShiftKind::LogicalRight, 1), "01000001");
+        assert_eq!(shift(ShiftKind::ArithmeticRight, 3), "11110000");
+        assert_eq!(shift(ShiftKind::RotateLeft, 1), "000001X1");
+        assert_eq!(shift(ShiftKind::RotateRight, 1), "X1000001");
+
+        let counter = BitCounter::new(8);
+        assert_eq!(counter.count_width(), 4);
+        let outputs = evaluate(&counter, vec![Value::new(0b1011_0110, 8)]);
+        assert_eq!(outputs[0], Value::new(5, 4));
+    }
+}
@@ -0,0 +1,306 @@
+use crate::node::{NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Buffer or inverter whose output floats unless enabled, so several can share a bus
+///
+/// Inputs are data and enable. An unknown enable makes the output X; an unconnected one counts
+/// as enabled.
+#[derive(Debug, Clone)]
+pub struct ControlledBuffer {
+    bit_width: u8,
+    inverting: bool,
+}
+
+impl ControlledBuffer {
+    pub fn new(bit_width: u8, inverting: bool) -> Self {
+        Self {
+            bit_width,
+            inverting,
+        }
+    }
+}
+
+impl NodeLogic for ControlledBuffer {
+    fn compute(&self, state: &mut NodeState) {
+        let data = &state.inputs[0];
+        state.outputs[0] = match state.inputs[1].get_bit_state(0) {
+            BitState::Logic1 | BitState::HighZ if self.inverting => !data,
+            // Floating data bits stay floating through a buffer
+            BitState::Logic1 | BitState::HighZ => data.clone(),
+            BitState::Logic0 => Value::high_z(self.bit_width),
+            BitState::Unknown => Value::unknown(self.bit_width),
+        };
+    }
+
+    fn name(&self) -> &str {
+        if self.inverting {
+            "Controlled Inverter"
+        } else {
+            "Controlled Buffer"
+        }
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 { self.bit_width } else { 1 }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+}
+
+/// Weakly drives every bit of its net to 1 or 0, so bits nothing else drives do not float
+#[derive(Debug, Clone)]
+pub struct PullResistor {
+    bit_width: u8,
+    pull_up: bool,
+}
+
+impl PullResistor {
+    pub fn new(bit_width: u8, pull_up: bool) -> Self {
+        Self { bit_width, pull_up }
+    }
+}
+
+impl NodeLogic for PullResistor {
+    fn compute(&self, state: &mut NodeState) {
+        let low = Value::new(0, self.bit_width);
+        state.outputs[0] = if self.pull_up { !&low } else { low };
+    }
+
+    fn name(&self) -> &str {
+        if self.pull_up { "Pull-Up" } else { "Pull-Down" }
+    }
+
+    fn input_count(&self) -> usize {
+        0
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        0
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+
+    fn drives_weakly(&self) -> bool {
+        true
+    }
+}
+
+/// Switch joining its two sides while its gate is 1 and its inverted gate 0, and leaving
+/// them apart while they are the other way round
+///
+/// Inputs are side A, gate, inverted gate and side B; outputs are sides A and B, each on the
+/// same net as the input for that side. Signals pass either way, each side driving what the
+/// other side's drivers put on theirs. Any other control might join the sides or not, so
+/// each side gets X wherever the other is driven.
+#[derive(Debug, Clone)]
+pub struct TransmissionGate {
+    bit_width: u8,
+}
+
+impl TransmissionGate {
+    pub fn new(bit_width: u8) -> Self {
+        Self { bit_width }
+    }
+}
+
+impl NodeLogic for TransmissionGate {
+    fn compute(&self, state: &mut NodeState) {
+        let gate = state.inputs[1].get_bit_state(0);
+        let inverted_gate = state.inputs[2].get_bit_state(0);
+        let floating = Value::high_z(self.bit_width);
+        let pass = |from: &Value| match (gate, inverted_gate) {
+            (BitState::Logic1, BitState::Logic0) => from.clone(),
+            (BitState::Logic0, BitState::Logic1) => floating.clone(),
+            _ => Value::merge(&[from.clone(), floating.clone()]),
+        };
+        state.outputs = vec![pass(&state.inputs[3]), pass(&state.inputs[0])];
+    }
+
+    fn name(&self) -> &str {
+        "Transmission Gate"
+    }
+
+    fn input_count(&self) -> usize {
+        4
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 || pin == 3 {
+            self.bit_width
+        } else {
+            1
+        }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+
+    /// Drive nothing until the first evaluation, rather than X
+    fn init(&self, state: &mut NodeState) {
+        state.outputs = vec![Value::high_z(self.bit_width); 2];
+    }
+
+    fn is_bidirectional(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{Circuit, InputPin, OutputPin};
+    use crate::component::io::{Input, VALUE_KEY};
+    use crate::simulation::Simulator;
+
+    #[test]
+    fn buffers_share_a_pulled_up_bus() {
+        let mut circuit = Circuit::new();
+        let bus = circuit.add_net(4);
+        let mut enables = Vec::new();
+        for data in [0b0101, 0b0011] {
+            let buffer = circuit.add_node(Box::new(ControlledBuffer::new(4, false)));
+            circuit
+                .attach_output(bus, OutputPin::new(buffer, 0))
+                .unwrap();
+            for (pin, width, value) in [(0, 4, data), (1, 1, 0)] {
+                let input = circuit.add_input(Box::new(Input::new(width)));
+                let net = circuit.add_net(width);
+                circuit
+                    .attach_output(net, OutputPin::new(input, 0))
+                    .unwrap();
+                circuit
+                    .attach_input(net, InputPin::new(buffer, pin))
+                    .unwrap();
+                let state = &mut circuit.node_mut(input).unwrap().state;
+                state
+                    .metadata
+                    .insert(VALUE_KEY.to_string(), Value::new(value, width));
+                if pin == 1 {
+                    enables.push(input);
+                }
+            }
+        }
+        let pull_up = circuit.add_node(Box::new(PullResistor::new(4, true)));
+        circuit
+            .attach_output(bus, OutputPin::new(pull_up, 0))
+            .unwrap();
+
+        let mut simulator = Simulator::new(circuit);
+        let enable = |simulator: &mut Simulator, index: usize, on: u32| {
+            let id = enables[index];
+            let state = &mut simulator.circuit_mut().node_mut(id).unwrap().state;
+            state
+                .metadata
+                .insert(VALUE_KEY.to_string(), Value::new(on, 1));
+            simulator.schedule(id);
+            simulator.settle().unwrap();
+        };
+
+        simulator.settle().unwrap();
+        assert_eq!(simulator.net_value(bus), Some(&Value::new(0b1111, 4)));
+        enable(&mut simulator, 0, 1);
+        assert_eq!(simulator.net_value(bus), Some(&Value::new(0b0101, 4)));
+
+        // Two enabled drivers short where they disagree
+        enable(&mut simulator, 1, 1);
+        let value = simulator.net_value(bus).unwrap();
+        assert_eq!(value.get_bit_state(0), BitState::Logic1);
+        assert_eq!(value.get_bit_state(1), BitState::Unknown);
+        assert_eq!(value.get_bit_state(2), BitState::Unknown);
+        assert_eq!(value.get_bit_state(3), BitState::Logic0);
+    }
+
+    #[test]
+    fn transmission_gate_needs_both_controls() {
+        let gate = TransmissionGate::new(1);
+        let mut state = NodeState::new(&gate);
+        for (controls, expected) in [
+            ([1, 0], Value::new(1, 1)),
+            ([0, 1], Value::high_z(1)),
+            ([1, 1], Value::unknown(1)),
+        ] {
+            state.inputs = vec![
+                Value::new(1, 1),
+                Value::new(controls[0], 1),
+                Value::new(controls[1], 1),
+                Value::high_z(1),
+            ];
+            gate.compute(&mut state);
+            assert_eq!(state.outputs[1], expected);
+            // Nothing drives side B, so side A gets nothing either way
+            assert_eq!(state.outputs[0], Value::high_z(1));
+        }
+    }
+
+    #[test]
+    fn transmission_gate_passes_both_ways() {
+        let mut circuit = Circuit::new();
+        let gate = circuit.add_node(Box::new(TransmissionGate::new(2)));
+        let mut inputs = Vec::new();
+        let mut sides = Vec::new();
+        for (pin, width) in [(0, 2), (1, 1), (2, 1), (3, 2)] {
+            let input = circuit.add_input(Box::new(Input::new(width)));
+            let net = circuit.add_net(width);
+            circuit
+                .attach_output(net, OutputPin::new(input, 0))
+                .unwrap();
+            circuit.attach_input(net, InputPin::new(gate, pin)).unwrap();
+            if width == 2 {
+                // Sides are driven by the gate as well as read
+                let side = sides.len();
+                circuit
+                    .attach_output(net, OutputPin::new(gate, side))
+                    .unwrap();
+                sides.push(net);
+            }
+            inputs.push(input);
+        }
+
+        let mut simulator = Simulator::new(circuit);
+        let drive = |simulator: &mut Simulator, pin: usize, value: Value| {
+            let id = inputs[pin];
+            let state = &mut simulator.circuit_mut().node_mut(id).unwrap().state;
+            state.metadata.insert(VALUE_KEY.to_string(), value);
+            simulator.schedule(id);
+            simulator.settle().unwrap();
+        };
+        drive(&mut simulator, 0, Value::new(0b10, 2));
+        drive(&mut simulator, 3, Value::high_z(2));
+        drive(&mut simulator, 2, Value::new(0, 1));
+        drive(&mut simulator, 1, Value::new(1, 1));
+        assert_eq!(simulator.net_value(sides[1]), Some(&Value::new(0b10, 2)));
+
+        // The other way round
+        drive(&mut simulator, 0, Value::high_z(2));
+        drive(&mut simulator, 3, Value::new(0b01, 2));
+        assert_eq!(simulator.net_value(sides[0]), Some(&Value::new(0b01, 2)));
+
+        // Open, side A floats; unsure, it might get side B
+        drive(&mut simulator, 1, Value::new(0, 1));
+        drive(&mut simulator, 2, Value::new(1, 1));
+        assert_eq!(simulator.net_value(sides[0]), Some(&Value::high_z(2)));
+        drive(&mut simulator, 1, Value::unknown(1));
+        assert_eq!(simulator.net_value(sides[0]), Some(&Value::unknown(2)));
+    }
+}
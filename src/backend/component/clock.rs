@@ -0,0 +1,111 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::{NodeLogic, NodeState};
+use crate::value::Value;
+
+/// Metadata key holding how many ticks a clock is into its period
+pub const PHASE_KEY: &str = "phase";
+
+/// How long a clock stays at each level, in simulation ticks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClockTiming {
+    pub high_ticks: u32,
+    pub low_ticks: u32,
+}
+
+impl Default for ClockTiming {
+    fn default() -> Self {
+        Self {
+            high_ticks: 1,
+            low_ticks: 1,
+        }
+    }
+}
+
+impl ClockTiming {
+    /// Ticks in one full period, up to `u32::MAX`. Levels last at least one tick each.
+    pub fn period(&self) -> u32 {
+        self.high_ticks.max(1).saturating_add(self.low_ticks.max(1))
+    }
+
+    /// Level `phase` ticks into the period: low first, then high
+    pub fn level(&self, phase: u32) -> bool {
+        phase % self.period() >= self.low_ticks.max(1)
+    }
+}
+
+/// Square wave driven by simulation time rather than its inputs
+#[derive(Debug, Clone)]
+pub struct Clock {
+    timing: ClockTiming,
+}
+
+impl Clock {
+    pub fn new(timing: ClockTiming) -> Self {
+        Self { timing }
+    }
+
+    fn phase(state: &NodeState) -> u32 {
+        state
+            .metadata
+            .get(PHASE_KEY)
+            .and_then(Value::as_u32)
+            .unwrap_or(0)
+    }
+}
+
+impl NodeLogic for Clock {
+    fn compute(&self, state: &mut NodeState) {
+        let level = self.timing.level(Self::phase(state));
+        state.outputs[0] = Value::new(level as u32, 1);
+    }
+
+    fn name(&self) -> &str {
+        "Clock"
+    }
+
+    fn input_count(&self) -> usize {
+        0
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        0
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        1
+    }
+
+    fn tick(&self, state: &mut NodeState) {
+        let phase = (Self::phase(state) + 1) % self.timing.period();
+        state
+            .metadata
+            .insert(PHASE_KEY.to_string(), Value::new(phase, 32));
+        self.compute(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_follows_its_timing() {
+        let clock = Clock::new(ClockTiming {
+            high_ticks: 2,
+            low_ticks: 1,
+        });
+        let mut state = NodeState::new(&clock);
+        clock.compute(&mut state);
+        let mut levels = vec![state.outputs[0].as_u32().unwrap()];
+        for _ in 0..6 {
+            clock.tick(&mut state);
+            levels.push(state.outputs[0].as_u32().unwrap());
+        }
+        assert_eq!(levels, vec![0, 1, 1, 0, 1, 1, 0]);
+    }
+}
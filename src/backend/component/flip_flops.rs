@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::{Edge, NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Metadata key holding the stored value of a flip-flop or register
+pub const STATE_KEY: &str = "state";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FlipFlopKind {
+    D,
+    T,
+    JK,
+    SR,
+}
+
+impl FlipFlopKind {
+    /// Number of data inputs, which come before the clock
+    pub fn data_inputs(&self) -> usize {
+        match self {
+            Self::D | Self::T => 1,
+            Self::JK | Self::SR => 2,
+        }
+    }
+
+    /// State after a clock edge, given the current state `q` and the data inputs
+    fn next(&self, q: &Value, data: &[Value]) -> Value {
+        let one = Value::new(1, 1);
+        match self {
+            Self::D => &data[0] & &one,
+            Self::T => &data[0] ^ q,
+            // Set, reset, hold or toggle
+            Self::JK => (&data[0] & &!q) | (&!&data[1] & q),
+            // Set, reset or hold; setting and resetting at once is undefined
+            Self::SR => {
+                let next = &data[0] | &(&!&data[1] & q);
+                let both = &data[0] & &data[1];
+                choose(&both, Value::unknown(1), next)
+            }
+        }
+    }
+}
+
+/// `if_true` where `condition` is 1, `if_false` where it is 0. An unknown condition gives the
+/// bits the two agree on, and X elsewhere.
+fn choose(condition: &Value, if_true: Value, if_false: Value) -> Value {
+    match condition.get_bit_state(0) {
+        BitState::Logic1 => if_true,
+        BitState::Logic0 => if_false,
+        // Resolving the two as drivers of one net keeps agreeing bits and makes the rest X
+        BitState::HighZ | BitState::Unknown => Value::resolve(&[if_true, if_false]),
+    }
+}
+
+/// Shared edge-triggered behaviour of flip-flops and registers
+///
+/// Inputs are the data inputs, then clock, enable, asynchronous reset and asynchronous preset.
+/// Reset wins over preset, and both act at once without waiting for the clock. An unconnected
+/// enable counts as enabled; unconnected reset and preset as inactive. The stored value starts
+/// at 0.
+fn clock_state(
+    state: &mut NodeState,
+    width: u8,
+    data_inputs: usize,
+    trigger: Edge,
+    next: impl FnOnce(&Value, &[Value]) -> Value,
+) -> Value {
+    let zero = Value::new(0, width);
+    let ones = !&zero;
+    let mut stored = state
+        .metadata
+        .get(STATE_KEY)
+        .cloned()
+        .unwrap_or_else(|| zero.clone());
+
+    let clock = data_inputs;
+    let [enable, reset, preset] = [clock + 1, clock + 2, clock + 3].map(|pin| &state.inputs[pin]);
+    let enabled = match enable.get_bit_state(0) {
+        BitState::HighZ => Value::new(1, 1),
+        _ => enable.clone(),
+    };
+    let triggered = match state.edge(clock, trigger) {
+        Some(true) => enabled,
+        Some(false) => Value::new(0, 1),
+        None => &enabled & &Value::unknown(1),
+    };
+    if triggered.get_bit_state(0) != BitState::Logic0 {
+        let next = next(&stored, &state.inputs[..data_inputs]);
+        stored = choose(&triggered, next, stored);
+    }
+
+    let active = |pin: &Value| match pin.get_bit_state(0) {
+        BitState::HighZ => Value::new(0, 1),
+        _ => pin.clone(),
+    };
+    stored = choose(&active(preset), ones, stored);
+    stored = choose(&active(reset), zero, stored);
+
+    state.metadata.insert(STATE_KEY.to_string(), stored.clone());
+    stored
+}
+
+/// Single-bit edge-triggered flip-flop with Q and inverted Q outputs
+#[derive(Debug, Clone)]
+pub struct FlipFlop {
+    kind: FlipFlopKind,
+    trigger: Edge,
+}
+
+impl FlipFlop {
+    pub fn new(kind: FlipFlopKind, trigger: Edge) -> Self {
+        Self { kind, trigger }
+    }
+}
+
+impl NodeLogic for FlipFlop {
+    fn compute(&self, state: &mut NodeState) {
+        let data_inputs = self.kind.data_inputs();
+        let q = clock_state(state, 1, data_inputs, self.trigger, |q, data| {
+            self.kind.next(q, data)
+        });
+        state.outputs[1] = !&q;
+        state.outputs[0] = q;
+    }
+
+    fn name(&self) -> &str {
+        match self.kind {
+            FlipFlopKind::D => "D Flip-Flop",
+            FlipFlopKind::T => "T Flip-Flop",
+            FlipFlopKind::JK => "JK Flip-Flop",
+            FlipFlopKind::SR => "SR Flip-Flop",
+        }
+    }
+
+    fn input_count(&self) -> usize {
+        self.kind.data_inputs() + 4
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        1
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        1
+    }
+}
+
+/// Multi-bit register: a D flip-flop per bit sharing one clock, enable, reset and preset
+#[derive(Debug, Clone)]
+pub struct Register {
+    bit_width: u8,
+    trigger: Edge,
+}
+
+impl Register {
+    pub fn new(bit_width: u8, trigger: Edge) -> Self {
+        Self { bit_width, trigger }
+    }
+}
+
+impl NodeLogic for Register {
+    fn compute(&self, state: &mut NodeState) {
+        let ones = !&Value::new(0, self.bit_width);
+        state.outputs[0] = clock_state(state, self.bit_width, 1, self.trigger, |_, data| {
+            &data[0] & &ones
+        });
+    }
+
+    fn name(&self) -> &str {
+        "Register"
+    }
+
+    fn input_count(&self) -> usize {
+        5
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 { self.bit_width } else { 1 }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Evaluate `logic` with new input values, the way the simulator does
+    fn evaluate(logic: &dyn NodeLogic, state: &mut NodeState, inputs: &[Value]) {
+        state.previous_inputs = std::mem::replace(&mut state.inputs, inputs.to_vec());
+        logic.compute(state);
+    }
+
+    fn bits(values: &[u32]) -> Vec<Value> {
+        values.iter().map(|&v| Value::new(v, 1)).collect()
+    }
+
+    #[test]
+    fn d_flip_flop_samples_on_the_rising_edge() {
+        let logic = FlipFlop::new(FlipFlopKind::D, Edge::Rising);
+        let mut state = NodeState::new(&logic);
+        // D, clock, enable, reset, preset
+        evaluate(&logic, &mut state, &bits(&[1, 0, 1, 0, 0]));
+        assert_eq!(state.outputs, bits(&[0, 1]));
+        evaluate(&logic, &mut state, &bits(&[1, 1, 1, 0, 0]));
+        assert_eq!(state.outputs, bits(&[1, 0]));
+
+        // Data changes without a clock edge are ignored, as are falling edges
+        evaluate(&logic, &mut state, &bits(&[0, 1, 1, 0, 0]));
+        evaluate(&logic, &mut state, &bits(&[0, 0, 1, 0, 0]));
+        assert_eq!(state.outputs[0], Value::new(1, 1));
+
+        // Disabled, the edge does nothing; reset acts without one
+        evaluate(&logic, &mut state, &bits(&[0, 1, 0, 0, 0]));
+        assert_eq!(state.outputs[0], Value::new(1, 1));
+        evaluate(&logic, &mut state, &bits(&[0, 1, 0, 1, 0]));
+        assert_eq!(state.outputs[0], Value::new(0, 1));
+    }
+
+    #[test]
+    fn unknown_clock_only_affects_bits_that_would_change() {
+        let logic = Register::new(4, Edge::Rising);
+        let mut state = NodeState::new(&logic);
+        let inputs = |d: u32, clock: Value| {
+            let mut inputs = vec![Value::new(d, 4), clock];
+            inputs.extend(bits(&[1, 0, 0]));
+            inputs
+        };
+        evaluate(&logic, &mut state, &inputs(0b0011, Value::new(0, 1)));
+        evaluate(&logic, &mut state, &inputs(0b0011, Value::new(1, 1)));
+        assert_eq!(state.outputs[0], Value::new(0b0011, 4));
+
+        // The clock might have risen: bits loading the same value stay known
+        evaluate(&logic, &mut state, &inputs(0b0101, Value::new(0, 1)));
+        evaluate(&logic, &mut state, &inputs(0b0101, Value::unknown(1)));
+        let q = &state.outputs[0];
+        assert_eq!(q.get_bit_state(0), BitState::Logic1);
+        assert_eq!(q.get_bit_state(1), BitState::Unknown);
+        assert_eq!(q.get_bit_state(2), BitState::Unknown);
+        assert_eq!(q.get_bit_state(3), BitState::Logic0);
+    }
+
+    #[test]
+    fn jk_flip_flop_toggles() {
+        let logic = FlipFlop::new(FlipFlopKind::JK, Edge::Falling);
+        let mut state = NodeState::new(&logic);
+        for expected in [1, 0, 1] {
+            // J, K, clock, enable, reset, preset
+            evaluate(&logic, &mut state, &bits(&[1, 1, 1, 1, 0, 0]));
+            evaluate(&logic, &mut state, &bits(&[1, 1, 0, 1, 0, 0]));
+            assert_eq!(state.outputs[0], Value::new(expected, 1));
+        }
+    }
+}
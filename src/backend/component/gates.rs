@@ -0,0 +1,301 @@
+use crate::node::{NodeLogic, NodeState};
+use crate::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GateKind {
+    And,
+    Or,
+    Nand,
+    Nor,
+    Xor,
+    Xnor,
+    Not,
+}
+
+impl GateKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::And => "AND Gate",
+            Self::Or => "OR Gate",
+            Self::Nand => "NAND Gate",
+            Self::Nor => "NOR Gate",
+            Self::Xor => "XOR Gate",
+            Self::Xnor => "XNOR Gate",
+            Self::Not => "NOT Gate",
+        }
+    }
+}
+
+/// Bitwise logic gate with four-state semantics
+///
+/// Every input and the output share one bit width, and the gate operates on each bit
+/// independently. XOR and XNOR with more than two inputs compute odd parity (and its inverse),
+/// the same as chaining two-input gates.
+#[derive(Debug, Clone)]
+pub struct Gate {
+    kind: GateKind,
+    input_count: u8,
+    bit_width: u8,
+    negated_inputs: u32, // bit i set = input i has an inversion bubble
+}
+
+impl Gate {
+    pub const MIN_INPUTS: u8 = 2;
+    pub const MAX_INPUTS: u8 = 32;
+    pub const MAX_WIDTH: u8 = 32;
+
+    /// Create a gate. `input_count` is ignored for NOT gates, which always have one input.
+    pub fn new(kind: GateKind, input_count: u8, bit_width: u8) -> Self {
+        let input_count = if kind == GateKind::Not {
+            1
+        } else {
+            input_count
+        };
+        assert!(
+            kind == GateKind::Not || (Self::MIN_INPUTS..=Self::MAX_INPUTS).contains(&input_count),
+            "gates take between {} and {} inputs",
+            Self::MIN_INPUTS,
+            Self::MAX_INPUTS
+        );
+        assert!(
+            (1..=Self::MAX_WIDTH).contains(&bit_width),
+            "gate width must be between 1 and {}",
+            Self::MAX_WIDTH
+        );
+        Self {
+            kind,
+            input_count,
+            bit_width,
+            negated_inputs: 0,
+        }
+    }
+
+    /// Add inversion bubbles to the inputs whose bit is set in `mask`
+    pub fn with_negated_inputs(mut self, mask: u32) -> Self {
+        self.negated_inputs = mask & (u32::MAX >> (32 - self.input_count as u32));
+        self
+    }
+
+    pub fn kind(&self) -> GateKind {
+        self.kind
+    }
+
+    pub fn bit_width(&self) -> u8 {
+        self.bit_width
+    }
+
+    pub fn negated_inputs(&self) -> u32 {
+        self.negated_inputs
+    }
+
+    /// Input `pin` after applying its bubble
+    fn input(&self, state: &NodeState, pin: usize) -> Value {
+        let value = state.inputs[pin].clone();
+        if self.negated_inputs & (1 << pin) != 0 {
+            !value
+        } else {
+            value
+        }
+    }
+}
+
+impl NodeLogic for Gate {
+    fn compute(&self, state: &mut NodeState) {
+        let mut inputs = (0..self.input_count as usize).map(|pin| self.input(state, pin));
+        let first = inputs.next().expect("gates have at least one input");
+
+        let output = match self.kind {
+            GateKind::And => inputs.fold(first, |acc, v| acc & v),
+            GateKind::Or => inputs.fold(first, |acc, v| acc | v),
+            GateKind::Nand => !inputs.fold(first, |acc, v| acc & v),
+            GateKind::Nor => !inputs.fold(first, |acc, v| acc | v),
+            GateKind::Xor => inputs.fold(first, |acc, v| acc ^ v),
+            GateKind::Xnor => !inputs.fold(first, |acc, v| acc ^ v),
+            GateKind::Not => !first,
+        };
+        state.outputs[0] = output;
+    }
+
+    fn name(&self) -> &str {
+        self.kind.name()
+    }
+
+    fn input_count(&self) -> usize {
+        self.input_count as usize
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::BitState;
+
+    const STATES: [BitState; 4] = [
+        BitState::Logic0,
+        BitState::Logic1,
+        BitState::HighZ,
+        BitState::Unknown,
+    ];
+
+    fn evaluate(gate: &Gate, inputs: &[Value]) -> Value {
+        let mut state = NodeState::new(gate);
+        state.inputs = inputs.to_vec();
+        gate.compute(&mut state);
+        state.outputs[0].clone()
+    }
+
+    fn bits(values: &[u32]) -> Vec<Value> {
+        values.iter().map(|&v| Value::new(v, 1)).collect()
+    }
+
+    fn value_of(states: &[BitState]) -> Value {
+        let mut value = Value::new(0, states.len() as u8);
+        for (bit, state) in states.iter().enumerate() {
+            let bit = bit as u8;
+            match state {
+                BitState::Logic0 => {}
+                BitState::Logic1 => value.set_logic_bit(bit, true),
+                BitState::HighZ => value.set_high_z_bit(bit),
+                BitState::Unknown => value.set_unknown_bit(bit),
+            }
+        }
+        value
+    }
+
+    /// Expected output bit for two known inputs, before any floating input turns it to X
+    fn truth(kind: GateKind, a: bool, b: bool) -> bool {
+        match kind {
+            GateKind::And => a & b,
+            GateKind::Or => a | b,
+            GateKind::Nand => !(a & b),
+            GateKind::Nor => !(a | b),
+            GateKind::Xor => a ^ b,
+            GateKind::Xnor => !(a ^ b),
+            GateKind::Not => !a,
+        }
+    }
+
+    #[test]
+    fn every_kind_on_every_state() {
+        let kinds = [
+            GateKind::And,
+            GateKind::Or,
+            GateKind::Nand,
+            GateKind::Nor,
+            GateKind::Xor,
+            GateKind::Xnor,
+        ];
+        // Every pair of states side by side in one 16-bit value, so each bit is its own case
+        let pairs: Vec<(BitState, BitState)> = STATES
+            .iter()
+            .flat_map(|&a| STATES.iter().map(move |&b| (a, b)))
+            .collect();
+        let lhs = value_of(&pairs.iter().map(|pair| pair.0).collect::<Vec<_>>());
+        let rhs = value_of(&pairs.iter().map(|pair| pair.1).collect::<Vec<_>>());
+        let known = |state: BitState| match state {
+            BitState::Logic0 => Some(false),
+            BitState::Logic1 => Some(true),
+            _ => None,
+        };
+
+        for kind in kinds {
+            let output = evaluate(&Gate::new(kind, 2, 16), &[lhs.clone(), rhs.clone()]);
+            for (bit, &(a, b)) in pairs.iter().enumerate() {
+                let expected = match (kind, known(a), known(b)) {
+                    (_, Some(a), Some(b)) => truth(kind, a, b),
+                    // A controlling input decides the output whatever the other one is
+                    (GateKind::And | GateKind::Nand, Some(false), _)
+                    | (GateKind::And | GateKind::Nand, _, Some(false)) => kind == GateKind::Nand,
+                    (GateKind::Or | GateKind::Nor, Some(true), _)
+                    | (GateKind::Or | GateKind::Nor, _, Some(true)) => kind == GateKind::Or,
+                    _ => {
+                        assert_eq!(output.get_bit_state(bit as u8), BitState::Unknown);
+                        continue;
+                    }
+                };
+                let expected = if expected {
+                    BitState::Logic1
+                } else {
+                    BitState::Logic0
+                };
+                assert_eq!(
+                    output.get_bit_state(bit as u8),
+                    expected,
+                    "{:?} of {:?} and {:?}",
+                    kind,
+                    a,
+                    b
+                );
+            }
+        }
+
+        let not = Gate::new(GateKind::Not, 1, 4);
+        assert_eq!(evaluate(&not, &[value_of(&STATES)]).to_string(), "XX01");
+    }
+
+    #[test]
+    fn wide_inputs_compute_parity() {
+        let xor = Gate::new(GateKind::Xor, 5, 1);
+        let xnor = Gate::new(GateKind::Xnor, 5, 1);
+        let nand = Gate::new(GateKind::Nand, 3, 1);
+        for inputs in [
+            [0, 0, 0, 0, 0],
+            [1, 0, 0, 0, 0],
+            [1, 1, 0, 1, 0],
+            [1, 1, 1, 1, 1],
+        ] {
+            let ones = inputs.iter().sum::<u32>();
+            assert_eq!(evaluate(&xor, &bits(&inputs)), Value::new(ones % 2, 1));
+            assert_eq!(evaluate(&xnor, &bits(&inputs)), Value::new(1 - ones % 2, 1));
+        }
+        assert_eq!(evaluate(&nand, &bits(&[1, 1, 1])), Value::new(0, 1));
+        assert_eq!(evaluate(&nand, &bits(&[1, 0, 1])), Value::new(1, 1));
+    }
+
+    #[test]
+    fn bubbles_invert_only_their_inputs() {
+        let and = Gate::new(GateKind::And, 2, 4).with_negated_inputs(0b01);
+        let inputs = [Value::new(0b0011, 4), Value::new(0b0110, 4)];
+        assert_eq!(evaluate(&and, &inputs), Value::new(0b0100, 4));
+
+        // Bits past the last input are dropped
+        let or = Gate::new(GateKind::Or, 3, 1).with_negated_inputs(u32::MAX);
+        assert_eq!(or.negated_inputs(), 0b111);
+        assert_eq!(evaluate(&or, &bits(&[1, 1, 1])), Value::new(0, 1));
+    }
+
+    #[test]
+    fn input_count_bounds() {
+        assert_eq!(
+            Gate::new(GateKind::And, Gate::MAX_INPUTS, 1).input_count(),
+            32
+        );
+        assert_eq!(Gate::new(GateKind::Not, 0, 1).input_count(), 1);
+        let all = Gate::new(GateKind::Or, 32, 1).with_negated_inputs(u32::MAX);
+        assert_eq!(all.negated_inputs(), u32::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "gates take between")]
+    fn too_few_inputs_panics() {
+        Gate::new(GateKind::And, 1, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "gates take between")]
+    fn too_many_inputs_panics() {
+        Gate::new(GateKind::Xor, Gate::MAX_INPUTS + 1, 1);
+    }
+}
@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+
+use crate::node::{NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Metadata key holding the value an `Input` drives
+pub const VALUE_KEY: &str = "value";
+
+/// External input of a circuit. Drives the value stored under `VALUE_KEY` in its metadata,
+/// or 0 until one is set.
+#[derive(Debug, Clone)]
+pub struct Input {
+    bit_width: u8,
+}
+
+impl Input {
+    pub fn new(bit_width: u8) -> Self {
+        Self { bit_width }
+    }
+}
+
+impl NodeLogic for Input {
+    fn compute(&self, state: &mut NodeState) {
+        state.outputs[0] = match state.metadata.get(VALUE_KEY) {
+            Some(value) if value.width() == self.bit_width => value.clone(),
+            _ => Value::new(0, self.bit_width),
+        };
+    }
+
+    fn name(&self) -> &str {
+        "Input Pin"
+    }
+
+    fn input_count(&self) -> usize {
+        0
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        0
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+}
+
+/// Value of an input after clicking bit `bit`: 0 becomes 1, and 1 goes back to 0, or to Z
+/// first on a three-state input. Floating and unknown bits become 0.
+pub fn toggle_bit(value: &Value, bit: u8, three_state: bool) -> Value {
+    let mut toggled = value.clone();
+    match value.get_bit_state(bit) {
+        BitState::Logic0 => toggled.set_logic_bit(bit, true),
+        BitState::Logic1 if three_state => toggled.set_high_z_bit(bit),
+        _ => toggled.set_logic_bit(bit, false),
+    }
+    toggled
+}
+
+/// External output of a circuit. Its value is whatever its input reads.
+#[derive(Debug, Clone)]
+pub struct Output {
+    bit_width: u8,
+}
+
+impl Output {
+    pub fn new(bit_width: u8) -> Self {
+        Self { bit_width }
+    }
+}
+
+impl NodeLogic for Output {
+    fn compute(&self, _state: &mut NodeState) {}
+
+    fn name(&self) -> &str {
+        "Output Pin"
+    }
+
+    fn input_count(&self) -> usize {
+        1
+    }
+
+    fn output_count(&self) -> usize {
+        0
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        0
+    }
+}
+
+/// Drives a fixed value
+#[derive(Debug, Clone)]
+pub struct Constant {
+    value: Value,
+}
+
+impl Constant {
+    /// Drive the low `bit_width` bits of `value`
+    pub fn new(value: u64, bit_width: u8) -> Self {
+        Self {
+            value: Value::from_u64(value, bit_width),
+        }
+    }
+}
+
+impl NodeLogic for Constant {
+    fn compute(&self, state: &mut NodeState) {
+        state.outputs[0] = self.value.clone();
+    }
+
+    fn name(&self) -> &str {
+        "Constant"
+    }
+
+    fn input_count(&self) -> usize {
+        0
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        0
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.value.width()
+    }
+}
+
+/// Shows the value of the net it is attached to. Unlike an `Output`, it is not a pin of the
+/// circuit.
+#[derive(Debug, Clone)]
+pub struct Probe {
+    bit_width: u8,
+}
+
+impl Probe {
+    pub fn new(bit_width: u8) -> Self {
+        Self { bit_width }
+    }
+}
+
+impl NodeLogic for Probe {
+    fn compute(&self, _state: &mut NodeState) {}
+
+    fn name(&self) -> &str {
+        "Probe"
+    }
+
+    fn input_count(&self) -> usize {
+        1
+    }
+
+    fn output_count(&self) -> usize {
+        0
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.bit_width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        0
+    }
+}
+
+/// How a probe writes out the value it shows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Radix {
+    #[default]
+    Binary,
+    Hex,
+    Decimal,
+    /// Decimal, reading the value as two's complement
+    Signed,
+}
+
+impl Radix {
+    pub const ALL: [Radix; 4] = [Self::Binary, Self::Hex, Self::Decimal, Self::Signed];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Binary => "Binary",
+            Self::Hex => "Hex",
+            Self::Decimal => "Decimal",
+            Self::Signed => "Signed",
+        }
+    }
+
+    /// `value` written out in this radix. Decimal values with an unknown bit show as X, and
+    /// ones with a floating bit as Z.
+    pub fn format(&self, value: &Value) -> String {
+        match self {
+            Self::Binary => value.to_string(),
+            Self::Hex => hex(value),
+            _ if value.has_unknown() => "X".to_string(),
+            _ if value.has_high_z() => "Z".to_string(),
+            Self::Decimal => decimal(value, false),
+            Self::Signed => decimal(value, true),
+        }
+    }
+
+    /// Most characters `format` gives for a value `width` bits wide
+    pub fn max_len(&self, width: u8) -> usize {
+        match self {
+            Self::Binary => width as usize,
+            Self::Hex => (width as usize).div_ceil(4),
+            // Each bit adds log10(2) digits, plus one for the sign
+            Self::Decimal => (width as usize * 30103).div_ceil(100000),
+            Self::Signed => (width as usize * 30103).div_ceil(100000) + 1,
+        }
+    }
+}
+
+/// Hex digits, most significant first. A digit with an unknown bit shows X, and one with
+/// a floating bit Z.
+fn hex(value: &Value) -> String {
+    let digits = (value.width() as u32).div_ceil(4);
+    (0..digits)
+        .rev()
+        .map(|digit| {
+            let bits = digit * 4..(digit * 4 + 4).min(value.width() as u32);
+            let states: Vec<BitState> = bits.map(|bit| value.get_bit_state(bit as u8)).collect();
+            if states.contains(&BitState::Unknown) {
+                'X'
+            } else if states.contains(&BitState::HighZ) {
+                'Z'
+            } else {
+                let nibble = states.iter().rev().fold(0, |acc, state| {
+                    acc << 1 | (*state == BitState::Logic1) as u32
+                });
+                char::from_digit(nibble, 16)
+                    .unwrap_or('?')
+                    .to_ascii_uppercase()
+            }
+        })
+        .collect()
+}
+
+/// Decimal digits of a logic value of any width, with a minus sign if `signed` and its top
+/// bit is set
+fn decimal(value: &Value, signed: bool) -> String {
+    let width = value.width();
+    let mut bits: Vec<bool> = (0..width)
+        .map(|bit| value.get_bit_state(bit) == BitState::Logic1)
+        .collect();
+    let negative = signed && bits.last() == Some(&true);
+    if negative {
+        // Two's complement: invert, then add one
+        let mut carry = true;
+        for bit in &mut bits {
+            let sum = !*bit;
+            *bit = sum ^ carry;
+            carry &= sum;
+        }
+    }
+
+    // Base 10^9 limbs, least significant first, doubled once per bit from the top
+    const LIMB: u64 = 1_000_000_000;
+    let mut limbs = vec![0u64];
+    for &bit in bits.iter().rev() {
+        let mut carry = bit as u64;
+        for limb in &mut limbs {
+            let doubled = *limb * 2 + carry;
+            *limb = doubled % LIMB;
+            carry = doubled / LIMB;
+        }
+        if carry > 0 {
+            limbs.push(carry);
+        }
+    }
+    let mut digits = if negative {
+        "-".to_string()
+    } else {
+        String::new()
+    };
+    let mut limbs = limbs.iter().rev();
+    if let Some(top) = limbs.next() {
+        digits += &top.to_string();
+    }
+    for limb in limbs {
+        digits += &format!("{limb:09}");
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_cycles_through_three_states() {
+        let value = Value::new(0b10, 2);
+        assert_eq!(toggle_bit(&value, 0, false), Value::new(0b11, 2));
+        assert_eq!(toggle_bit(&value, 1, false), Value::new(0b00, 2));
+        let floating = toggle_bit(&value, 1, true);
+        assert_eq!(floating.to_string(), "Z0");
+        assert_eq!(toggle_bit(&floating, 1, true), Value::new(0b00, 2));
+    }
+
+    #[test]
+    fn probes_write_values_in_each_radix() {
+        let value = Value::new(0xF6, 8);
+        assert_eq!(Radix::Binary.format(&value), "11110110");
+        assert_eq!(Radix::Hex.format(&value), "F6");
+        assert_eq!(Radix::Decimal.format(&value), "246");
+        assert_eq!(Radix::Signed.format(&value), "-10");
+        assert_eq!(Radix::Signed.format(&Value::new(0x7F, 8)), "127");
+        assert_eq!(Radix::Decimal.format(&Value::from_masks(1, 2, 0, 2)), "Z");
+        assert_eq!(Radix::Signed.format(&Value::new(0, 0)), "0");
+
+        // Wider than a machine word
+        let mut wide = Value::new(0, 100);
+        wide.set_logic_bit(99, true);
+        assert_eq!(
+            Radix::Decimal.format(&wide),
+            "633825300114114700748351602688"
+        );
+        assert_eq!(
+            Radix::Signed.format(&wide),
+            "-633825300114114700748351602688"
+        );
+        for radix in Radix::ALL {
+            assert!(radix.format(&wide).len() <= radix.max_len(100));
+        }
+    }
+
+    #[test]
+    fn hex_digits() {
+        assert_eq!(hex(&Value::new(0x1a5, 12)), "1A5");
+        assert_eq!(hex(&Value::new(0x1f, 5)), "1F");
+        let mut value = Value::new(0xff, 8);
+        value.set_high_z_bit(0);
+        assert_eq!(hex(&value), "FZ");
+        value.set_unknown_bit(7);
+        assert_eq!(hex(&value), "XZ");
+    }
+}
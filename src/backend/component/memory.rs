@@ -0,0 +1,460 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::node::{Edge, NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Widest address a memory may have, giving 64Ki words
+pub const MAX_ADDRESS_WIDTH: u8 = 16;
+
+/// Widest word a memory may hold, since contents are stored as `u64`
+pub const MAX_DATA_WIDTH: u8 = 64;
+
+/// Settings of a RAM or ROM
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub address_width: u8,
+    /// RAM reads and writes data through one pin instead of separate input and output pins
+    pub shared_bus: bool,
+    /// RAM writes on the rising clock edge instead of whenever write enable is high
+    pub clocked: bool,
+    /// Words from address 0 up that the memory starts with; the rest start at 0
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub contents: Vec<u64>,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            address_width: 8,
+            shared_bus: false,
+            clocked: true,
+            contents: Vec::new(),
+        }
+    }
+}
+
+impl MemoryConfig {
+    /// Number of words the address can reach. Addresses wider than `MAX_ADDRESS_WIDTH` count
+    /// as that wide.
+    pub fn capacity(&self) -> usize {
+        1 << self.address_width.min(MAX_ADDRESS_WIDTH)
+    }
+}
+
+/// Word stored at `address` in a memory's state
+pub fn read_word(state: &NodeState, address: u64, width: u8) -> Value {
+    state
+        .words
+        .get(&address)
+        .cloned()
+        .unwrap_or_else(|| Value::new(0, width))
+}
+
+pub fn write_word(state: &mut NodeState, address: u64, value: Value) {
+    state.words.insert(address, value);
+}
+
+fn load_contents(state: &mut NodeState, contents: &[u64], width: u8) {
+    for (address, &word) in contents.iter().enumerate() {
+        if word != 0 {
+            write_word(state, address as u64, Value::from_u64(word, width));
+        }
+    }
+}
+
+/// Read-only memory. Outputs the word at its address input, or X for an unknown address.
+#[derive(Debug, Clone)]
+pub struct Rom {
+    address_width: u8,
+    data_width: u8,
+    contents: Vec<u64>,
+}
+
+impl Rom {
+    pub fn new(config: &MemoryConfig, data_width: u8) -> Self {
+        Self {
+            address_width: config.address_width,
+            data_width,
+            contents: config.contents.clone(),
+        }
+    }
+}
+
+impl NodeLogic for Rom {
+    fn init(&self, state: &mut NodeState) {
+        load_contents(state, &self.contents, self.data_width);
+    }
+
+    fn compute(&self, state: &mut NodeState) {
+        state.outputs[0] = match state.inputs[0].as_u64() {
+            Some(address) => read_word(state, address, self.data_width),
+            None => Value::unknown(self.data_width),
+        };
+    }
+
+    fn name(&self) -> &str {
+        "ROM"
+    }
+
+    fn input_count(&self) -> usize {
+        1
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        self.address_width
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.data_width
+    }
+}
+
+/// Read-write memory
+///
+/// Inputs are address, data, write enable and, when clocked, the clock. Reads are
+/// asynchronous. With a shared bus the data input and output sit on the same net, and the
+/// output floats while writing. An unconnected write enable counts as inactive; a write that
+/// might happen turns the bits it might change to X, and one to an unknown address is dropped.
+#[derive(Debug, Clone)]
+pub struct Ram {
+    config: MemoryConfig,
+    data_width: u8,
+}
+
+impl Ram {
+    pub fn new(config: &MemoryConfig, data_width: u8) -> Self {
+        Self {
+            config: config.clone(),
+            data_width,
+        }
+    }
+}
+
+impl NodeLogic for Ram {
+    fn init(&self, state: &mut NodeState) {
+        load_contents(state, &self.config.contents, self.data_width);
+    }
+
+    fn compute(&self, state: &mut NodeState) {
+        let address = state.inputs[0].as_u64();
+        let enable = match state.inputs[2].get_bit_state(0) {
+            BitState::HighZ => BitState::Logic0,
+            enable => enable,
+        };
+        let write = if !self.config.clocked || enable == BitState::Logic0 {
+            enable
+        } else {
+            match state.edge(3, Edge::Rising) {
+                Some(true) => enable,
+                Some(false) => BitState::Logic0,
+                None => BitState::Unknown,
+            }
+        };
+
+        if let Some(address) = address {
+            let data = state.inputs[1].clone();
+            match write {
+                BitState::Logic0 => {}
+                BitState::Logic1 => write_word(state, address, data),
+                _ => {
+                    let old = read_word(state, address, self.data_width);
+                    write_word(state, address, Value::resolve(&[old, data]));
+                }
+            }
+        }
+
+        state.outputs[0] = match (self.config.shared_bus, enable, address) {
+            (true, BitState::Logic1, _) => Value::high_z(self.data_width),
+            (true, BitState::Unknown, _) | (_, _, None) => Value::unknown(self.data_width),
+            (_, _, Some(address)) => read_word(state, address, self.data_width),
+        };
+    }
+
+    fn name(&self) -> &str {
+        "RAM"
+    }
+
+    fn input_count(&self) -> usize {
+        if self.config.clocked { 4 } else { 3 }
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        match pin {
+            0 => self.config.address_width,
+            1 => self.data_width,
+            _ => 1,
+        }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.data_width
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// Neither a Logisim `v2.0 raw` file nor Intel HEX
+    UnknownFormat,
+    /// Line that could not be read, counting from 1
+    Syntax(usize),
+    /// Intel HEX record whose checksum does not match, counting lines from 1
+    Checksum(usize),
+    /// More words than the memory can hold
+    TooLarge { capacity: usize },
+    /// Word width that is zero or more than `MAX_DATA_WIDTH`
+    DataWidth(u8),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat => write!(f, "not a Logisim v2.0 raw or Intel HEX file"),
+            Self::Syntax(line) => write!(f, "unreadable data on line {}", line),
+            Self::Checksum(line) => write!(f, "checksum mismatch on line {}", line),
+            Self::TooLarge { capacity } => {
+                write!(f, "image does not fit in {} words", capacity)
+            }
+            Self::DataWidth(width) => write!(
+                f,
+                "{}-bit words are not between 1 and {} bits",
+                width, MAX_DATA_WIDTH
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Read a memory image in Logisim `v2.0 raw` or Intel HEX format into words from address 0.
+/// Words are masked to `data_width` bits.
+///
+/// Intel HEX addresses bytes, so each word takes the next `data_width / 8` bytes (rounded up),
+/// most significant first.
+pub fn parse_image(text: &str, data_width: u8, capacity: usize) -> Result<Vec<u64>, ImageError> {
+    if !(1..=MAX_DATA_WIDTH).contains(&data_width) {
+        return Err(ImageError::DataWidth(data_width));
+    }
+    let first = text.lines().map(str::trim).find(|line| !line.is_empty());
+    let mut words = match first {
+        Some("v2.0 raw") => parse_logisim_raw(text, capacity)?,
+        Some(line) if line.starts_with(':') => parse_intel_hex(text, data_width, capacity)?,
+        _ => return Err(ImageError::UnknownFormat),
+    };
+    let mask = u64::MAX >> (64 - data_width as u32);
+    for word in &mut words {
+        *word &= mask;
+    }
+    Ok(words)
+}
+
+/// Hex words separated by whitespace after the header, where `n*word` repeats a word `n`
+/// times and `#` starts a comment
+fn parse_logisim_raw(text: &str, capacity: usize) -> Result<Vec<u64>, ImageError> {
+    let mut words = Vec::new();
+    let lines = text
+        .lines()
+        .enumerate()
+        .skip_while(|(_, line)| line.trim().is_empty());
+    for (index, line) in lines.skip(1) {
+        let line = line.split('#').next().unwrap_or_default();
+        for token in line.split_whitespace() {
+            let (count, word) = match token.split_once('*') {
+                Some((count, word)) => (count.parse().ok(), word),
+                None => (Some(1), token),
+            };
+            let (Some(count), Ok(word)) = (count, u64::from_str_radix(word, 16)) else {
+                return Err(ImageError::Syntax(index + 1));
+            };
+            // Never more than `capacity` words, so this cannot overflow
+            if count > capacity - words.len() {
+                return Err(ImageError::TooLarge { capacity });
+            }
+            words.extend(std::iter::repeat_n(word, count));
+        }
+    }
+    Ok(words)
+}
+
+fn parse_intel_hex(text: &str, data_width: u8, capacity: usize) -> Result<Vec<u64>, ImageError> {
+    let word_bytes = data_width.div_ceil(8).max(1) as usize;
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut base = 0usize;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let syntax = ImageError::Syntax(index + 1);
+        let hex = line.strip_prefix(':').ok_or(syntax.clone())?;
+        if hex.len() % 2 != 0 || !hex.is_ascii() {
+            return Err(syntax);
+        }
+        let record: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| syntax.clone())?;
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(syntax);
+        }
+        if record.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return Err(ImageError::Checksum(index + 1));
+        }
+
+        let address = u16::from_be_bytes([record[1], record[2]]) as usize;
+        let data = &record[4..record.len() - 1];
+        match record[3] {
+            // Data
+            0x00 => {
+                let start = base + address;
+                let end = start + data.len();
+                if end > capacity * word_bytes {
+                    return Err(ImageError::TooLarge { capacity });
+                }
+                if bytes.len() < end {
+                    bytes.resize(end, 0);
+                }
+                bytes[start..end].copy_from_slice(data);
+            }
+            // End of file
+            0x01 => break,
+            // Extended segment and linear addresses
+            0x02 | 0x04 if data.len() == 2 => {
+                let upper = u16::from_be_bytes([data[0], data[1]]) as usize;
+                base = if record[3] == 0x02 {
+                    upper << 4
+                } else {
+                    upper << 16
+                };
+            }
+            // Start addresses mean nothing to a memory
+            0x03 | 0x05 => {}
+            _ => return Err(syntax),
+        }
+    }
+
+    bytes.resize(bytes.len().next_multiple_of(word_bytes), 0);
+    Ok(bytes
+        .chunks(word_bytes)
+        .map(|word| word.iter().fold(0u64, |acc, byte| acc << 8 | *byte as u64))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(clocked: bool, shared_bus: bool) -> MemoryConfig {
+        MemoryConfig {
+            address_width: 4,
+            shared_bus,
+            clocked,
+            contents: vec![0x12, 0x34],
+        }
+    }
+
+    /// Evaluate `logic` with new input values, the way the simulator does
+    fn evaluate(logic: &dyn NodeLogic, state: &mut NodeState, inputs: &[Value]) {
+        state.previous_inputs = std::mem::replace(&mut state.inputs, inputs.to_vec());
+        logic.compute(state);
+    }
+
+    #[test]
+    fn capacity_is_bounded() {
+        let mut config = config(false, false);
+        assert_eq!(config.capacity(), 16);
+        config.address_width = 200;
+        assert_eq!(config.capacity(), 1 << MAX_ADDRESS_WIDTH);
+    }
+
+    #[test]
+    fn rom_reads_its_contents() {
+        let rom = Rom::new(&config(true, false), 8);
+        let mut state = NodeState::new(&rom);
+        evaluate(&rom, &mut state, &[Value::new(1, 4)]);
+        assert_eq!(state.outputs[0], Value::new(0x34, 8));
+        evaluate(&rom, &mut state, &[Value::new(5, 4)]);
+        assert_eq!(state.outputs[0], Value::new(0, 8));
+        evaluate(&rom, &mut state, &[Value::unknown(4)]);
+        assert_eq!(state.outputs[0], Value::unknown(8));
+    }
+
+    #[test]
+    fn clocked_ram_writes_on_the_rising_edge() {
+        let ram = Ram::new(&config(true, false), 8);
+        let mut state = NodeState::new(&ram);
+        let inputs = |data: u32, enable: u32, clock: u32| {
+            [
+                Value::new(2, 4),
+                Value::new(data, 8),
+                Value::new(enable, 1),
+                Value::new(clock, 1),
+            ]
+        };
+        evaluate(&ram, &mut state, &inputs(0xab, 1, 0));
+        assert_eq!(state.outputs[0], Value::new(0, 8));
+        evaluate(&ram, &mut state, &inputs(0xab, 1, 1));
+        assert_eq!(state.outputs[0], Value::new(0xab, 8));
+        // No edge, or no write enable, leaves the word alone
+        evaluate(&ram, &mut state, &inputs(0xcd, 1, 1));
+        evaluate(&ram, &mut state, &inputs(0xcd, 0, 0));
+        evaluate(&ram, &mut state, &inputs(0xcd, 0, 1));
+        assert_eq!(state.outputs[0], Value::new(0xab, 8));
+    }
+
+    #[test]
+    fn shared_bus_floats_while_writing() {
+        let ram = Ram::new(&config(false, true), 8);
+        let mut state = NodeState::new(&ram);
+        let address = Value::new(0, 4);
+        evaluate(
+            &ram,
+            &mut state,
+            &[address.clone(), Value::new(0x77, 8), Value::new(1, 1)],
+        );
+        assert_eq!(state.outputs[0], Value::high_z(8));
+        evaluate(
+            &ram,
+            &mut state,
+            &[address, Value::high_z(8), Value::new(0, 1)],
+        );
+        assert_eq!(state.outputs[0], Value::new(0x77, 8));
+    }
+
+    #[test]
+    fn images_load_from_both_formats() {
+        let raw = "v2.0 raw\n# comment\n1 2 3*ff\n4\n";
+        assert_eq!(parse_image(raw, 8, 16), Ok(vec![1, 2, 0xff, 0xff, 0xff, 4]));
+        assert_eq!(
+            parse_image(raw, 8, 4),
+            Err(ImageError::TooLarge { capacity: 4 })
+        );
+        let huge = format!("v2.0 raw\n1 {}*ff\n", usize::MAX);
+        assert_eq!(
+            parse_image(&huge, 8, 16),
+            Err(ImageError::TooLarge { capacity: 16 })
+        );
+
+        // Two 16-bit words at byte address 2, then end of file
+        let hex = ":040002000012ABCD70\n:00000001FF\n";
+        assert_eq!(parse_image(hex, 16, 16), Ok(vec![0, 0x12, 0xabcd]));
+        assert_eq!(
+            parse_image(&hex.replace("70", "71"), 16, 16),
+            Err(ImageError::Checksum(1))
+        );
+        assert_eq!(parse_image("hello", 8, 16), Err(ImageError::UnknownFormat));
+        assert_eq!(parse_image(raw, 0, 16), Err(ImageError::DataWidth(0)));
+        assert_eq!(parse_image(raw, 65, 16), Err(ImageError::DataWidth(65)));
+    }
+}
@@ -0,0 +1,11 @@
+// Built-in components implementing `NodeLogic`
+
+pub mod arithmetic;
+pub mod bus;
+pub mod clock;
+pub mod flip_flops;
+pub mod gates;
+pub mod io;
+pub mod memory;
+pub mod plexers;
+pub mod splitter;
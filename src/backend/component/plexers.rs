@@ -0,0 +1,399 @@
+use crate::node::{NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Widest select input a plexer may have, giving 32 ways
+pub const MAX_SELECT_WIDTH: u8 = 5;
+
+/// Bits needed to number `count` things, at least 1
+pub fn select_width_for(count: u8) -> u8 {
+    (u8::BITS - (count.max(2) - 1).leading_zeros()) as u8
+}
+
+/// Every value `select` might have, taking each X or Z bit as either 0 or 1
+fn possible_selections(select: &Value) -> Vec<usize> {
+    let mut options = vec![0];
+    for bit in 0..select.width() {
+        match select.get_bit_state(bit) {
+            BitState::Logic0 => {}
+            BitState::Logic1 => options.iter_mut().for_each(|option| *option |= 1 << bit),
+            BitState::HighZ | BitState::Unknown => {
+                let set: Vec<usize> = options.iter().map(|option| option | 1 << bit).collect();
+                options.extend(set);
+            }
+        }
+    }
+    options
+}
+
+fn bit_value(state: BitState) -> Value {
+    match state {
+        BitState::Logic0 => Value::new(0, 1),
+        BitState::Logic1 => Value::new(1, 1),
+        BitState::HighZ => Value::high_z(1),
+        BitState::Unknown => Value::unknown(1),
+    }
+}
+
+/// Passes through the data input picked by its select input
+///
+/// Inputs are the `2^select_width` data inputs, then select. An unknown select bit gives the
+/// bits every input it might pick agrees on, and X elsewhere.
+#[derive(Debug, Clone)]
+pub struct Multiplexer {
+    select_width: u8,
+    data_width: u8,
+}
+
+impl Multiplexer {
+    pub fn new(select_width: u8, data_width: u8) -> Self {
+        Self {
+            select_width,
+            data_width,
+        }
+    }
+
+    fn ways(&self) -> usize {
+        1 << self.select_width
+    }
+}
+
+impl NodeLogic for Multiplexer {
+    fn compute(&self, state: &mut NodeState) {
+        let options: Vec<Value> = possible_selections(&state.inputs[self.ways()])
+            .into_iter()
+            .map(|option| state.inputs[option].clone())
+            .collect();
+        state.outputs[0] = Value::merge(&options);
+    }
+
+    fn name(&self) -> &str {
+        "Multiplexer"
+    }
+
+    fn input_count(&self) -> usize {
+        self.ways() + 1
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == self.ways() {
+            self.select_width
+        } else {
+            self.data_width
+        }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.data_width
+    }
+}
+
+/// Sends its data input to the output picked by its select input; the other outputs are 0
+///
+/// Inputs are data and select. Outputs an unknown select might pick carry the bits data has
+/// in common with 0, and X elsewhere.
+#[derive(Debug, Clone)]
+pub struct Demultiplexer {
+    select_width: u8,
+    data_width: u8,
+}
+
+impl Demultiplexer {
+    pub fn new(select_width: u8, data_width: u8) -> Self {
+        Self {
+            select_width,
+            data_width,
+        }
+    }
+}
+
+impl NodeLogic for Demultiplexer {
+    fn compute(&self, state: &mut NodeState) {
+        let options = possible_selections(&state.inputs[1]);
+        let zero = Value::new(0, self.data_width);
+        let data = match options.len() {
+            1 => state.inputs[0].clone(),
+            _ => Value::merge(&[state.inputs[0].clone(), zero.clone()]),
+        };
+        for (pin, output) in state.outputs.iter_mut().enumerate() {
+            *output = if options.contains(&pin) {
+                data.clone()
+            } else {
+                zero.clone()
+            };
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Demultiplexer"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        1 << self.select_width
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 {
+            self.data_width
+        } else {
+            self.select_width
+        }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        self.data_width
+    }
+}
+
+/// Sets the one output numbered by its select input while enabled
+///
+/// Inputs are select and enable. An unconnected enable counts as enabled. Outputs an unknown
+/// select or enable might set are X.
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    select_width: u8,
+}
+
+impl Decoder {
+    pub fn new(select_width: u8) -> Self {
+        Self { select_width }
+    }
+}
+
+impl NodeLogic for Decoder {
+    fn compute(&self, state: &mut NodeState) {
+        let options = possible_selections(&state.inputs[0]);
+        let set = match (state.inputs[1].get_bit_state(0), options.len()) {
+            (BitState::Logic0, _) => BitState::Logic0,
+            (BitState::Logic1 | BitState::HighZ, 1) => BitState::Logic1,
+            _ => BitState::Unknown,
+        };
+        for (pin, output) in state.outputs.iter_mut().enumerate() {
+            *output = if options.contains(&pin) {
+                bit_value(set)
+            } else {
+                Value::new(0, 1)
+            };
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Decoder"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        1 << self.select_width
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 { self.select_width } else { 1 }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        1
+    }
+}
+
+/// Outputs the number of its highest set input, and whether any input is set
+///
+/// Unconnected inputs count as 0. An X input above the highest set one makes the bits of the
+/// outputs that depend on it X. With no input set the number is 0.
+#[derive(Debug, Clone)]
+pub struct PriorityEncoder {
+    select_width: u8,
+}
+
+impl PriorityEncoder {
+    pub fn new(select_width: u8) -> Self {
+        Self { select_width }
+    }
+}
+
+impl NodeLogic for PriorityEncoder {
+    fn compute(&self, state: &mut NodeState) {
+        // Inputs that might be the highest one set, or `None` for none at all
+        let mut candidates = Vec::new();
+        let mut found = false;
+        for pin in (0..state.inputs.len()).rev() {
+            match state.inputs[pin].get_bit_state(0) {
+                BitState::Logic0 | BitState::HighZ => {}
+                BitState::Logic1 => {
+                    candidates.push(Some(pin));
+                    found = true;
+                    break;
+                }
+                BitState::Unknown => candidates.push(Some(pin)),
+            }
+        }
+        if !found {
+            candidates.push(None);
+        }
+
+        let numbers: Vec<Value> = candidates
+            .iter()
+            .map(|pin| Value::new(pin.unwrap_or(0) as u32, self.select_width))
+            .collect();
+        let valid: Vec<Value> = candidates
+            .iter()
+            .map(|pin| Value::new(pin.is_some() as u32, 1))
+            .collect();
+        state.outputs[0] = Value::merge(&numbers);
+        state.outputs[1] = Value::merge(&valid);
+    }
+
+    fn name(&self) -> &str {
+        "Priority Encoder"
+    }
+
+    fn input_count(&self) -> usize {
+        1 << self.select_width
+    }
+
+    fn output_count(&self) -> usize {
+        2
+    }
+
+    fn input_width(&self, _pin: usize) -> u8 {
+        1
+    }
+
+    fn output_width(&self, pin: usize) -> u8 {
+        if pin == 0 { self.select_width } else { 1 }
+    }
+}
+
+/// Outputs the bit of its data input numbered by its select input, or 0 past the last bit
+///
+/// Inputs are data and select, which is as wide as it takes to number every data bit.
+#[derive(Debug, Clone)]
+pub struct BitSelector {
+    data_width: u8,
+}
+
+impl BitSelector {
+    pub fn new(data_width: u8) -> Self {
+        Self { data_width }
+    }
+}
+
+impl NodeLogic for BitSelector {
+    fn compute(&self, state: &mut NodeState) {
+        let data = &state.inputs[0];
+        let options: Vec<Value> = possible_selections(&state.inputs[1])
+            .into_iter()
+            .map(|bit| match u8::try_from(bit) {
+                Ok(bit) if bit < self.data_width => bit_value(data.get_bit_state(bit)),
+                _ => Value::new(0, 1),
+            })
+            .collect();
+        state.outputs[0] = Value::merge(&options);
+    }
+
+    fn name(&self) -> &str {
+        "Bit Selector"
+    }
+
+    fn input_count(&self) -> usize {
+        2
+    }
+
+    fn output_count(&self) -> usize {
+        1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        if pin == 0 {
+            self.data_width
+        } else {
+            select_width_for(self.data_width)
+        }
+    }
+
+    fn output_width(&self, _pin: usize) -> u8 {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(logic: &dyn NodeLogic, inputs: Vec<Value>) -> Vec<Value> {
+        let mut state = NodeState::new(logic);
+        state.inputs = inputs;
+        logic.compute(&mut state);
+        state.outputs
+    }
+
+    #[test]
+    fn unknown_select_merges_the_possible_inputs() {
+        let mux = Multiplexer::new(2, 4);
+        let data = [0b0001, 0b0011, 0b0111, 0b1111].map(|v| Value::new(v, 4));
+        let mut inputs = data.to_vec();
+        inputs.push(Value::new(2, 2));
+        assert_eq!(evaluate(&mux, inputs.clone()), vec![data[2].clone()]);
+
+        // Select 0b?1 picks input 1 or 3
+        let mut select = Value::new(1, 2);
+        select.set_unknown_bit(1);
+        inputs[4] = select;
+        assert_eq!(evaluate(&mux, inputs.clone())[0].to_string(), "XX11");
+
+        // A floating select could pick anything
+        inputs[4] = Value::high_z(2);
+        assert_eq!(evaluate(&mux, inputs)[0].to_string(), "XXX1");
+    }
+
+    #[test]
+    fn decoder_and_demultiplexer_follow_select() {
+        let decoder = Decoder::new(2);
+        let outputs = evaluate(&decoder, vec![Value::new(2, 2), Value::high_z(1)]);
+        let bits: Vec<String> = outputs.iter().map(Value::to_string).collect();
+        assert_eq!(bits, ["0", "0", "1", "0"]);
+        let outputs = evaluate(&decoder, vec![Value::new(2, 2), Value::new(0, 1)]);
+        assert!(outputs.iter().all(|bit| *bit == Value::new(0, 1)));
+
+        let demux = Demultiplexer::new(1, 4);
+        let outputs = evaluate(&demux, vec![Value::new(0b0101, 4), Value::unknown(1)]);
+        assert_eq!(outputs[0].to_string(), "0X0X");
+        assert_eq!(outputs[1].to_string(), "0X0X");
+    }
+
+    #[test]
+    fn priority_encoder_finds_the_highest_set_input() {
+        let encoder = PriorityEncoder::new(2);
+        let bits = |bits: [u32; 4]| bits.map(|bit| Value::new(bit, 1)).to_vec();
+        let outputs = evaluate(&encoder, bits([1, 0, 1, 0]));
+        assert_eq!(outputs, vec![Value::new(2, 2), Value::new(1, 1)]);
+        let outputs = evaluate(&encoder, bits([0, 0, 0, 0]));
+        assert_eq!(outputs, vec![Value::new(0, 2), Value::new(0, 1)]);
+
+        // Input 3 might be set: the number is 3 or 2
+        let mut inputs = bits([1, 0, 1, 0]);
+        inputs[3] = Value::unknown(1);
+        let outputs = evaluate(&encoder, inputs);
+        assert_eq!(outputs[0].to_string(), "1X");
+        assert_eq!(outputs[1], Value::new(1, 1));
+    }
+
+    #[test]
+    fn bit_selector_picks_one_bit() {
+        let selector = BitSelector::new(5);
+        assert_eq!(selector.input_width(1), 3);
+        let outputs = evaluate(&selector, vec![Value::new(0b10000, 5), Value::new(4, 3)]);
+        assert_eq!(outputs[0], Value::new(1, 1));
+        let outputs = evaluate(&selector, vec![Value::new(0b11111, 5), Value::new(6, 3)]);
+        assert_eq!(outputs[0], Value::new(0, 1));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::node::{NodeLogic, NodeState};
+use crate::value::{BitState, Value};
+
+/// Fan of each bus bit when `width` bits are split as evenly as possible over `fans` fans,
+/// lowest bits to the first fan
+pub fn even_split(width: u8, fans: u8) -> Vec<Option<u8>> {
+    let fans = fans.max(1) as usize;
+    (0..width as usize)
+        .map(|bit| Some((bit * fans / width as usize) as u8))
+        .collect()
+}
+
+fn copy_bit(from: &Value, from_bit: u8, to: &mut Value, to_bit: u8) {
+    match from.get_bit_state(from_bit) {
+        BitState::Logic0 => to.set_logic_bit(to_bit, false),
+        BitState::Logic1 => to.set_logic_bit(to_bit, true),
+        BitState::HighZ => to.set_high_z_bit(to_bit),
+        BitState::Unknown => to.set_unknown_bit(to_bit),
+    }
+}
+
+/// Wires the bits of a bus to narrower fans, in both directions
+///
+/// Pin 0 is the bus and pin `1 + k` is fan `k`; each pin is both an input and an output on
+/// the same net. Each fan carries the bus bits mapped to it, lowest first. Every output
+/// drives what the other side's drivers put on the matching bits, so Z and X pass through
+/// either way. Bus bits mapped to no fan, and fans with no bits, are left floating.
+#[derive(Debug, Clone)]
+pub struct Splitter {
+    width: u8,
+    /// Fan and bit within it for each bus bit
+    slots: Vec<Option<(usize, u8)>>,
+    fan_widths: Vec<u8>,
+}
+
+impl Splitter {
+    /// `bit_fans` gives the fan of each bus bit; fans past `fans` count as none
+    pub fn new(bit_fans: &[Option<u8>], fans: usize) -> Self {
+        let mut fan_widths = vec![0u8; fans];
+        let slots = bit_fans
+            .iter()
+            .map(|fan| {
+                let fan = fan.map(usize::from).filter(|&fan| fan < fans)?;
+                fan_widths[fan] += 1;
+                Some((fan, fan_widths[fan] - 1))
+            })
+            .collect();
+        Self {
+            width: bit_fans.len() as u8,
+            slots,
+            fan_widths,
+        }
+    }
+
+    fn pin_width(&self, pin: usize) -> u8 {
+        // A fan with no bits still needs a width for its pin
+        match pin {
+            0 => self.width,
+            fan => self.fan_widths[fan - 1].max(1),
+        }
+    }
+
+    fn floating(&self) -> Vec<Value> {
+        (0..self.fan_widths.len() + 1)
+            .map(|pin| Value::high_z(self.pin_width(pin)))
+            .collect()
+    }
+}
+
+impl NodeLogic for Splitter {
+    fn compute(&self, state: &mut NodeState) {
+        let mut outputs = self.floating();
+        for (bit, slot) in self.slots.iter().enumerate() {
+            let Some((fan, fan_bit)) = *slot else {
+                continue;
+            };
+            let bit = bit as u8;
+            copy_bit(&state.inputs[0], bit, &mut outputs[fan + 1], fan_bit);
+            copy_bit(&state.inputs[fan + 1], fan_bit, &mut outputs[0], bit);
+        }
+        state.outputs = outputs;
+    }
+
+    fn name(&self) -> &str {
+        "Splitter"
+    }
+
+    fn input_count(&self) -> usize {
+        self.fan_widths.len() + 1
+    }
+
+    fn output_count(&self) -> usize {
+        self.fan_widths.len() + 1
+    }
+
+    fn input_width(&self, pin: usize) -> u8 {
+        self.pin_width(pin)
+    }
+
+    fn output_width(&self, pin: usize) -> u8 {
+        self.pin_width(pin)
+    }
+
+    /// Drive nothing until the first evaluation, rather than X
+    fn init(&self, state: &mut NodeState) {
+        state.outputs = self.floating();
+    }
+
+    fn is_bidirectional(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{Circuit, InputPin, NetId, OutputPin};
+    use crate::component::io::{Input, VALUE_KEY};
+    use crate::node::NodeId;
+    use crate::simulation::Simulator;
+
+    #[test]
+    fn bits_go_to_their_fans() {
+        assert_eq!(
+            even_split(5, 2),
+            vec![Some(0), Some(0), Some(0), Some(1), Some(1)]
+        );
+        let splitter = Splitter::new(&[Some(1), None, Some(0), Some(1)], 2);
+        assert_eq!(splitter.input_width(1), 1);
+        assert_eq!(splitter.input_width(2), 2);
+
+        let mut state = NodeState::new(&splitter);
+        state.inputs[0] = Value::from_masks(0b1001, 0, 0b0100, 4);
+        splitter.compute(&mut state);
+        assert_eq!(state.outputs[0], Value::high_z(4));
+        assert_eq!(state.outputs[1], Value::unknown(1));
+        assert_eq!(state.outputs[2], Value::new(0b11, 2));
+    }
+
+    /// Input node of `width` bits driving a new net, initially floating
+    fn driver(circuit: &mut Circuit, net: NetId, width: u8) -> NodeId {
+        let input = circuit.add_input(Box::new(Input::new(width)));
+        circuit
+            .attach_output(net, OutputPin::new(input, 0))
+            .unwrap();
+        let state = &mut circuit.node_mut(input).unwrap().state;
+        state
+            .metadata
+            .insert(VALUE_KEY.to_string(), Value::high_z(width));
+        input
+    }
+
+    #[test]
+    fn signals_pass_both_ways() {
+        let mut circuit = Circuit::new();
+        let splitter = circuit.add_node(Box::new(Splitter::new(&even_split(4, 2), 2)));
+        let nets: Vec<NetId> = [4, 2, 2].map(|width| circuit.add_net(width)).to_vec();
+        let mut drivers = Vec::new();
+        for (pin, (&net, width)) in nets.iter().zip([4, 2, 2]).enumerate() {
+            circuit
+                .attach_input(net, InputPin::new(splitter, pin))
+                .unwrap();
+            circuit
+                .attach_output(net, OutputPin::new(splitter, pin))
+                .unwrap();
+            drivers.push(driver(&mut circuit, net, width));
+        }
+
+        let mut simulator = Simulator::new(circuit);
+        let drive = |simulator: &mut Simulator, pin: usize, value: Value| {
+            let id = drivers[pin];
+            let state = &mut simulator.circuit_mut().node_mut(id).unwrap().state;
+            state.metadata.insert(VALUE_KEY.to_string(), value);
+            simulator.schedule(id);
+            simulator.settle().unwrap();
+        };
+
+        // Bus to fans
+        drive(&mut simulator, 0, Value::new(0b1001, 4));
+        assert_eq!(simulator.net_value(nets[1]), Some(&Value::new(0b01, 2)));
+        assert_eq!(simulator.net_value(nets[2]), Some(&Value::new(0b10, 2)));
+
+        // Fans to bus, with the undriven fan floating through
+        drive(&mut simulator, 0, Value::high_z(4));
+        drive(&mut simulator, 2, Value::new(0b11, 2));
+        assert_eq!(simulator.net_value(nets[0]).unwrap().to_string(), "11ZZ");
+
+        // Both sides driving disagree
+        drive(&mut simulator, 0, Value::new(0b0100, 4));
+        assert_eq!(simulator.net_value(nets[0]).unwrap().to_string(), "X100");
+        assert_eq!(simulator.net_value(nets[2]).unwrap().to_string(), "X1");
+    }
+}
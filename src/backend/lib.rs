@@ -0,0 +1,6 @@
+pub mod circuit;
+pub mod component;
+pub mod node;
+pub mod schematic;
+pub mod simulation;
+pub mod value;
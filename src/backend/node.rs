@@ -1,72 +1,190 @@
-use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
+use std::collections::HashMap;
 
-use crate::backend::value::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::NetId;
+use crate::value::{BitState, Value};
 
 pub type NodeId = u32;
 
+/// Clock transition an edge-triggered component responds to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Edge {
+    #[default]
+    Rising,
+    Falling,
+}
+
+impl Edge {
+    /// Whether bit 0 going from `previous` to `current` is this edge: `Some(true)` if it
+    /// definitely is, `Some(false)` if it definitely isn't, and `None` if an X on either side
+    /// means it might be. A floating (Z) clock never triggers.
+    pub fn detect(self, previous: &Value, current: &Value) -> Option<bool> {
+        let (from, to) = match self {
+            Self::Rising => (BitState::Logic0, BitState::Logic1),
+            Self::Falling => (BitState::Logic1, BitState::Logic0),
+        };
+        let (previous, current) = (previous.get_bit_state(0), current.get_bit_state(0));
+        if previous == BitState::HighZ || current == BitState::HighZ {
+            return Some(false);
+        }
+        let could_start = previous == from || previous == BitState::Unknown;
+        let could_end = current == to || current == BitState::Unknown;
+        if previous == from && current == to {
+            Some(true)
+        } else if could_start && could_end {
+            None
+        } else {
+            Some(false)
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NodeState {
+    pub inputs: Vec<Value>,
+    pub previous_inputs: Vec<Value>, // inputs at the last evaluation, for edge detection
     pub outputs: Vec<Value>,
-    pub metadata: HashMap<String, Value>, // for components like RAM
+    pub metadata: HashMap<String, Value>, // for components like flip-flops
+    pub words: HashMap<u64, Value>, // words of a ROM or RAM by address; missing words hold 0
     pub input_hash: u64, // for oscillation detection
     pub generation: u32, // helps distinguish state changes
 }
 
 impl NodeState {
-    pub fn new(num_inputs: usize, num_outputs: usize) -> Self {
-        Self {
-            outputs: vec![Value::unknown(Value::BitWidth(1)); num_outputs],
+    /// Unconnected inputs start floating (Z), outputs start unknown (X). The logic then sets up
+    /// any internal state it needs.
+    pub fn new(logic: &dyn NodeLogic) -> Self {
+        let inputs: Vec<Value> = (0..logic.input_count())
+            .map(|pin| Value::high_z(logic.input_width(pin)))
+            .collect();
+        let mut state = Self {
+            previous_inputs: inputs.clone(),
+            inputs,
+            outputs: (0..logic.output_count())
+                .map(|pin| Value::unknown(logic.output_width(pin)))
+                .collect(),
             metadata: HashMap::new(),
+            words: HashMap::new(),
             input_hash: 0,
             generation: 0,
-        }
+        };
+        logic.init(&mut state);
+        state
+    }
+
+    /// Whether input `pin` just went through `edge`, as for `Edge::detect`
+    pub fn edge(&self, pin: usize, edge: Edge) -> Option<bool> {
+        edge.detect(&self.previous_inputs[pin], &self.inputs[pin])
     }
 }
 
 pub trait NodeLogic: Send + Sync {
-    fn compute(&self, state: &mut NodeState) -> Vec<NodeId>;
+    /// Recompute `state.outputs` from `state.inputs` (and any internal state)
+    fn compute(&self, state: &mut NodeState);
     fn name(&self) -> &str;
     fn input_count(&self) -> usize;
     fn output_count(&self) -> usize;
+    fn input_width(&self, pin: usize) -> u8;
+    fn output_width(&self, pin: usize) -> u8;
+
+    /// Set up internal state, such as memory contents, when the node is created
+    fn init(&self, _state: &mut NodeState) {}
+
+    /// True if the outputs only set net bits that no other driver drives, like a pull
+    /// resistor
+    fn drives_weakly(&self) -> bool {
+        false
+    }
+
+    /// True if the node passes signals both ways between nets it both reads and drives, like
+    /// a splitter. It reads those nets as resolved from their other strong drivers only, so it
+    /// does not latch onto its own outputs.
+    fn is_bidirectional(&self) -> bool {
+        false
+    }
+
+    /// Called once per simulation tick, for components driven by time rather than inputs
+    fn tick(&self, _state: &mut NodeState) {}
 }
 
+/// Instance of a component in a circuit
 pub struct Node {
     pub id: NodeId,
     pub logic: Box<dyn NodeLogic>,
     pub state: NodeState,
-    pub input_connections: Vec<Vec<NodeId>>, // input_pin -> [source_nodes]
-    pub output_connections: Vec<Vec<NodeId>>, // output_pin -> [destination_nodes]
+    pub input_connections: Vec<Option<NetId>>, // input_pin -> net
+    pub output_connections: Vec<Option<NetId>>, // output_pin -> net
 }
 
 impl Node {
     pub fn new(id: NodeId, logic: Box<dyn NodeLogic>) -> Self {
         let input_count = logic.input_count();
         let output_count = logic.output_count();
-        
+
         Self {
             id,
-            state: NodeState::new(input_count, output_count),
+            state: NodeState::new(logic.as_ref()),
             logic,
-            input_connections: vec![Vec::new(); input_count],
-            output_connections: vec![Vec::new(); output_count],
+            input_connections: vec![None; input_count],
+            output_connections: vec![None; output_count],
         }
     }
 
-    pub fn read_values(&mut self, circuit_state: &HashMap<NodeId, Vec<Value>>) {
-        for (pin, sources) in self.input_connections.iter().enumerate() {
-            if let Some(source_id) = sources.first() {
-                if let Some(source_outputs) = circuit_state.get(source_id) {
-                    if let Some(value) = source_outputs.get(0) { // assume single output for now
-                        self.state.inputs[pin] = value.clone();
-                    }
-                }
+    /// Read every connected input pin from the committed value of its net, keeping the
+    /// values seen last time in `previous_inputs`
+    pub fn read_values(&mut self, signals: &[Value]) {
+        self.state.previous_inputs.clone_from(&self.state.inputs);
+        for (pin, net) in self.input_connections.iter().enumerate() {
+            if let Some(net) = net {
+                self.state.inputs[pin] = signals[*net as usize].clone();
             }
         }
     }
 
-    pub fn update(&mut self) -> Vec<NodeId> {
-        // Run logic
-        self.logic.compute(&mut self.state)
+    /// Run the node's logic and return the output pins whose value changed
+    pub fn update(&mut self) -> Vec<usize> {
+        let previous = self.state.outputs.clone();
+        self.logic.compute(&mut self.state);
+        self.changed_outputs(&previous)
+    }
+
+    /// Advance the node by one tick and return the output pins whose value changed
+    pub fn tick(&mut self) -> Vec<usize> {
+        let previous = self.state.outputs.clone();
+        self.logic.tick(&mut self.state);
+        self.changed_outputs(&previous)
+    }
+
+    fn changed_outputs(&self, previous: &[Value]) -> Vec<usize> {
+        (0..self.state.outputs.len())
+            .filter(|&pin| previous.get(pin) != Some(&self.state.outputs[pin]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_edges() {
+        let (low, high) = (Value::new(0, 1), Value::new(1, 1));
+        let (z, x) = (Value::high_z(1), Value::unknown(1));
+        assert_eq!(Edge::Rising.detect(&low, &high), Some(true));
+        assert_eq!(Edge::Rising.detect(&high, &low), Some(false));
+        assert_eq!(Edge::Falling.detect(&high, &low), Some(true));
+        assert_eq!(Edge::Rising.detect(&high, &high), Some(false));
+
+        // X might have been either level, so going to or from it may be the edge
+        assert_eq!(Edge::Rising.detect(&low, &x), None);
+        assert_eq!(Edge::Rising.detect(&x, &high), None);
+        assert_eq!(Edge::Rising.detect(&x, &low), Some(false));
+        assert_eq!(Edge::Falling.detect(&x, &low), None);
+        assert_eq!(Edge::Rising.detect(&x, &x), None);
+
+        // An unconnected clock never triggers
+        assert_eq!(Edge::Rising.detect(&z, &high), Some(false));
+        assert_eq!(Edge::Falling.detect(&low, &z), Some(false));
     }
 }
@@ -0,0 +1,1111 @@
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+
+use crate::component::arithmetic::{
+    self, Adder, BitCounter, Comparator, Divider, Multiplier, Negator, ShiftKind, Shifter,
+    Subtractor,
+};
+use crate::component::bus::{ControlledBuffer, PullResistor, TransmissionGate};
+use crate::component::clock::{Clock, ClockTiming};
+use crate::component::flip_flops::{FlipFlop, FlipFlopKind, Register};
+use crate::component::gates::{Gate, GateKind};
+use crate::component::io::{Constant, Input, Output, Probe, Radix};
+use crate::component::memory::{MAX_ADDRESS_WIDTH, MAX_DATA_WIDTH, MemoryConfig, Ram, Rom};
+use crate::component::plexers::{
+    BitSelector, Decoder, Demultiplexer, MAX_SELECT_WIDTH, Multiplexer, PriorityEncoder,
+};
+use crate::component::splitter::{Splitter, even_split};
+use crate::node::{Edge, NodeLogic};
+
+use super::Schematic;
+use super::nets::{Direction, GridPos};
+
+/// Kind of component that can be placed on the grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ComponentKind {
+    AndGate,
+    OrGate,
+    NandGate,
+    NorGate,
+    XorGate,
+    XnorGate,
+    NotGate,
+    Input,
+    Output,
+    Clock,
+    Constant,
+    ControlledBuffer,
+    ControlledInverter,
+    TransmissionGate,
+    PullUp,
+    PullDown,
+    DFlipFlop,
+    TFlipFlop,
+    JKFlipFlop,
+    SRFlipFlop,
+    Register,
+    Rom,
+    Ram,
+    Multiplexer,
+    Demultiplexer,
+    Decoder,
+    PriorityEncoder,
+    BitSelector,
+    Adder,
+    Subtractor,
+    Multiplier,
+    Divider,
+    Negator,
+    Comparator,
+    Shifter,
+    BitCounter,
+    Splitter,
+    Joiner,
+    Probe,
+    /// Joins the net on its pin to those of every tunnel in the circuit with the same `label`
+    Tunnel,
+    /// Instance of a circuit from the project's library, named by `PlacedComponent::subcircuit`
+    Subcircuit,
+}
+
+impl ComponentKind {
+    pub const GATES: [ComponentKind; 7] = [
+        Self::AndGate,
+        Self::OrGate,
+        Self::NandGate,
+        Self::NorGate,
+        Self::XorGate,
+        Self::XnorGate,
+        Self::NotGate,
+    ];
+
+    /// Pins connecting a circuit to whatever contains it
+    pub const PINS: [ComponentKind; 2] = [Self::Input, Self::Output];
+
+    /// Components driving a value without reading any input
+    pub const SOURCES: [ComponentKind; 2] = [Self::Clock, Self::Constant];
+
+    /// Components for sharing a net between several drivers
+    pub const BUS: [ComponentKind; 5] = [
+        Self::ControlledBuffer,
+        Self::ControlledInverter,
+        Self::TransmissionGate,
+        Self::PullUp,
+        Self::PullDown,
+    ];
+
+    /// Clocked components that hold state
+    pub const MEMORY: [ComponentKind; 7] = [
+        Self::DFlipFlop,
+        Self::TFlipFlop,
+        Self::JKFlipFlop,
+        Self::SRFlipFlop,
+        Self::Register,
+        Self::Rom,
+        Self::Ram,
+    ];
+
+    /// Components routing signals by a binary select input
+    pub const PLEXERS: [ComponentKind; 5] = [
+        Self::Multiplexer,
+        Self::Demultiplexer,
+        Self::Decoder,
+        Self::PriorityEncoder,
+        Self::BitSelector,
+    ];
+
+    /// Components doing arithmetic on their inputs as binary numbers
+    pub const ARITHMETIC: [ComponentKind; 8] = [
+        Self::Adder,
+        Self::Subtractor,
+        Self::Multiplier,
+        Self::Divider,
+        Self::Negator,
+        Self::Comparator,
+        Self::Shifter,
+        Self::BitCounter,
+    ];
+
+    /// Components wiring the bits of a bus to narrower fans, probes showing the value of a net
+    /// and tunnels connecting nets by name. A joiner is a splitter with its bus on the east
+    /// side; both pass signals either way.
+    pub const WIRING: [ComponentKind; 4] =
+        [Self::Splitter, Self::Joiner, Self::Probe, Self::Tunnel];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::AndGate => "AND Gate",
+            Self::OrGate => "OR Gate",
+            Self::NandGate => "NAND Gate",
+            Self::NorGate => "NOR Gate",
+            Self::XorGate => "XOR Gate",
+            Self::XnorGate => "XNOR Gate",
+            Self::NotGate => "NOT Gate",
+            Self::Input => "Input Pin",
+            Self::Output => "Output Pin",
+            Self::Clock => "Clock",
+            Self::Constant => "Constant",
+            Self::ControlledBuffer => "Controlled Buffer",
+            Self::ControlledInverter => "Controlled Inverter",
+            Self::TransmissionGate => "Transmission Gate",
+            Self::PullUp => "Pull-Up Resistor",
+            Self::PullDown => "Pull-Down Resistor",
+            Self::DFlipFlop => "D Flip-Flop",
+            Self::TFlipFlop => "T Flip-Flop",
+            Self::JKFlipFlop => "JK Flip-Flop",
+            Self::SRFlipFlop => "SR Flip-Flop",
+            Self::Register => "Register",
+            Self::Rom => "ROM",
+            Self::Ram => "RAM",
+            Self::Multiplexer => "Multiplexer",
+            Self::Demultiplexer => "Demultiplexer",
+            Self::Decoder => "Decoder",
+            Self::PriorityEncoder => "Priority Encoder",
+            Self::BitSelector => "Bit Selector",
+            Self::Adder => "Adder",
+            Self::Subtractor => "Subtractor",
+            Self::Multiplier => "Multiplier",
+            Self::Divider => "Divider",
+            Self::Negator => "Negator",
+            Self::Comparator => "Comparator",
+            Self::Shifter => "Shifter",
+            Self::BitCounter => "Bit Counter",
+            Self::Splitter => "Splitter",
+            Self::Joiner => "Joiner",
+            Self::Probe => "Probe",
+            Self::Tunnel => "Tunnel",
+            Self::Subcircuit => "Subcircuit",
+        }
+    }
+
+    /// Gate implementing this component, if it is one
+    pub fn gate_kind(&self) -> Option<GateKind> {
+        Some(match self {
+            Self::AndGate => GateKind::And,
+            Self::OrGate => GateKind::Or,
+            Self::NandGate => GateKind::Nand,
+            Self::NorGate => GateKind::Nor,
+            Self::XorGate => GateKind::Xor,
+            Self::XnorGate => GateKind::Xnor,
+            Self::NotGate => GateKind::Not,
+            _ => return None,
+        })
+    }
+
+    /// Flip-flop implementing this component, if it is one
+    pub fn flip_flop_kind(&self) -> Option<FlipFlopKind> {
+        Some(match self {
+            Self::DFlipFlop => FlipFlopKind::D,
+            Self::TFlipFlop => FlipFlopKind::T,
+            Self::JKFlipFlop => FlipFlopKind::JK,
+            Self::SRFlipFlop => FlipFlopKind::SR,
+            _ => return None,
+        })
+    }
+
+    /// True for components that act on a clock edge, set by `ComponentSettings::Trigger`
+    pub fn is_edge_triggered(&self) -> bool {
+        self.flip_flop_kind().is_some() || *self == Self::Register
+    }
+
+    /// True for components addressing words set by `ComponentSettings::Memory`
+    pub fn is_addressed(&self) -> bool {
+        matches!(self, Self::Rom | Self::Ram)
+    }
+
+    /// True for components with a data input on the west side and control inputs above and
+    /// below
+    pub fn is_controlled(&self) -> bool {
+        matches!(
+            self,
+            Self::ControlledBuffer | Self::ControlledInverter | Self::TransmissionGate
+        )
+    }
+
+    /// True for components with `2^ComponentSettings::SelectWidth` ways
+    pub fn is_plexer(&self) -> bool {
+        matches!(
+            self,
+            Self::Multiplexer | Self::Demultiplexer | Self::Decoder | Self::PriorityEncoder
+        )
+    }
+
+    /// True for components from `ARITHMETIC`
+    pub fn is_arithmetic(&self) -> bool {
+        Self::ARITHMETIC.contains(self)
+    }
+
+    /// True for splitters and joiners, whose every pin is both an input and an output
+    pub fn is_splitter(&self) -> bool {
+        matches!(self, Self::Splitter | Self::Joiner)
+    }
+
+    /// True for components showing the value on their pin bit by bit, set by clicking the bits
+    /// of an input
+    pub fn shows_bits(&self) -> bool {
+        matches!(self, Self::Input | Self::Output)
+    }
+
+    pub fn default_bit_width(&self) -> u8 {
+        if self.is_addressed()
+            || self.is_arithmetic()
+            || self.is_splitter()
+            || *self == Self::BitSelector
+        {
+            8
+        } else {
+            1
+        }
+    }
+
+    pub fn default_input_count(&self) -> u8 {
+        match self {
+            Self::NotGate
+            | Self::Output
+            | Self::Probe
+            | Self::Tunnel
+            | Self::Rom
+            | Self::Negator
+            | Self::BitCounter => 1,
+            // Address, data, write enable and clock
+            Self::Ram => 4,
+            Self::Input
+            | Self::Clock
+            | Self::Constant
+            | Self::PullUp
+            | Self::PullDown
+            | Self::Subcircuit => 0,
+            // Two data inputs, then select
+            Self::Multiplexer => 3,
+            // Side A, gate, inverted gate and side B
+            Self::TransmissionGate => 4,
+            // Two operands, then carry or borrow in
+            Self::Adder | Self::Subtractor => 3,
+            // Bus, then two fans
+            Self::Splitter | Self::Joiner => 3,
+            // Data inputs, then clock, enable, reset and preset
+            Self::Register => 5,
+            kind => match kind.flip_flop_kind() {
+                Some(flip_flop) => flip_flop.data_inputs() as u8 + 4,
+                None => 2,
+            },
+        }
+    }
+
+    pub fn default_output_count(&self) -> u8 {
+        match self {
+            Self::Output | Self::Probe | Self::Tunnel | Self::Subcircuit => 0,
+            kind if kind.flip_flop_kind().is_some() => 2,
+            Self::Demultiplexer | Self::Decoder | Self::PriorityEncoder => 2,
+            Self::Adder | Self::Subtractor | Self::Multiplier | Self::Divider => 2,
+            // Side A and side B
+            Self::TransmissionGate => 2,
+            // Less than, equal and greater than
+            Self::Comparator => 3,
+            Self::Splitter | Self::Joiner => 3,
+            _ => 1,
+        }
+    }
+}
+
+/// Bits shown on each row of a multi-bit input or output pin
+const BITS_PER_ROW: i32 = 8;
+
+/// Component instance on the grid
+///
+/// Components are laid out facing east, with inputs on the west side and outputs on the east,
+/// then rotated so their outputs point towards `facing`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlacedComponent {
+    pub kind: ComponentKind,
+    pub position: GridPos, // top-left cell of the rotated body
+    pub facing: Direction,
+    pub bit_width: u8,
+    pub input_count: u8,
+    #[serde(default = "default_output_count")]
+    pub output_count: u8,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub label: String,
+    /// Library circuit this is an instance of, for `ComponentKind::Subcircuit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subcircuit: Option<String>,
+    /// Setting of the kinds of component that have one
+    #[serde(default, skip_serializing_if = "ComponentSettings::is_none")]
+    pub settings: ComponentSettings,
+}
+
+fn default_output_count() -> u8 {
+    1
+}
+
+/// Setting that only some kinds of component have, no kind having more than one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComponentSettings {
+    #[default]
+    None,
+    /// Clock edge that edge-triggered components act on
+    Trigger(Edge),
+    /// Level durations of a `ComponentKind::Clock`
+    Timing(ClockTiming),
+    /// Settings and initial contents of a ROM or RAM
+    Memory(MemoryConfig),
+    /// Width of the select input of a plexer, which has a way for each value it can take
+    SelectWidth(u8),
+    /// Whether a comparator treats its operands as two's complement
+    Signed(bool),
+    /// Direction and fill of a `ComponentKind::Shifter`
+    Shift(ShiftKind),
+    /// Fan of each bus bit of a splitter or joiner, lowest bit first, or `None` for a bit on no
+    /// fan. Ignored unless there is one entry per bit; see `PlacedComponent::fan_map`.
+    BitFans(Vec<Option<u8>>),
+    /// Whether clicking a bit of an input that is 1 lets it float before it goes back to 0
+    ThreeState(bool),
+    /// Value a `ComponentKind::Constant` drives, cut down to its bit width
+    Value(u64),
+    /// How a `ComponentKind::Probe` writes out its value
+    Radix(Radix),
+}
+
+impl ComponentSettings {
+    /// Settings a new component of `kind` starts with
+    pub fn for_kind(kind: ComponentKind) -> Self {
+        match kind {
+            kind if kind.is_edge_triggered() => Self::Trigger(Edge::Rising),
+            ComponentKind::Clock => Self::Timing(ClockTiming::default()),
+            kind if kind.is_addressed() => Self::Memory(MemoryConfig::default()),
+            kind if kind.is_plexer() => Self::SelectWidth(1),
+            ComponentKind::Comparator => Self::Signed(false),
+            ComponentKind::Shifter => Self::Shift(ShiftKind::default()),
+            kind if kind.is_splitter() => Self::BitFans(Vec::new()),
+            ComponentKind::Input => Self::ThreeState(false),
+            ComponentKind::Constant => Self::Value(0),
+            ComponentKind::Probe => Self::Radix(Radix::default()),
+            _ => Self::None,
+        }
+    }
+
+    /// True if these are settings a component of `kind` has
+    pub fn fit(&self, kind: ComponentKind) -> bool {
+        mem::discriminant(self) == mem::discriminant(&Self::for_kind(kind))
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::None
+    }
+}
+
+impl PlacedComponent {
+    pub fn new(kind: ComponentKind, position: GridPos) -> Self {
+        Self {
+            kind,
+            position,
+            facing: Direction::East,
+            bit_width: kind.default_bit_width(),
+            input_count: kind.default_input_count(),
+            output_count: kind.default_output_count(),
+            label: String::new(),
+            subcircuit: None,
+            settings: ComponentSettings::for_kind(kind),
+        }
+    }
+
+    /// Number of fans of a splitter or joiner, which has a pin for each plus one for the bus
+    pub fn fan_count(&self) -> u8 {
+        self.output_count.saturating_sub(1)
+    }
+
+    /// Change the number of fans of a splitter or joiner, going back to an even split
+    pub fn set_fan_count(&mut self, fans: u8) {
+        self.input_count = fans + 1;
+        self.output_count = fans + 1;
+        self.settings = ComponentSettings::BitFans(Vec::new());
+    }
+
+    /// Fan of each bus bit of a splitter or joiner: `ComponentSettings::BitFans` when it
+    /// covers every bit, an even split otherwise
+    pub fn fan_map(&self) -> Vec<Option<u8>> {
+        match &self.settings {
+            ComponentSettings::BitFans(bit_fans) if bit_fans.len() == self.bit_width as usize => {
+                bit_fans.clone()
+            }
+            _ => even_split(self.bit_width, self.fan_count()),
+        }
+    }
+
+    /// Width of the select input of a plexer, or 0 for a component without one
+    pub fn select_width(&self) -> u8 {
+        match self.settings {
+            ComponentSettings::SelectWidth(select_width) => select_width,
+            _ => 0,
+        }
+    }
+
+    /// Number of ways of a plexer. Select widths past `MAX_SELECT_WIDTH` count as that wide.
+    fn ways(&self) -> u32 {
+        1 << self.select_width().min(MAX_SELECT_WIDTH)
+    }
+
+    /// Change the select width of a plexer, up to `MAX_SELECT_WIDTH`, along with its number of
+    /// ways
+    pub fn set_select_width(&mut self, select_width: u8) {
+        if !self.kind.is_plexer() {
+            return;
+        }
+        self.settings = ComponentSettings::SelectWidth(select_width.clamp(1, MAX_SELECT_WIDTH));
+        // At most 32 ways, so the pin counts fit
+        let ways = self.ways() as u8;
+        match self.kind {
+            ComponentKind::Multiplexer => self.input_count = ways + 1,
+            ComponentKind::Demultiplexer | ComponentKind::Decoder => self.output_count = ways,
+            ComponentKind::PriorityEncoder => self.input_count = ways,
+            _ => {}
+        }
+    }
+
+    /// Settings and initial contents of a ROM or RAM
+    pub fn memory(&self) -> Option<&MemoryConfig> {
+        match &self.settings {
+            ComponentSettings::Memory(memory) => Some(memory),
+            _ => None,
+        }
+    }
+
+    pub fn memory_mut(&mut self) -> Option<&mut MemoryConfig> {
+        match &mut self.settings {
+            ComponentSettings::Memory(memory) => Some(memory),
+            _ => None,
+        }
+    }
+
+    /// Instance of the library circuit `name`, with a pin for each of its input and output pins
+    pub fn subcircuit(name: &str, definition: &Schematic, position: GridPos) -> Self {
+        let mut component = Self::new(ComponentKind::Subcircuit, position);
+        component.subcircuit = Some(name.to_string());
+        component.match_definition(definition);
+        component
+    }
+
+    /// Give a subcircuit instance the pins of its (possibly edited) definition
+    pub fn match_definition(&mut self, definition: &Schematic) {
+        let (inputs, outputs) = definition.ports();
+        self.input_count = inputs.len() as u8;
+        self.output_count = outputs.len() as u8;
+    }
+
+    /// Check the settings `build` relies on, since a file may hold any value. Returns why the
+    /// first bad setting found is out of range.
+    pub fn validate(&self) -> Result<(), String> {
+        let out_of_range = |setting: &str, value: u8, min: u8, max: u8| {
+            if (min..=max).contains(&value) {
+                Ok(())
+            } else {
+                Err(format!("{setting} {value} is not between {min} and {max}"))
+            }
+        };
+
+        if !self.settings.fit(self.kind) {
+            return Err(format!(
+                "{} cannot have setting {:?}",
+                self.kind.label(),
+                self.settings
+            ));
+        }
+        let max_width = if self.kind.gate_kind().is_some() {
+            Gate::MAX_WIDTH
+        } else if self.kind.is_arithmetic() {
+            arithmetic::MAX_WIDTH
+        } else if self.kind.is_addressed() {
+            MAX_DATA_WIDTH
+        } else {
+            u8::MAX
+        };
+        out_of_range("bit width", self.bit_width, 1, max_width)?;
+        if self.kind.gate_kind().is_some() && self.kind != ComponentKind::NotGate {
+            out_of_range(
+                "input count",
+                self.input_count,
+                Gate::MIN_INPUTS,
+                Gate::MAX_INPUTS,
+            )?;
+        }
+        match &self.settings {
+            &ComponentSettings::SelectWidth(select_width) => {
+                out_of_range("select width", select_width, 1, MAX_SELECT_WIDTH)
+            }
+            ComponentSettings::Memory(memory) => {
+                out_of_range("address width", memory.address_width, 1, MAX_ADDRESS_WIDTH)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Create the simulation logic for this instance. Subcircuits have none of their own; they
+    /// are compiled from their definition instead. Tunnels have none either, only joining nets.
+    ///
+    /// Panics if the settings are not those of the kind, which `validate` checks.
+    pub fn build(&self) -> Option<Box<dyn NodeLogic>> {
+        let width = self.bit_width;
+        Some(match (self.kind, &self.settings) {
+            (ComponentKind::Input, _) => Box::new(Input::new(width)),
+            (ComponentKind::Output, _) => Box::new(Output::new(width)),
+            (ComponentKind::Clock, &ComponentSettings::Timing(timing)) => {
+                Box::new(Clock::new(timing))
+            }
+            (ComponentKind::Constant, &ComponentSettings::Value(value)) => {
+                Box::new(Constant::new(value, width))
+            }
+            (ComponentKind::Probe, _) => Box::new(Probe::new(width)),
+            (ComponentKind::ControlledBuffer, _) => Box::new(ControlledBuffer::new(width, false)),
+            (ComponentKind::ControlledInverter, _) => Box::new(ControlledBuffer::new(width, true)),
+            (ComponentKind::TransmissionGate, _) => Box::new(TransmissionGate::new(width)),
+            (ComponentKind::PullUp, _) => Box::new(PullResistor::new(width, true)),
+            (ComponentKind::PullDown, _) => Box::new(PullResistor::new(width, false)),
+            (ComponentKind::Rom, ComponentSettings::Memory(memory)) => {
+                Box::new(Rom::new(memory, width))
+            }
+            (ComponentKind::Ram, ComponentSettings::Memory(memory)) => {
+                Box::new(Ram::new(memory, width))
+            }
+            (ComponentKind::Multiplexer, &ComponentSettings::SelectWidth(select_width)) => {
+                Box::new(Multiplexer::new(select_width, width))
+            }
+            (ComponentKind::Demultiplexer, &ComponentSettings::SelectWidth(select_width)) => {
+                Box::new(Demultiplexer::new(select_width, width))
+            }
+            (ComponentKind::Decoder, &ComponentSettings::SelectWidth(select_width)) => {
+                Box::new(Decoder::new(select_width))
+            }
+            (ComponentKind::PriorityEncoder, &ComponentSettings::SelectWidth(select_width)) => {
+                Box::new(PriorityEncoder::new(select_width))
+            }
+            (ComponentKind::BitSelector, _) => Box::new(BitSelector::new(width)),
+            (ComponentKind::Adder, _) => Box::new(Adder::new(width)),
+            (ComponentKind::Subtractor, _) => Box::new(Subtractor::new(width)),
+            (ComponentKind::Multiplier, _) => Box::new(Multiplier::new(width)),
+            (ComponentKind::Divider, _) => Box::new(Divider::new(width)),
+            (ComponentKind::Negator, _) => Box::new(Negator::new(width)),
+            (ComponentKind::Comparator, &ComponentSettings::Signed(signed)) => {
+                Box::new(Comparator::new(width, signed))
+            }
+            (ComponentKind::Shifter, &ComponentSettings::Shift(shift)) => {
+                Box::new(Shifter::new(width, shift))
+            }
+            (ComponentKind::BitCounter, _) => Box::new(BitCounter::new(width)),
+            (ComponentKind::Splitter | ComponentKind::Joiner, _) => {
+                Box::new(Splitter::new(&self.fan_map(), self.fan_count() as usize))
+            }
+            (ComponentKind::Subcircuit | ComponentKind::Tunnel, _) => return None,
+            (ComponentKind::Register, &ComponentSettings::Trigger(trigger)) => {
+                Box::new(Register::new(width, trigger))
+            }
+            (kind, &ComponentSettings::Trigger(trigger))
+                if let Some(flip_flop) = kind.flip_flop_kind() =>
+            {
+                Box::new(FlipFlop::new(flip_flop, trigger))
+            }
+            (kind, _) if let Some(gate) = kind.gate_kind() => {
+                Box::new(Gate::new(gate, self.input_count, width))
+            }
+            (kind, settings) => panic!("{} cannot have setting {:?}", kind.label(), settings),
+        })
+    }
+
+    /// Width and height of the body before rotation
+    fn unrotated_size(&self) -> (i32, i32) {
+        match self.kind {
+            // A cell per bit, in rows of eight
+            kind if kind.shows_bits() && self.bit_width > 1 => {
+                let width = self.bit_width as i32;
+                (
+                    width.clamp(2, BITS_PER_ROW),
+                    (width + BITS_PER_ROW - 1) / BITS_PER_ROW,
+                )
+            }
+            ComponentKind::Tunnel => {
+                // Room for the name, at about three characters a cell
+                let len = self.label.chars().count() as i32;
+                (((len + 2) / 3 + 1).max(2), 1)
+            }
+            ComponentKind::Probe => {
+                // Room for the longest value, at about three characters a cell
+                let radix = match self.settings {
+                    ComponentSettings::Radix(radix) => radix,
+                    _ => Radix::default(),
+                };
+                let len = radix.max_len(self.bit_width) as i32;
+                (((len + 2) / 3).max(2), 1)
+            }
+            ComponentKind::Input
+            | ComponentKind::Output
+            | ComponentKind::Clock
+            | ComponentKind::Constant
+            | ComponentKind::PullUp
+            | ComponentKind::PullDown => (2, 1),
+            ComponentKind::Subcircuit => {
+                let pins = self.input_count.max(self.output_count).max(1) as i32;
+                (4, pins * 2 + 1)
+            }
+            kind if kind.is_splitter() => (2, (self.fan_count() as i32).max(1)),
+            kind if kind.is_edge_triggered() => (3, 5),
+            kind if kind.is_addressed() => (4, 5),
+            kind if kind.is_controlled() => (3, 3),
+            kind if kind.is_plexer() => (3, (self.ways() as i32 | 1).max(3)),
+            _ => {
+                let inputs = self.input_count as i32;
+                (3, (inputs | 1).max(3))
+            }
+        }
+    }
+
+    /// Width and height of the body in cells
+    pub fn size(&self) -> (i32, i32) {
+        let (width, height) = self.unrotated_size();
+        if self.facing.is_vertical() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Map a cell of the east-facing layout to the grid
+    fn place(&self, (x, y): GridPos) -> GridPos {
+        let (width, height) = self.unrotated_size();
+        let (dx, dy) = match self.facing {
+            Direction::East => (x, y),
+            Direction::South => (height - 1 - y, x),
+            Direction::West => (width - 1 - x, height - 1 - y),
+            Direction::North => (y, width - 1 - x),
+        };
+        (self.position.0 + dx, self.position.1 + dy)
+    }
+
+    /// Cells of the input pins, in pin order. Pins sit just outside the body so a wire can end
+    /// on them. An even number of inputs skips the middle row; subcircuits leave a row between
+    /// every pin.
+    ///
+    /// Edge-triggered components take data and clock on the west side, enable and reset along
+    /// the bottom and preset at the top. Plexers take select along the bottom, and have a pin for
+    /// each way whatever pin counts they were saved with.
+    pub fn input_pins(&self) -> Vec<GridPos> {
+        let (_, height) = self.unrotated_size();
+        if self.kind.is_splitter() {
+            return self.splitter_pins();
+        }
+        if self.kind.is_edge_triggered() {
+            // Data then clock on the west side, enable and reset below, preset above. The
+            // kind decides the data inputs, registers having one, so saved counts are not used.
+            let data_inputs = self
+                .kind
+                .flip_flop_kind()
+                .map_or(1, |kind| kind.data_inputs());
+            let data: &[GridPos] = match data_inputs {
+                2 => &[(-1, 1), (-1, 3), (-1, 2)],
+                _ => &[(-1, 1), (-1, 3)],
+            };
+            let controls = [(0, height), (1, height), (1, -1)];
+            return data
+                .iter()
+                .chain(&controls)
+                .map(|&cell| self.place(cell))
+                .collect();
+        }
+        if self.kind == ComponentKind::TransmissionGate {
+            // Side A, gate from below, inverted gate from above, then side B. Older files
+            // count fewer pins, so the counts are not used.
+            let (width, _) = self.unrotated_size();
+            return [(-1, height / 2), (1, height), (1, -1), (width, height / 2)]
+                .into_iter()
+                .map(|cell| self.place(cell))
+                .collect();
+        }
+        if self.kind.is_controlled() {
+            // Data, then control from below
+            return [(-1, height / 2), (1, height)]
+                .into_iter()
+                .take(self.input_count as usize)
+                .map(|cell| self.place(cell))
+                .collect();
+        }
+        if self.kind == ComponentKind::Ram {
+            // Address and data on the west side, or data on the output's cell with a shared
+            // bus; write enable and, when clocked, the clock along the bottom. The memory
+            // settings decide the pins, so saved counts are not used.
+            let (width, _) = self.unrotated_size();
+            let (shared_bus, clocked) = self
+                .memory()
+                .map_or((false, true), |memory| (memory.shared_bus, memory.clocked));
+            let data = if shared_bus {
+                (width, height / 2)
+            } else {
+                (-1, 3)
+            };
+            return [(-1, 1), data, (1, height), (2, height)]
+                .into_iter()
+                .take(if clocked { 4 } else { 3 })
+                .map(|cell| self.place(cell))
+                .collect();
+        }
+        let select = (1, height);
+        let cells: Vec<GridPos> = match self.kind {
+            ComponentKind::Subcircuit => (0..self.input_count as i32)
+                .map(|pin| (-1, pin * 2 + 1))
+                .collect(),
+            ComponentKind::Multiplexer => {
+                let data = rows(self.ways() as usize, height)
+                    .into_iter()
+                    .map(|row| (-1, row));
+                data.chain([select]).collect()
+            }
+            ComponentKind::PriorityEncoder => rows(self.ways() as usize, height)
+                .into_iter()
+                .map(|row| (-1, row))
+                .collect(),
+            ComponentKind::Demultiplexer | ComponentKind::BitSelector => {
+                vec![(-1, height / 2), select]
+            }
+            // Select, then enable
+            ComponentKind::Decoder => vec![select, (2, height)],
+            // Operands, then carry or borrow in from above
+            ComponentKind::Adder | ComponentKind::Subtractor => vec![(-1, 0), (-1, 2), (1, -1)],
+            _ => rows(self.input_count as usize, height)
+                .into_iter()
+                .map(|row| (-1, row))
+                .collect(),
+        };
+        cells.into_iter().map(|cell| self.place(cell)).collect()
+    }
+
+    /// Cells of the output pins, in pin order
+    pub fn output_pins(&self) -> Vec<GridPos> {
+        let (width, height) = self.unrotated_size();
+        if self.kind.is_splitter() {
+            self.splitter_pins()
+        } else if self.kind == ComponentKind::TransmissionGate {
+            // Both sides, on the same cells as the inputs for them
+            vec![
+                self.place((-1, height / 2)),
+                self.place((width, height / 2)),
+            ]
+        } else if self.kind.is_edge_triggered() {
+            // Q, then the inverted Q of flip-flops
+            (0..self.kind.default_output_count() as i32)
+                .map(|pin| self.place((width, pin * 2 + 1)))
+                .collect()
+        } else if self.kind == ComponentKind::Subcircuit {
+            (0..self.output_count as i32)
+                .map(|pin| self.place((width, pin * 2 + 1)))
+                .collect()
+        } else if matches!(
+            self.kind,
+            ComponentKind::Demultiplexer | ComponentKind::Decoder
+        ) {
+            rows(self.ways() as usize, height)
+                .into_iter()
+                .map(|row| self.place((width, row)))
+                .collect()
+        } else if matches!(
+            self.kind,
+            ComponentKind::Adder
+                | ComponentKind::Subtractor
+                | ComponentKind::Multiplier
+                | ComponentKind::Divider
+        ) {
+            // Result, then carry, borrow, high word or remainder below
+            vec![self.place((width, height / 2)), self.place((1, height))]
+        } else if self.kind == ComponentKind::Comparator {
+            rows(3, height)
+                .into_iter()
+                .map(|row| self.place((width, row)))
+                .collect()
+        } else if self.kind == ComponentKind::PriorityEncoder {
+            // Number, then valid
+            vec![
+                self.place((width, height / 2)),
+                self.place((width, height / 2 + 1)),
+            ]
+        } else if self.output_count == 0 {
+            Vec::new()
+        } else {
+            vec![self.place((width, height / 2))]
+        }
+    }
+
+    /// Cells of a splitter's bus then fans, one row each; the bus faces west on a splitter and
+    /// east on a joiner
+    fn splitter_pins(&self) -> Vec<GridPos> {
+        let (width, height) = self.unrotated_size();
+        let (bus, fans) = match self.kind {
+            ComponentKind::Joiner => (width, -1),
+            _ => (-1, width),
+        };
+        std::iter::once((bus, height / 2))
+            .chain((0..self.fan_count() as i32).map(|fan| (fans, fan)))
+            .map(|cell| self.place(cell))
+            .collect()
+    }
+
+    /// Cell showing bit `bit` of an input or output pin more than one bit wide. Bits run from
+    /// the bottom right cell of the unrotated body, lowest first, a row of eight at a time.
+    pub fn bit_cell(&self, bit: u8) -> GridPos {
+        let (width, height) = self.unrotated_size();
+        let bit = bit as i32;
+        self.place((
+            width - 1 - bit % BITS_PER_ROW,
+            height - 1 - bit / BITS_PER_ROW,
+        ))
+    }
+
+    /// Bit of an input or output pin shown at `pos`. A single bit fills the whole body.
+    pub fn bit_at(&self, pos: GridPos) -> Option<u8> {
+        if !self.kind.shows_bits() || !self.contains(pos) {
+            None
+        } else if self.bit_width == 1 {
+            Some(0)
+        } else {
+            (0..self.bit_width).find(|&bit| self.bit_cell(bit) == pos)
+        }
+    }
+
+    /// True if `pos` is covered by the body
+    pub fn contains(&self, pos: GridPos) -> bool {
+        let (width, height) = self.size();
+        (self.position.0..self.position.0 + width).contains(&pos.0)
+            && (self.position.1..self.position.1 + height).contains(&pos.1)
+    }
+
+    /// True if the bodies of the two components share a cell
+    pub fn overlaps(&self, other: &PlacedComponent) -> bool {
+        let (width, height) = self.size();
+        let (other_width, other_height) = other.size();
+        self.position.0 < other.position.0 + other_width
+            && other.position.0 < self.position.0 + width
+            && self.position.1 < other.position.1 + other_height
+            && other.position.1 < self.position.1 + height
+    }
+}
+
+/// Rows of `count` pins along a side `height` cells tall. A single pin sits in the middle; an
+/// even number skips the middle row.
+fn rows(count: usize, height: i32) -> Vec<i32> {
+    if count == 1 {
+        vec![height / 2]
+    } else {
+        (0..height)
+            .filter(|row| count % 2 == 1 || *row != height / 2)
+            .take(count)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pins_follow_rotation() {
+        let mut gate = PlacedComponent::new(ComponentKind::AndGate, (10, 10));
+        assert_eq!(gate.input_pins(), vec![(9, 10), (9, 12)]);
+        assert_eq!(gate.output_pins(), vec![(13, 11)]);
+
+        gate.facing = Direction::South;
+        assert_eq!(gate.input_pins(), vec![(12, 9), (10, 9)]);
+        assert_eq!(gate.output_pins(), vec![(11, 13)]);
+
+        gate.facing = Direction::West;
+        assert_eq!(gate.input_pins(), vec![(13, 12), (13, 10)]);
+        assert_eq!(gate.output_pins(), vec![(9, 11)]);
+
+        gate.facing = Direction::North;
+        gate.input_count = 4;
+        assert_eq!(gate.size(), (5, 3));
+        assert_eq!(
+            gate.input_pins(),
+            vec![(10, 13), (11, 13), (13, 13), (14, 13)]
+        );
+        assert_eq!(gate.output_pins(), vec![(12, 9)]);
+    }
+
+    /// A component of every kind, plus one for each setting that changes its pins
+    fn every_layout() -> Vec<PlacedComponent> {
+        let kinds = [
+            &ComponentKind::GATES[..],
+            &ComponentKind::PINS,
+            &ComponentKind::SOURCES,
+            &ComponentKind::BUS,
+            &ComponentKind::MEMORY,
+            &ComponentKind::PLEXERS,
+            &ComponentKind::ARITHMETIC,
+            &ComponentKind::WIRING,
+        ]
+        .concat();
+        let mut components = Vec::new();
+        for kind in kinds {
+            let component = PlacedComponent::new(kind, (0, 0));
+            if kind.is_plexer() {
+                for select_width in [2, 3] {
+                    let mut plexer = component.clone();
+                    plexer.set_select_width(select_width);
+                    components.push(plexer);
+                }
+            }
+            if kind.is_splitter() {
+                let mut splitter = component.clone();
+                splitter.set_fan_count(3);
+                components.push(splitter);
+            }
+            if kind == ComponentKind::Ram {
+                let mut ram = component.clone();
+                ram.memory_mut().unwrap().shared_bus = true;
+                components.push(ram.clone());
+                ram.memory_mut().unwrap().clocked = false;
+                ram.input_count = 3;
+                components.push(ram);
+            }
+            components.push(component);
+        }
+        components
+    }
+
+    #[test]
+    fn pins_match_their_logic() {
+        for component in every_layout() {
+            let Some(logic) = component.build() else {
+                continue;
+            };
+            let kind = component.kind;
+            assert_eq!(
+                component.input_pins().len(),
+                logic.input_count(),
+                "{kind:?}"
+            );
+            assert_eq!(
+                component.output_pins().len(),
+                logic.output_count(),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn pin_layouts() {
+        let mut mux = PlacedComponent::new(ComponentKind::Multiplexer, (0, 0));
+        mux.set_select_width(2);
+        let mut shared_ram = PlacedComponent::new(ComponentKind::Ram, (0, 0));
+        shared_ram.memory_mut().unwrap().shared_bus = true;
+        let table = [
+            (
+                // J, K, clock, enable, reset, preset
+                PlacedComponent::new(ComponentKind::JKFlipFlop, (0, 0)),
+                vec![(-1, 1), (-1, 3), (-1, 2), (0, 5), (1, 5), (1, -1)],
+                vec![(3, 1), (3, 3)],
+            ),
+            (
+                mux,
+                vec![(-1, 0), (-1, 1), (-1, 3), (-1, 4), (1, 5)],
+                vec![(3, 2)],
+            ),
+            (
+                // Data shares the output's cell
+                shared_ram,
+                vec![(-1, 1), (4, 2), (1, 5), (2, 5)],
+                vec![(4, 2)],
+            ),
+            (
+                PlacedComponent::new(ComponentKind::TransmissionGate, (0, 0)),
+                vec![(-1, 1), (1, 3), (1, -1), (3, 1)],
+                vec![(-1, 1), (3, 1)],
+            ),
+        ];
+        for (component, inputs, outputs) in table {
+            assert_eq!(component.input_pins(), inputs, "{:?}", component.kind);
+            assert_eq!(component.output_pins(), outputs, "{:?}", component.kind);
+        }
+    }
+
+    #[test]
+    fn select_width_is_clamped() {
+        let mut mux = PlacedComponent::new(ComponentKind::Multiplexer, (0, 0));
+        mux.set_select_width(9);
+        assert_eq!(mux.select_width(), MAX_SELECT_WIDTH);
+        assert_eq!(mux.input_count, 33);
+    }
+
+    #[test]
+    fn plexer_pins_ignore_saved_counts() {
+        // Counts in a file that disagree with the select width
+        let mut mux = PlacedComponent::new(ComponentKind::Multiplexer, (0, 0));
+        mux.set_select_width(2);
+        mux.input_count = 0;
+        assert_eq!(mux.input_pins().len(), 5);
+
+        let mut decoder = PlacedComponent::new(ComponentKind::Decoder, (0, 0));
+        decoder.set_select_width(2);
+        decoder.output_count = 9;
+        assert_eq!(decoder.output_pins().len(), 4);
+    }
+
+    #[test]
+    fn edge_triggered_pins_ignore_saved_counts() {
+        for kind in [
+            ComponentKind::DFlipFlop,
+            ComponentKind::JKFlipFlop,
+            ComponentKind::Register,
+        ] {
+            let mut component = PlacedComponent::new(kind, (0, 0));
+            component.input_count = 6 - component.input_count;
+            component.output_count = 0;
+            let logic = component.build().unwrap();
+            assert_eq!(
+                component.input_pins().len(),
+                logic.input_count(),
+                "{kind:?}"
+            );
+            assert_eq!(
+                component.output_pins().len(),
+                logic.output_count(),
+                "{kind:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn ram_pins_follow_memory_settings() {
+        let mut ram = PlacedComponent::new(ComponentKind::Ram, (0, 0));
+        for clocked in [false, true] {
+            ram.memory_mut().unwrap().clocked = clocked;
+            for count in [0, 3, 4, 9] {
+                ram.input_count = count;
+                assert_eq!(ram.input_pins().len(), ram.build().unwrap().input_count());
+            }
+        }
+    }
+
+    #[test]
+    fn pins_show_a_cell_per_bit() {
+        let mut input = PlacedComponent::new(ComponentKind::Input, (0, 0));
+        assert_eq!(input.bit_at((1, 0)), Some(0));
+        input.bit_width = 12;
+        assert_eq!(input.size(), (8, 2));
+        assert_eq!(input.output_pins(), vec![(8, 1)]);
+        assert_eq!(input.bit_cell(0), (7, 1));
+        assert_eq!(input.bit_at((4, 0)), Some(11));
+        assert_eq!(input.bit_at((0, 0)), None);
+
+        input.facing = Direction::West;
+        assert_eq!(input.bit_cell(0), (0, 0));
+        assert_eq!(input.bit_at((0, 0)), Some(0));
+    }
+
+    #[test]
+    fn splitter_pins_are_shared_by_inputs_and_outputs() {
+        let mut joiner = PlacedComponent::new(ComponentKind::Joiner, (0, 0));
+        joiner.set_fan_count(3);
+        let logic = joiner.build().unwrap();
+        assert_eq!(joiner.input_pins().len(), logic.input_count());
+        assert_eq!(joiner.input_pins(), joiner.output_pins());
+        assert_eq!(joiner.input_pins(), vec![(2, 1), (-1, 0), (-1, 1), (-1, 2)]);
+        // Fans of 3, 3 and 2 bits
+        assert_eq!(logic.input_width(3), 2);
+    }
+}
@@ -0,0 +1,542 @@
+// Project file format: pretty-printed JSON with a version number, so files diff cleanly and
+// older files can be upgraded on load
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+
+use super::components::{ComponentKind, ComponentSettings, PlacedComponent};
+use super::nets::WireCell;
+use super::{Library, Schematic};
+
+/// Version written by `ProjectFile::to_json`
+pub const FORMAT_VERSION: u32 = 3;
+
+/// Upgrades from each older version, indexed by the version they upgrade from minus one
+const MIGRATIONS: [fn(&mut Json); FORMAT_VERSION as usize - 1] = [add_subcircuits, group_settings];
+
+/// Version 2 added the subcircuit library
+fn add_subcircuits(json: &mut Json) {
+    json["subcircuits"] = Json::Object(Default::default());
+}
+
+/// Field each variant of `ComponentSettings` was kept in before version 3
+const SETTING_FIELDS: [(&str, &str); 10] = [
+    ("Trigger", "trigger"),
+    ("Timing", "timing"),
+    ("Memory", "memory"),
+    ("SelectWidth", "select_width"),
+    ("Signed", "signed"),
+    ("Shift", "shift"),
+    ("BitFans", "bit_fans"),
+    ("ThreeState", "three_state"),
+    ("Value", "value"),
+    ("Radix", "radix"),
+];
+
+/// Version 3 moved the setting of each kind of component from a field of its own into
+/// `settings`
+fn group_settings(json: &mut Json) {
+    group_component_settings(json.get_mut("components"));
+    if let Some(subcircuits) = json.get_mut("subcircuits").and_then(Json::as_object_mut) {
+        for circuit in subcircuits.values_mut() {
+            group_component_settings(circuit.get_mut("components"));
+        }
+    }
+}
+
+fn group_component_settings(components: Option<&mut Json>) {
+    let components = components
+        .and_then(Json::as_array_mut)
+        .into_iter()
+        .flatten();
+    for component in components.filter_map(Json::as_object_mut) {
+        let mut fields = serde_json::Map::new();
+        for (variant, field) in SETTING_FIELDS {
+            if let Some(value) = component.remove(field) {
+                fields.insert(variant.to_string(), value);
+            }
+        }
+        // Fields at their default were left out, and kinds without the setting ignored them
+        let kind = component.get("kind").cloned().map(serde_json::from_value);
+        let Some(Ok(kind)) = kind else {
+            continue;
+        };
+        let Ok(Json::Object(mut settings)) =
+            serde_json::to_value(ComponentSettings::for_kind(kind))
+        else {
+            continue;
+        };
+        for (variant, value) in &mut settings {
+            if let Some(field) = fields.remove(variant) {
+                *value = field;
+            }
+        }
+        component.insert("settings".to_string(), Json::Object(settings));
+    }
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Io(io::Error),
+    Parse(serde_json::Error),
+    /// The file was written by a newer version of the program
+    UnsupportedVersion(u64),
+    MissingVersion,
+    /// Component `index` of the main circuit, or of the named subcircuit, has a setting out
+    /// of range
+    InvalidComponent {
+        circuit: Option<String>,
+        index: usize,
+        reason: String,
+    },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Parse(err) => write!(f, "invalid project file: {}", err),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "project file version {} is newer than the supported version {}",
+                version, FORMAT_VERSION
+            ),
+            Self::MissingVersion => write!(f, "project file has no version"),
+            Self::InvalidComponent {
+                circuit,
+                index,
+                reason,
+            } => {
+                write!(f, "component {}", index)?;
+                if let Some(name) = circuit {
+                    write!(f, " of subcircuit '{}'", name)?;
+                }
+                write!(f, ": {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FileError {}
+
+impl From<io::Error> for FileError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for FileError {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Parse(err)
+    }
+}
+
+/// One wire cell, with its connections written as a string of compass letters ("NES")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WireEntry {
+    pub x: i32,
+    pub y: i32,
+    pub connections: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub junction: bool,
+}
+
+impl WireEntry {
+    fn new((x, y): (i32, i32), cell: &WireCell) -> Self {
+        let connections = [
+            (cell.north, 'N'),
+            (cell.east, 'E'),
+            (cell.south, 'S'),
+            (cell.west, 'W'),
+        ]
+        .into_iter()
+        .filter_map(|(connected, letter)| connected.then_some(letter))
+        .collect();
+        Self {
+            x,
+            y,
+            connections,
+            junction: cell.junction,
+        }
+    }
+
+    fn cell(&self) -> WireCell {
+        WireCell {
+            north: self.connections.contains('N'),
+            east: self.connections.contains('E'),
+            south: self.connections.contains('S'),
+            west: self.connections.contains('W'),
+            junction: self.junction,
+        }
+    }
+}
+
+/// Editor state saved alongside the circuit. Ignored by the console runner.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorState {
+    pub camera: [f32; 2],
+    pub zoom: f32,
+    pub hotbar: Vec<Option<ComponentKind>>,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self {
+            camera: [0.0, 0.0],
+            zoom: 50.0,
+            hotbar: Vec::new(),
+        }
+    }
+}
+
+/// Wires and components of one circuit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitEntry {
+    pub wires: Vec<WireEntry>,
+    pub components: Vec<PlacedComponent>,
+}
+
+impl CircuitEntry {
+    /// Wires are sorted by position so saving the same drawing always gives the same file
+    pub fn new(schematic: &Schematic) -> Self {
+        let mut wires: Vec<WireEntry> = schematic
+            .wires
+            .iter()
+            .map(|(pos, cell)| WireEntry::new(*pos, cell))
+            .collect();
+        wires.sort_by_key(|wire| (wire.y, wire.x));
+
+        Self {
+            wires,
+            components: schematic.components.clone(),
+        }
+    }
+
+    pub fn schematic(&self) -> Schematic {
+        Schematic {
+            wires: self
+                .wires
+                .iter()
+                .map(|wire| ((wire.x, wire.y), wire.cell()))
+                .collect(),
+            components: self.components.clone(),
+        }
+    }
+}
+
+/// Contents of a project file: the main circuit, plus the circuits it can use as subcircuits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectFile {
+    pub version: u32,
+    #[serde(flatten)]
+    pub main: CircuitEntry,
+    pub subcircuits: BTreeMap<String, CircuitEntry>,
+    #[serde(default)]
+    pub editor: EditorState,
+}
+
+impl ProjectFile {
+    pub fn new(schematic: &Schematic, library: &Library, editor: EditorState) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            main: CircuitEntry::new(schematic),
+            subcircuits: library
+                .iter()
+                .map(|(name, circuit)| (name.clone(), CircuitEntry::new(circuit)))
+                .collect(),
+            editor,
+        }
+    }
+
+    pub fn schematic(&self) -> Schematic {
+        self.main.schematic()
+    }
+
+    pub fn library(&self) -> Library {
+        self.subcircuits
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.schematic()))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("project files always serialize")
+    }
+
+    /// Parse a project file of any supported version, upgrading it to the current one
+    pub fn from_json(text: &str) -> Result<Self, FileError> {
+        let mut json: Json = serde_json::from_str(text)?;
+        migrate(&mut json)?;
+        let file: Self = serde_json::from_value(json)?;
+        file.validate()?;
+        Ok(file)
+    }
+
+    /// Check every component has settings it can be built with
+    fn validate(&self) -> Result<(), FileError> {
+        let circuits = std::iter::once((None, &self.main)).chain(
+            self.subcircuits
+                .iter()
+                .map(|(name, entry)| (Some(name), entry)),
+        );
+        for (circuit, entry) in circuits {
+            for (index, component) in entry.components.iter().enumerate() {
+                component
+                    .validate()
+                    .map_err(|reason| FileError::InvalidComponent {
+                        circuit: circuit.cloned(),
+                        index,
+                        reason,
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        fs::write(path, self.to_json() + "\n")?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+/// Apply every migration between the file's version and `FORMAT_VERSION`
+fn migrate(json: &mut Json) -> Result<(), FileError> {
+    let version = json
+        .get("version")
+        .and_then(Json::as_u64)
+        .ok_or(FileError::MissingVersion)?;
+    if version == 0 || version > FORMAT_VERSION as u64 {
+        return Err(FileError::UnsupportedVersion(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        migration(json);
+    }
+    json["version"] = FORMAT_VERSION.into();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::clock::ClockTiming;
+    use crate::node::Edge;
+    use crate::schematic::nets::Direction;
+    use crate::simulation::Simulator;
+
+    #[test]
+    fn round_trip() {
+        let mut schematic = Schematic::default();
+        schematic.wires.insert(
+            (1, 2),
+            WireCell {
+                north: true,
+                east: true,
+                south: true,
+                west: true,
+                junction: true,
+            },
+        );
+        schematic.wires.insert(
+            (0, 2),
+            WireCell {
+                east: true,
+                ..WireCell::default()
+            },
+        );
+        let mut gate = PlacedComponent::new(ComponentKind::XorGate, (4, -3));
+        gate.facing = Direction::North;
+        gate.bit_width = 8;
+        gate.input_count = 3;
+        gate.label = "parity".to_string();
+        schematic.components.push(gate);
+        let library = Library::from([("half adder".to_string(), schematic.clone())]);
+
+        let editor = EditorState {
+            camera: [1.5, -2.0],
+            zoom: 80.0,
+            hotbar: vec![Some(ComponentKind::AndGate), None],
+        };
+        let file = ProjectFile::new(&schematic, &library, editor);
+        let text = file.to_json();
+        assert!(text.contains("\"connections\": \"NESW\""));
+
+        let loaded = ProjectFile::from_json(&text).unwrap();
+        assert_eq!(loaded, file);
+        assert_eq!(loaded.schematic().wires, schematic.wires);
+        assert_eq!(loaded.schematic().components, schematic.components);
+        assert_eq!(loaded.library()["half adder"].wires, schematic.wires);
+    }
+
+    #[test]
+    fn rejects_unknown_versions() {
+        assert!(matches!(
+            ProjectFile::from_json(r#"{"version": 99, "wires": [], "components": []}"#),
+            Err(FileError::UnsupportedVersion(99))
+        ));
+        assert!(matches!(
+            ProjectFile::from_json(r#"{"wires": [], "components": []}"#),
+            Err(FileError::MissingVersion)
+        ));
+
+        // The editor section is optional, and version 1 files gain an empty library
+        let file = ProjectFile::from_json(r#"{"version": 1, "wires": [], "components": []}"#);
+        let file = file.unwrap();
+        assert_eq!(file.editor, EditorState::default());
+        assert!(file.subcircuits.is_empty());
+        assert_eq!(file.version, FORMAT_VERSION);
+
+        // Not truncated to version 2
+        assert!(matches!(
+            ProjectFile::from_json(r#"{"version": 4294967298, "wires": [], "components": []}"#),
+            Err(FileError::UnsupportedVersion(4294967298))
+        ));
+    }
+
+    /// Load a file holding `component` in the main circuit, and return the reason it fails
+    fn load_error(component: PlacedComponent) -> String {
+        let mut schematic = Schematic::default();
+        schematic.components.push(component);
+        let text = ProjectFile::new(&schematic, &Library::new(), EditorState::default()).to_json();
+        match ProjectFile::from_json(&text) {
+            Err(FileError::InvalidComponent {
+                circuit: None,
+                index: 0,
+                reason,
+            }) => reason,
+            other => panic!("expected an invalid component, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_zero_bit_width() {
+        let mut input = PlacedComponent::new(ComponentKind::Input, (0, 0));
+        input.bit_width = 0;
+        assert_eq!(load_error(input), "bit width 0 is not between 1 and 255");
+
+        let mut adder = PlacedComponent::new(ComponentKind::Adder, (0, 0));
+        adder.bit_width = 65;
+        assert_eq!(load_error(adder), "bit width 65 is not between 1 and 64");
+    }
+
+    #[test]
+    fn rejects_gate_input_counts() {
+        let mut gate = PlacedComponent::new(ComponentKind::AndGate, (0, 0));
+        gate.input_count = 200;
+        assert_eq!(load_error(gate), "input count 200 is not between 2 and 32");
+
+        // NOT gates ignore their input count
+        let mut not = PlacedComponent::new(ComponentKind::NotGate, (0, 0));
+        not.input_count = 200;
+        assert!(not.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_select_widths() {
+        let mut mux = PlacedComponent::new(ComponentKind::Multiplexer, (0, 0));
+        mux.settings = ComponentSettings::SelectWidth(9);
+        assert_eq!(load_error(mux), "select width 9 is not between 1 and 5");
+    }
+
+    #[test]
+    fn rejects_address_widths() {
+        let mut ram = PlacedComponent::new(ComponentKind::Ram, (0, 0));
+        ram.memory_mut().unwrap().address_width = 40;
+        assert_eq!(load_error(ram), "address width 40 is not between 1 and 16");
+    }
+
+    #[test]
+    fn rejects_memory_words_wider_than_64_bits() {
+        let mut rom = PlacedComponent::new(ComponentKind::Rom, (0, 0));
+        rom.bit_width = 128;
+        assert_eq!(load_error(rom), "bit width 128 is not between 1 and 64");
+    }
+
+    #[test]
+    fn loads_the_longest_clock_timing() {
+        let mut clock = PlacedComponent::new(ComponentKind::Clock, (0, 0));
+        clock.settings = ComponentSettings::Timing(ClockTiming {
+            high_ticks: u32::MAX,
+            low_ticks: u32::MAX,
+        });
+        let schematic = Schematic {
+            components: vec![clock],
+            ..Schematic::default()
+        };
+        let text = ProjectFile::new(&schematic, &Library::new(), EditorState::default()).to_json();
+        let file = ProjectFile::from_json(&text).unwrap();
+        let compiled = file.schematic().compile(&file.library()).unwrap();
+        let mut simulator = Simulator::new(compiled.circuit);
+        simulator.run_ticks(3).unwrap();
+    }
+
+    #[test]
+    fn rejects_settings_of_another_kind() {
+        let mut gate = PlacedComponent::new(ComponentKind::AndGate, (0, 0));
+        gate.settings = ComponentSettings::Value(3);
+        assert_eq!(load_error(gate), "AND Gate cannot have setting Value(3)");
+    }
+
+    #[test]
+    fn upgrades_component_settings() {
+        let text = r#"{
+            "version": 2,
+            "wires": [],
+            "components": [
+                {"kind": "DFlipFlop", "position": [0, 0], "facing": "East", "bit_width": 1,
+                    "input_count": 5, "trigger": "Falling"},
+                {"kind": "AndGate", "position": [0, 0], "facing": "East", "bit_width": 1,
+                    "input_count": 2, "value": 3}
+            ],
+            "subcircuits": {
+                "sub": {"wires": [], "components": [
+                    {"kind": "Multiplexer", "position": [0, 0], "facing": "East",
+                        "bit_width": 1, "input_count": 3}
+                ]}
+            }
+        }"#;
+        let file = ProjectFile::from_json(text).unwrap();
+        let settings: Vec<_> = file
+            .main
+            .components
+            .iter()
+            .chain(&file.subcircuits["sub"].components)
+            .map(|component| component.settings.clone())
+            .collect();
+        assert_eq!(
+            settings,
+            [
+                ComponentSettings::Trigger(Edge::Falling),
+                ComponentSettings::None,
+                ComponentSettings::SelectWidth(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn names_the_subcircuit_with_a_bad_component() {
+        let mut gate = PlacedComponent::new(ComponentKind::OrGate, (0, 0));
+        gate.bit_width = 0;
+        let definition = Schematic {
+            components: vec![PlacedComponent::new(ComponentKind::Input, (0, 0)), gate],
+            ..Schematic::default()
+        };
+        let library = Library::from([("broken".to_string(), definition)]);
+        let text = ProjectFile::new(&Schematic::default(), &library, EditorState::default());
+        let err = ProjectFile::from_json(&text.to_json()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "component 1 of subcircuit 'broken': bit width 0 is not between 1 and 32"
+        );
+    }
+}
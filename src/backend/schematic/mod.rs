@@ -0,0 +1,495 @@
+// Grid-based drawing of a circuit, shared by the frontend editor and the console runner
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::circuit::{Circuit, CircuitError, InputPin, NetId, OutputPin};
+use crate::node::NodeId;
+use components::{ComponentKind, PlacedComponent};
+use nets::{GridPos, NetMap, WireCell, extract_nets};
+
+pub mod components;
+pub mod file;
+pub mod nets;
+
+/// A circuit as drawn on the grid
+#[derive(Debug, Clone, Default)]
+pub struct Schematic {
+    pub wires: HashMap<GridPos, WireCell>,
+    pub components: Vec<PlacedComponent>,
+}
+
+/// Circuits that can be placed as subcircuits, by name
+pub type Library = BTreeMap<String, Schematic>;
+
+/// Error building a circuit from a schematic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    Circuit(CircuitError),
+    /// A subcircuit instance names a circuit missing from the library
+    UnknownSubcircuit(String),
+    /// A subcircuit contains an instance of itself, directly or further down
+    RecursiveSubcircuit(String),
+    /// A tunnel is a different width from the net it joins
+    TunnelWidth {
+        name: String,
+        expected: u8,
+        found: u8,
+    },
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Circuit(err) => write!(f, "{err}"),
+            Self::UnknownSubcircuit(name) => write!(f, "no subcircuit named '{name}'"),
+            Self::RecursiveSubcircuit(name) => write!(f, "subcircuit '{name}' contains itself"),
+            Self::TunnelWidth {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "tunnel '{name}' is {found} bits wide on a net of {expected} bits"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<CircuitError> for CompileError {
+    fn from(err: CircuitError) -> Self {
+        Self::Circuit(err)
+    }
+}
+
+/// Circuit built from a schematic, with the mapping from drawn nets to circuit nets
+pub struct CompiledSchematic {
+    pub circuit: Circuit,
+    pub root: CompiledInstance,
+}
+
+/// One schematic's part of a compiled circuit: the top level, or a subcircuit instance
+#[derive(Default)]
+pub struct CompiledInstance {
+    pub wire_nets: NetMap,
+    pub net_ids: Vec<NetId>,                  // wire net index -> circuit net
+    pub component_nodes: Vec<Option<NodeId>>, // component index -> circuit node, if it has one
+    pub children: HashMap<usize, CompiledInstance>, // component index -> subcircuit instance
+}
+
+impl CompiledInstance {
+    /// Instance reached by following subcircuit component indices down from this one
+    pub fn descendant(&self, path: &[usize]) -> Option<&CompiledInstance> {
+        match path.split_first() {
+            Some((index, rest)) => self.children.get(index)?.descendant(rest),
+            None => Some(self),
+        }
+    }
+}
+
+impl Schematic {
+    /// Indices of the input and output pin components, each sorted top to bottom then left to
+    /// right. This is the pin order of the schematic's subcircuit instances.
+    pub fn ports(&self) -> (Vec<usize>, Vec<usize>) {
+        let sorted = |kind| {
+            let mut ports: Vec<usize> = (0..self.components.len())
+                .filter(|&index| self.components[index].kind == kind)
+                .collect();
+            ports.sort_by_key(|&index| {
+                let (x, y) = self.components[index].position;
+                (y, x)
+            });
+            ports
+        };
+        (sorted(ComponentKind::Input), sorted(ComponentKind::Output))
+    }
+
+    /// Update the pins of every subcircuit instance to match its definition in `library`
+    pub fn match_library(&mut self, library: &Library) {
+        for component in &mut self.components {
+            if let Some(definition) = component.subcircuit.as_ref().and_then(|n| library.get(n)) {
+                component.match_definition(definition);
+            }
+        }
+    }
+
+    /// Build the circuit. A pin is connected to the wire net covering its cell; pins with no
+    /// wire on them are left unconnected. Each net takes the width of the first pin on it, and
+    /// any other pin of a different width is a `WidthMismatch`. Nets with tunnels of the same
+    /// name on them are one net, and a tunnel of a different width from the other pins on that
+    /// net, or from the other tunnels of its name, is a `TunnelWidth` wherever it was placed.
+    ///
+    /// Subcircuits are flattened into the same circuit: the nets on an instance's pins are
+    /// shared with the nets on the matching pin components inside it.
+    pub fn compile(&self, library: &Library) -> Result<CompiledSchematic, CompileError> {
+        let mut circuit = Circuit::new();
+        let root = self.compile_into(&mut circuit, library, None, &mut Vec::new())?;
+        Ok(CompiledSchematic { circuit, root })
+    }
+
+    /// Add this schematic to `circuit`. `bindings` holds the outer net of each input pin then
+    /// each output pin when compiling a subcircuit instance; bound pins get no node of their
+    /// own. `stack` names the subcircuits being compiled, to catch recursion.
+    fn compile_into(
+        &self,
+        circuit: &mut Circuit,
+        library: &Library,
+        bindings: Option<&[Option<NetId>]>,
+        stack: &mut Vec<String>,
+    ) -> Result<CompiledInstance, CompileError> {
+        let wire_nets = extract_nets(&self.wires);
+
+        // Subcircuit instances take their pins from the definition, whatever they were saved with
+        let mut components = self.components.clone();
+        let mut definitions = HashMap::new();
+        for (index, component) in components.iter_mut().enumerate() {
+            let Some(name) = component.subcircuit.clone() else {
+                continue;
+            };
+            let definition = library
+                .get(&name)
+                .ok_or_else(|| CompileError::UnknownSubcircuit(name.clone()))?;
+            if stack.contains(&name) {
+                return Err(CompileError::RecursiveSubcircuit(name));
+            }
+            component.match_definition(definition);
+            definitions.insert(index, (name, definition));
+        }
+
+        let joined = join_tunnels(&components, &wire_nets);
+
+        let mut bound_nets: HashMap<usize, NetId> = HashMap::new();
+        let mut bound_ports = Vec::new();
+        if let Some(bindings) = bindings {
+            let (inputs, outputs) = self.ports();
+            for (index, binding) in inputs.into_iter().chain(outputs).zip(bindings) {
+                bound_ports.push(index);
+                let component = &components[index];
+                let pins = component
+                    .input_pins()
+                    .into_iter()
+                    .chain(component.output_pins());
+                for pos in pins {
+                    if let (Some(net), Some(outer)) = (wire_nets.net_at_cell(pos), binding) {
+                        bound_nets.entry(joined[net]).or_insert(*outer);
+                    }
+                }
+            }
+        }
+
+        let logic: Vec<_> = components
+            .iter()
+            .enumerate()
+            .map(|(index, c)| c.build().filter(|_| !bound_ports.contains(&index)))
+            .collect();
+
+        // Tunnels come last, so they are checked against the other pins on their net rather
+        // than setting its width
+        let mut order: Vec<usize> = (0..components.len()).collect();
+        order.sort_by_key(|&index| components[index].kind == ComponentKind::Tunnel);
+        let mut widths = vec![None; wire_nets.nets.len()];
+        for index in order {
+            let component = &components[index];
+            let (input_widths, output_widths) = match (&logic[index], definitions.get(&index)) {
+                (Some(logic), _) => (
+                    (0..logic.input_count())
+                        .map(|pin| logic.input_width(pin))
+                        .collect(),
+                    (0..logic.output_count())
+                        .map(|pin| logic.output_width(pin))
+                        .collect(),
+                ),
+                (None, Some((_, definition))) => definition.port_widths(),
+                (None, None) if component.kind == ComponentKind::Tunnel => {
+                    (vec![component.bit_width], Vec::new())
+                }
+                (None, None) => continue,
+            };
+            let inputs = component.input_pins().into_iter().zip(input_widths);
+            let outputs = component.output_pins().into_iter().zip(output_widths);
+            for (pos, width) in inputs.chain(outputs) {
+                let Some(net) = wire_nets.net_at_cell(pos) else {
+                    continue;
+                };
+                let expected = *widths[joined[net]].get_or_insert(width);
+                if component.kind == ComponentKind::Tunnel && expected != width {
+                    return Err(CompileError::TunnelWidth {
+                        name: component.label.clone(),
+                        expected,
+                        found: width,
+                    });
+                }
+            }
+        }
+
+        // Nets joined by tunnels share the circuit net of the first of them
+        let mut circuit_nets: HashMap<usize, NetId> = HashMap::new();
+        let net_ids: Vec<NetId> = joined
+            .iter()
+            .map(|&net| {
+                *circuit_nets
+                    .entry(net)
+                    .or_insert_with(|| match bound_nets.get(&net) {
+                        Some(outer) => *outer,
+                        None => circuit.add_net(widths[net].unwrap_or(1)),
+                    })
+            })
+            .collect();
+
+        let mut component_nodes = Vec::with_capacity(components.len());
+        for (component, logic) in components.iter().zip(logic) {
+            let Some(logic) = logic else {
+                component_nodes.push(None);
+                continue;
+            };
+            let node = match component.kind {
+                ComponentKind::Input => circuit.add_input(logic),
+                ComponentKind::Output => circuit.add_output(logic),
+                _ => circuit.add_node(logic),
+            };
+            for (pin, pos) in component.input_pins().into_iter().enumerate() {
+                if let Some(net) = wire_nets.net_at_cell(pos) {
+                    circuit.attach_input(net_ids[net], InputPin::new(node, pin))?;
+                }
+            }
+            for (pin, pos) in component.output_pins().into_iter().enumerate() {
+                if let Some(net) = wire_nets.net_at_cell(pos) {
+                    circuit.attach_output(net_ids[net], OutputPin::new(node, pin))?;
+                }
+            }
+            component_nodes.push(Some(node));
+        }
+
+        let mut children = HashMap::new();
+        for (index, (name, definition)) in definitions {
+            let component = &components[index];
+            let pins = component
+                .input_pins()
+                .into_iter()
+                .chain(component.output_pins());
+            let bindings: Vec<Option<NetId>> = pins
+                .map(|pos| wire_nets.net_at_cell(pos).map(|net| net_ids[net]))
+                .collect();
+            stack.push(name);
+            let child = definition.compile_into(circuit, library, Some(&bindings), stack)?;
+            stack.pop();
+            children.insert(index, child);
+        }
+
+        Ok(CompiledInstance {
+            wire_nets,
+            net_ids,
+            component_nodes,
+            children,
+        })
+    }
+
+    /// Bit widths of the pins an instance of this schematic has, inputs then outputs
+    fn port_widths(&self) -> (Vec<u8>, Vec<u8>) {
+        let (inputs, outputs) = self.ports();
+        let widths = |ports: Vec<usize>| {
+            ports
+                .into_iter()
+                .map(|index| self.components[index].bit_width)
+                .collect()
+        };
+        (widths(inputs), widths(outputs))
+    }
+}
+
+/// For each wire net, the lowest-numbered net joined to it by tunnels. Tunnels join the nets on
+/// their pins when they have the same name; unnamed tunnels join nothing.
+fn join_tunnels(components: &[PlacedComponent], wire_nets: &NetMap) -> Vec<usize> {
+    fn root(joined: &mut [usize], mut net: usize) -> usize {
+        while joined[net] != net {
+            joined[net] = joined[joined[net]];
+            net = joined[net];
+        }
+        net
+    }
+
+    let mut joined: Vec<usize> = (0..wire_nets.nets.len()).collect();
+    let mut named: HashMap<&str, usize> = HashMap::new();
+    for tunnel in components {
+        if tunnel.kind != ComponentKind::Tunnel || tunnel.label.is_empty() {
+            continue;
+        }
+        for pos in tunnel.input_pins() {
+            let Some(net) = wire_nets.net_at_cell(pos) else {
+                continue;
+            };
+            let first = *named.entry(tunnel.label.as_str()).or_insert(net);
+            let (a, b) = (root(&mut joined, first), root(&mut joined, net));
+            joined[a.max(b)] = a.min(b);
+        }
+    }
+    (0..joined.len())
+        .map(|net| root(&mut joined, net))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulator;
+    use crate::value::Value;
+    use components::ComponentKind;
+
+    fn horizontal(west: bool, east: bool) -> WireCell {
+        WireCell {
+            west,
+            east,
+            ..WireCell::default()
+        }
+    }
+
+    #[test]
+    fn pins_attach_to_wires_on_their_cells() {
+        // NOT gate at the origin with a wire leaving its output, feeding a second NOT gate
+        let mut schematic = Schematic::default();
+        schematic
+            .components
+            .push(PlacedComponent::new(ComponentKind::NotGate, (0, 0)));
+        schematic
+            .components
+            .push(PlacedComponent::new(ComponentKind::NotGate, (5, 0)));
+        schematic.wires.insert((3, 1), horizontal(false, true));
+        schematic.wires.insert((4, 1), horizontal(true, false));
+
+        let compiled = schematic.compile(&Library::new()).unwrap();
+        let root = compiled.root;
+        let net = root.wire_nets.net_at_cell((3, 1)).unwrap();
+        let mut simulator = Simulator::new(compiled.circuit);
+        simulator.settle().unwrap();
+
+        // The first gate's input floats, so both wires carry X
+        let value = simulator.net_value(root.net_ids[net]).unwrap();
+        assert_eq!(value, &Value::unknown(1));
+        let second = root.component_nodes[1].unwrap();
+        let circuit = simulator.circuit();
+        assert_eq!(
+            circuit.input_net(InputPin::new(second, 0)),
+            Some(root.net_ids[net])
+        );
+    }
+
+    /// Input pin, wire, `component` with an input and output on row `y`, wire, output pin
+    fn chain(component: PlacedComponent, y: i32) -> Schematic {
+        let x = component.position.0;
+        let (width, _) = component.size();
+        let mut schematic = Schematic::default();
+        for start in [x - 2, x + width] {
+            schematic.wires.insert((start, y), horizontal(false, true));
+            schematic
+                .wires
+                .insert((start + 1, y), horizontal(true, false));
+        }
+        let input = PlacedComponent::new(ComponentKind::Input, (x - 4, y));
+        let output = PlacedComponent::new(ComponentKind::Output, (x + width + 2, y));
+        schematic.components = vec![input, component, output];
+        schematic
+    }
+
+    #[test]
+    fn subcircuits_share_nets_with_their_instances() {
+        let inverter = chain(PlacedComponent::new(ComponentKind::NotGate, (0, -1)), 0);
+        let library = Library::from([("inverter".to_string(), inverter)]);
+        let instance = PlacedComponent::subcircuit("inverter", &library["inverter"], (0, -1));
+        let top = chain(instance, 0);
+
+        let compiled = top.compile(&library).unwrap();
+        let root = compiled.root;
+        let inner = root.descendant(&[1]).unwrap();
+        let mut simulator = Simulator::new(compiled.circuit);
+        simulator.settle().unwrap();
+        let output = simulator.circuit().outputs()[0];
+        assert_eq!(
+            simulator.input_value(InputPin::new(output, 0)),
+            Some(&Value::new(1, 1))
+        );
+
+        // The outer input drives the inner net directly, with no node in between
+        let outer_net = root.net_ids[root.wire_nets.net_at_cell((-1, 0)).unwrap()];
+        let inner_net = inner.net_ids[inner.wire_nets.net_at_cell((-1, 0)).unwrap()];
+        assert_eq!(outer_net, inner_net);
+        assert_eq!(inner.component_nodes[0], None);
+        assert_eq!(simulator.circuit().inputs().len(), 1);
+    }
+
+    #[test]
+    fn tunnels_with_the_same_name_join_nets() {
+        // A wire leading to each tunnel, two of them named the same
+        let mut schematic = Schematic::default();
+        let mut tunnel = |position, name: &str| {
+            let mut tunnel = PlacedComponent::new(ComponentKind::Tunnel, position);
+            tunnel.label = name.to_string();
+            schematic.components.push(tunnel);
+        };
+        tunnel((3, 0), "a");
+        tunnel((3, 4), "a");
+        tunnel((3, 8), "b");
+        for y in [0, 4, 8] {
+            schematic.wires.insert((1, y), horizontal(false, true));
+            schematic.wires.insert((2, y), horizontal(true, false));
+        }
+
+        let compiled = schematic.compile(&Library::new()).unwrap();
+        let root = compiled.root;
+        let net_at = |y| root.net_ids[root.wire_nets.net_at_cell((2, y)).unwrap()];
+        assert_eq!(net_at(0), net_at(4));
+        assert_ne!(net_at(0), net_at(8));
+
+        schematic.components[1].bit_width = 4;
+        assert_eq!(
+            schematic.compile(&Library::new()).err(),
+            Some(CompileError::TunnelWidth {
+                name: "a".to_string(),
+                expected: 1,
+                found: 4,
+            })
+        );
+    }
+
+    #[test]
+    fn tunnel_widths_are_checked_in_any_order() {
+        // An 8-bit input wired straight to a 1-bit tunnel
+        let mut input = PlacedComponent::new(ComponentKind::Input, (0, 0));
+        input.bit_width = 8;
+        let (x, y) = input.output_pins()[0];
+        let mut tunnel = PlacedComponent::new(ComponentKind::Tunnel, (0, 0));
+        tunnel.label = "bus".to_string();
+        let (pin_x, pin_y) = tunnel.input_pins()[0];
+        tunnel.position = (x + 3 - pin_x, y - pin_y);
+        let mut schematic = Schematic::default();
+        for cell in x..=x + 3 {
+            schematic
+                .wires
+                .insert((cell, y), horizontal(cell > x, cell < x + 3));
+        }
+
+        let error = Some(CompileError::TunnelWidth {
+            name: "bus".to_string(),
+            expected: 8,
+            found: 1,
+        });
+        schematic.components = vec![input.clone(), tunnel.clone()];
+        assert_eq!(schematic.compile(&Library::new()).err(), error);
+        schematic.components = vec![tunnel, input];
+        assert_eq!(schematic.compile(&Library::new()).err(), error);
+    }
+
+    #[test]
+    fn rejects_recursive_subcircuits() {
+        let mut library = Library::new();
+        library.insert("loop".to_string(), Schematic::default());
+        let instance = PlacedComponent::subcircuit("loop", &library["loop"], (0, 0));
+        library.get_mut("loop").unwrap().components.push(instance);
+        let top = library["loop"].clone();
+        assert_eq!(
+            top.compile(&library).err(),
+            Some(CompileError::RecursiveSubcircuit("loop".to_string()))
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+/// Cell coordinates on the grid. North is towards negative y.
+pub type GridPos = (i32, i32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    pub fn offset(&self) -> GridPos {
+        match self {
+            Self::North => (0, -1),
+            Self::East => (1, 0),
+            Self::South => (0, 1),
+            Self::West => (-1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Self::North => Self::South,
+            Self::East => Self::West,
+            Self::South => Self::North,
+            Self::West => Self::East,
+        }
+    }
+
+    pub fn is_vertical(&self) -> bool {
+        matches!(self, Self::North | Self::South)
+    }
+
+    pub fn step(&self, pos: GridPos) -> GridPos {
+        let (dx, dy) = self.offset();
+        (pos.0 + dx, pos.1 + dy)
+    }
+}
+
+/// Connections of a single wire cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct WireCell {
+    pub north: bool,
+    pub east: bool,
+    pub south: bool,
+    pub west: bool,
+    pub junction: bool, // only meaningful with all four connections
+}
+
+impl WireCell {
+    pub fn connects(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::North => self.north,
+            Direction::East => self.east,
+            Direction::South => self.south,
+            Direction::West => self.west,
+        }
+    }
+
+    /// A four-way cell without a junction: the vertical and horizontal wires cross without
+    /// connecting
+    pub fn is_overpass(&self) -> bool {
+        self.north && self.east && self.south && self.west && !self.junction
+    }
+
+    /// The part of this cell reached by a wire arriving from `direction`
+    fn channel_towards(&self, direction: Direction) -> Channel {
+        if !self.is_overpass() {
+            Channel::Whole
+        } else if direction.is_vertical() {
+            Channel::Vertical
+        } else {
+            Channel::Horizontal
+        }
+    }
+}
+
+/// The part of a cell a net occupies. Only overpasses are split in two.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Channel {
+    Whole,
+    Vertical,
+    Horizontal,
+}
+
+impl Channel {
+    fn allows(&self, direction: Direction) -> bool {
+        match self {
+            Self::Whole => true,
+            Self::Vertical => direction.is_vertical(),
+            Self::Horizontal => !direction.is_vertical(),
+        }
+    }
+}
+
+/// Set of wire cells that are electrically connected
+#[derive(Debug, Clone, Default)]
+pub struct WireNet {
+    pub cells: Vec<(GridPos, Channel)>,
+}
+
+/// Every net in a drawing, with a lookup from cell to net
+#[derive(Debug, Clone, Default)]
+pub struct NetMap {
+    pub nets: Vec<WireNet>,
+    lookup: HashMap<(GridPos, Channel), usize>,
+}
+
+impl NetMap {
+    /// Index of the net occupying `channel` of the cell at `pos`
+    pub fn net_at(&self, pos: GridPos, channel: Channel) -> Option<usize> {
+        self.lookup.get(&(pos, channel)).copied()
+    }
+
+    /// Index of the net a pin placed on `pos` touches. Pins cannot attach to overpasses.
+    pub fn net_at_cell(&self, pos: GridPos) -> Option<usize> {
+        self.net_at(pos, Channel::Whole)
+    }
+
+    /// Indices of every net passing through `pos`
+    pub fn nets_at(&self, pos: GridPos) -> impl Iterator<Item = usize> + '_ {
+        [Channel::Whole, Channel::Vertical, Channel::Horizontal]
+            .into_iter()
+            .filter_map(move |channel| self.net_at(pos, channel))
+    }
+}
+
+/// Flood fill the drawing into nets
+///
+/// Two neighbouring cells are connected when each has a connection towards the other. Overpass
+/// cells carry their vertical and horizontal wires in separate nets; junctions and every other
+/// cell join all their connections.
+pub fn extract_nets(cells: &HashMap<GridPos, WireCell>) -> NetMap {
+    let mut positions: Vec<GridPos> = cells.keys().copied().collect();
+    positions.sort_unstable();
+
+    let mut map = NetMap::default();
+    for pos in positions {
+        let cell = cells[&pos];
+        let channels: &[Channel] = if cell.is_overpass() {
+            &[Channel::Vertical, Channel::Horizontal]
+        } else {
+            &[Channel::Whole]
+        };
+
+        for &channel in channels {
+            if map.lookup.contains_key(&(pos, channel)) {
+                continue;
+            }
+
+            let index = map.nets.len();
+            let mut net = WireNet::default();
+            let mut queue = VecDeque::from([(pos, channel)]);
+            map.lookup.insert((pos, channel), index);
+
+            while let Some((current, channel)) = queue.pop_front() {
+                net.cells.push((current, channel));
+                let cell = cells[&current];
+
+                for direction in Direction::ALL {
+                    if !channel.allows(direction) || !cell.connects(direction) {
+                        continue;
+                    }
+                    let next = direction.step(current);
+                    let Some(neighbour) = cells.get(&next) else {
+                        continue;
+                    };
+                    if !neighbour.connects(direction.opposite()) {
+                        continue;
+                    }
+                    let key = (next, neighbour.channel_towards(direction));
+                    if let Entry::Vacant(entry) = map.lookup.entry(key) {
+                        entry.insert(index);
+                        queue.push_back(key);
+                    }
+                }
+            }
+
+            map.nets.push(net);
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(connections: &str, junction: bool) -> WireCell {
+        WireCell {
+            north: connections.contains('N'),
+            east: connections.contains('E'),
+            south: connections.contains('S'),
+            west: connections.contains('W'),
+            junction,
+        }
+    }
+
+    /// A plus shape of five cells centred on the origin
+    fn cross(junction: bool) -> HashMap<GridPos, WireCell> {
+        HashMap::from([
+            ((0, 0), cell("NESW", junction)),
+            ((0, -1), cell("S", false)),
+            ((0, 1), cell("N", false)),
+            ((1, 0), cell("W", false)),
+            ((-1, 0), cell("E", false)),
+        ])
+    }
+
+    #[test]
+    fn overpass_keeps_wires_apart() {
+        let map = extract_nets(&cross(false));
+        assert_eq!(map.nets.len(), 2);
+
+        let vertical = map.net_at((0, 0), Channel::Vertical).unwrap();
+        let horizontal = map.net_at((0, 0), Channel::Horizontal).unwrap();
+        assert_ne!(vertical, horizontal);
+        assert_eq!(map.net_at_cell((0, -1)), Some(vertical));
+        assert_eq!(map.net_at_cell((0, 1)), Some(vertical));
+        assert_eq!(map.net_at_cell((1, 0)), Some(horizontal));
+        assert_eq!(map.net_at_cell((0, 0)), None);
+        assert_eq!(map.nets_at((0, 0)).count(), 2);
+    }
+
+    #[test]
+    fn junction_joins_all_wires() {
+        let map = extract_nets(&cross(true));
+        assert_eq!(map.nets.len(), 1);
+        assert_eq!(map.nets[0].cells.len(), 5);
+    }
+
+    #[test]
+    fn one_sided_connection_does_not_join() {
+        let cells = HashMap::from([((0, 0), cell("E", false)), ((1, 0), cell("", false))]);
+        let map = extract_nets(&cells);
+        assert_eq!(map.nets.len(), 2);
+    }
+}
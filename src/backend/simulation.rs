@@ -0,0 +1,564 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::circuit::{Circuit, InputPin, NetId, OutputPin};
+use crate::node::NodeId;
+use crate::value::Value;
+
+/// Number of steps a single `settle` may take before the circuit is considered oscillating
+pub const DEFAULT_ITERATION_LIMIT: usize = 1000;
+
+/// Output values of the nodes evaluated in one step
+pub type StepSnapshot = Vec<(NodeId, Vec<Value>)>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulationError {
+    /// The circuit kept re-triggering without settling within the iteration budget
+    Oscillation {
+        /// Nodes that take part in the oscillation, sorted by id
+        nodes: Vec<NodeId>,
+        /// One snapshot per step of the repeating cycle. Empty if no exact repeat was found.
+        cycle: Vec<StepSnapshot>,
+        /// Steps taken before giving up
+        iterations: usize,
+    },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Oscillation {
+                nodes,
+                cycle,
+                iterations,
+            } => {
+                write!(
+                    f,
+                    "oscillation detected after {} iterations in nodes {:?}",
+                    iterations, nodes
+                )?;
+                if !cycle.is_empty() {
+                    write!(f, " (cycle of {} steps)", cycle.len())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+/// Event-driven simulator
+///
+/// Only nodes whose inputs changed are re-evaluated. Each step evaluates every scheduled node
+/// against the net values committed by the previous step, then resolves the nets driven by any
+/// output that changed and schedules their readers. This makes one step equal one gate delay.
+pub struct Simulator {
+    circuit: Circuit,
+    signals: Vec<Value>, // committed value of every net, read by downstream nodes
+    pending: Vec<NodeId>,
+    scheduled: HashSet<NodeId>,
+    tick: u64,
+    iteration_limit: usize,
+}
+
+impl Simulator {
+    /// Take ownership of a circuit and schedule every node so its initial outputs get computed
+    pub fn new(circuit: Circuit) -> Self {
+        let mut simulator = Self {
+            signals: Vec::new(),
+            circuit,
+            pending: Vec::new(),
+            scheduled: HashSet::new(),
+            tick: 0,
+            iteration_limit: DEFAULT_ITERATION_LIMIT,
+        };
+        simulator.signals = (0..simulator.circuit.nets().len() as NetId)
+            .map(|net| simulator.resolve_net(net))
+            .collect();
+        for id in 0..simulator.circuit.nodes().len() as NodeId {
+            simulator.schedule(id);
+        }
+        simulator
+    }
+
+    pub fn circuit(&self) -> &Circuit {
+        &self.circuit
+    }
+
+    /// Mutable access to the circuit's nodes. Call `schedule` after changing a node's state.
+    pub fn circuit_mut(&mut self) -> &mut Circuit {
+        &mut self.circuit
+    }
+
+    /// Committed value of a net
+    pub fn net_value(&self, net: NetId) -> Option<&Value> {
+        self.signals.get(net as usize)
+    }
+
+    /// Value an output pin is currently driving
+    pub fn output_value(&self, pin: OutputPin) -> Option<&Value> {
+        self.circuit.node(pin.node)?.state.outputs.get(pin.pin)
+    }
+
+    /// Value an input pin last read
+    pub fn input_value(&self, pin: InputPin) -> Option<&Value> {
+        self.circuit.node(pin.node)?.state.inputs.get(pin.pin)
+    }
+
+    /// Queue a node for evaluation in the next step
+    pub fn schedule(&mut self, id: NodeId) {
+        if self.scheduled.insert(id) {
+            self.pending.push(id);
+        }
+    }
+
+    /// True when no node is waiting to be evaluated
+    pub fn is_settled(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Number of ticks elapsed since the simulator was created
+    pub fn tick_count(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn iteration_limit(&self) -> usize {
+        self.iteration_limit
+    }
+
+    /// Set how many steps `settle` may take before reporting an oscillation
+    pub fn set_iteration_limit(&mut self, limit: usize) {
+        self.iteration_limit = limit.max(1);
+    }
+
+    /// Value of a net from the current outputs of all its drivers. Weak drivers such as pull
+    /// resistors only set the bits no other driver drives. Undriven nets float.
+    fn resolve_net(&self, net: NetId) -> Value {
+        let (strong, weak) = self.driver_values(net, None);
+        let width = self.circuit.nets()[net as usize].width;
+        let value = if strong.is_empty() {
+            Value::high_z(width)
+        } else {
+            Value::resolve(&strong)
+        };
+        if weak.is_empty() {
+            value
+        } else {
+            value.pull(&Value::resolve(&weak))
+        }
+    }
+
+    /// Current outputs of a net's strong and weak drivers, leaving out those of node `skip`
+    fn driver_values(&self, net: NetId, skip: Option<NodeId>) -> (Vec<Value>, Vec<Value>) {
+        let mut strong = Vec::new();
+        let mut weak = Vec::new();
+        for pin in &self.circuit.nets()[net as usize].drivers {
+            if Some(pin.node) == skip {
+                continue;
+            }
+            let Some(node) = self.circuit.node(pin.node) else {
+                continue;
+            };
+            if let Some(value) = node.state.outputs.get(pin.pin) {
+                if node.logic.drives_weakly() {
+                    weak.push(value.clone());
+                } else {
+                    strong.push(value.clone());
+                }
+            }
+        }
+        (strong, weak)
+    }
+
+    /// Values a bidirectional node reads on the input pins whose nets it also drives: each net
+    /// resolved from its other strong drivers. Empty for any other node.
+    fn bidirectional_inputs(&self, id: NodeId) -> Vec<(usize, Value)> {
+        let Some(node) = self.circuit.node(id) else {
+            return Vec::new();
+        };
+        if !node.logic.is_bidirectional() {
+            return Vec::new();
+        }
+        node.input_connections
+            .iter()
+            .enumerate()
+            .filter_map(|(pin, net)| {
+                let net = (*net)?;
+                if !node.output_connections.contains(&Some(net)) {
+                    return None;
+                }
+                let (strong, _) = self.driver_values(net, Some(id));
+                let value = if strong.is_empty() {
+                    Value::high_z(self.circuit.nets()[net as usize].width)
+                } else {
+                    Value::resolve(&strong)
+                };
+                Some((pin, value))
+            })
+            .collect()
+    }
+
+    /// Re-resolve the nets driven by the given output pins and schedule the readers of every
+    /// net whose value changed. Bidirectional readers are scheduled even when it did not, as
+    /// what they read depends on which driver drives what.
+    fn commit(&mut self, changed: Vec<OutputPin>) {
+        let mut nets: Vec<NetId> = changed
+            .into_iter()
+            .filter_map(|pin| self.circuit.output_net(pin))
+            .collect();
+        nets.sort_unstable();
+        nets.dedup();
+
+        let mut readers = Vec::new();
+        for net in nets {
+            let value = self.resolve_net(net);
+            let net_readers = self.circuit.nets()[net as usize].readers.iter();
+            if self.signals[net as usize] != value {
+                self.signals[net as usize] = value;
+                readers.extend(net_readers.map(|pin| pin.node));
+            } else {
+                readers.extend(net_readers.map(|pin| pin.node).filter(|&id| {
+                    self.circuit
+                        .node(id)
+                        .is_some_and(|node| node.logic.is_bidirectional())
+                }));
+            }
+        }
+        for id in readers {
+            self.schedule(id);
+        }
+    }
+
+    /// Evaluate every scheduled node once. Returns true if more events are pending.
+    pub fn step(&mut self) -> bool {
+        self.step_batch();
+        !self.is_settled()
+    }
+
+    /// Evaluate every scheduled node once and return the nodes that were evaluated
+    fn step_batch(&mut self) -> Vec<NodeId> {
+        let batch = std::mem::take(&mut self.pending);
+        self.scheduled.clear();
+
+        let mut changed = Vec::new();
+        for id in &batch {
+            let bidirectional = self.bidirectional_inputs(*id);
+            if let Some(node) = self.circuit.node_mut(*id) {
+                node.read_values(&self.signals);
+                for (pin, value) in bidirectional {
+                    node.state.inputs[pin] = value;
+                }
+                node.state.input_hash = hash_values(&node.state.inputs);
+                node.state.generation = node.state.generation.wrapping_add(1);
+                changed.extend(node.update().into_iter().map(|pin| OutputPin::new(*id, pin)));
+            }
+        }
+
+        // Commit after evaluating the whole batch so every node in a step sees the same signals
+        self.commit(changed);
+
+        batch
+    }
+
+    fn reset_generations(&mut self) {
+        for node in self.circuit.nodes_mut() {
+            node.state.generation = 0;
+        }
+    }
+
+    /// Step until no events remain. Returns the number of steps taken, or an error if the
+    /// circuit is still active after `iteration_limit` steps.
+    ///
+    /// After an error the remaining events are dropped, leaving the nets as the last step
+    /// left them. Settling again will not resume the oscillation, so rebuild the simulator to
+    /// get back to a consistent state.
+    pub fn settle(&mut self) -> Result<usize, SimulationError> {
+        self.reset_generations();
+
+        let mut steps = 0;
+        while !self.is_settled() {
+            if steps >= self.iteration_limit {
+                let error = self.diagnose_oscillation(steps);
+                self.pending.clear();
+                self.scheduled.clear();
+                return Err(error);
+            }
+            self.step_batch();
+            steps += 1;
+        }
+        Ok(steps)
+    }
+
+    /// Keep stepping an unsettled circuit to find the nodes involved and the cycle they repeat.
+    ///
+    /// A step is identified by the inputs and resulting outputs of every node it evaluated, so
+    /// seeing the same step twice means the simulation has entered a loop.
+    fn diagnose_oscillation(&mut self, iterations: usize) -> SimulationError {
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        let mut snapshots: Vec<StepSnapshot> = Vec::new();
+        self.reset_generations();
+
+        for index in 0..self.iteration_limit {
+            if self.is_settled() {
+                break;
+            }
+
+            let mut batch = self.step_batch();
+            batch.sort_unstable();
+
+            let mut hasher = DefaultHasher::new();
+            let mut snapshot = StepSnapshot::new();
+            for id in batch {
+                let Some(node) = self.circuit.node(id) else {
+                    continue;
+                };
+                id.hash(&mut hasher);
+                node.state.input_hash.hash(&mut hasher);
+                node.state.outputs.hash(&mut hasher);
+                snapshot.push((id, node.state.outputs.clone()));
+            }
+
+            let key = hasher.finish();
+            if let Some(&start) = seen.get(&key) {
+                let cycle = snapshots.split_off(start);
+                let mut nodes: Vec<NodeId> = cycle
+                    .iter()
+                    .flat_map(|step| step.iter().map(|(id, _)| *id))
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect();
+                nodes.sort_unstable();
+                return SimulationError::Oscillation {
+                    nodes,
+                    cycle,
+                    iterations: iterations + index + 1,
+                };
+            }
+
+            seen.insert(key, snapshots.len());
+            snapshots.push(snapshot);
+        }
+
+        // No exact repeat, so report every node that kept being evaluated
+        let mut nodes: Vec<NodeId> = self
+            .circuit
+            .nodes()
+            .iter()
+            .filter(|node| node.state.generation > 0)
+            .map(|node| node.id)
+            .collect();
+        nodes.sort_unstable();
+        SimulationError::Oscillation {
+            nodes,
+            cycle: Vec::new(),
+            iterations: iterations + snapshots.len(),
+        }
+    }
+
+    /// Advance simulation time by one tick, then settle. Returns the number of steps taken.
+    pub fn tick(&mut self) -> Result<usize, SimulationError> {
+        self.tick += 1;
+
+        let mut changed = Vec::new();
+        for node in self.circuit.nodes_mut() {
+            let id = node.id;
+            changed.extend(node.tick().into_iter().map(|pin| OutputPin::new(id, pin)));
+        }
+        self.commit(changed);
+
+        self.settle()
+    }
+
+    /// Run `ticks` ticks, settling after each one. Stops at the first tick that fails to settle.
+    pub fn run_ticks(&mut self, ticks: u64) -> Result<(), SimulationError> {
+        for _ in 0..ticks {
+            self.tick()?;
+        }
+        Ok(())
+    }
+}
+
+fn hash_values(values: &[Value]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    values.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::component::clock::{Clock, ClockTiming};
+    use crate::component::gates::{Gate, GateKind};
+    use crate::component::io::{Input, VALUE_KEY};
+
+    /// Drive `value` from input node `id` and schedule it, the way the front ends poke inputs
+    fn set_input(simulator: &mut Simulator, id: NodeId, value: Value) {
+        let node = simulator.circuit_mut().node_mut(id).unwrap();
+        node.state.metadata.insert(VALUE_KEY.to_string(), value);
+        simulator.schedule(id);
+    }
+
+    fn output(simulator: &Simulator, id: NodeId) -> Value {
+        simulator
+            .output_value(OutputPin::new(id, 0))
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn each_step_is_one_gate_delay() {
+        let mut circuit = Circuit::new();
+        let input = circuit.add_input(Box::new(Input::new(1)));
+        let first = circuit.add_node(Box::new(Gate::new(GateKind::Not, 1, 1)));
+        let second = circuit.add_node(Box::new(Gate::new(GateKind::Not, 1, 1)));
+        circuit
+            .connect(OutputPin::new(input, 0), InputPin::new(first, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(first, 0), InputPin::new(second, 0))
+            .unwrap();
+        let mut simulator = Simulator::new(circuit);
+        simulator.settle().unwrap();
+        assert_eq!(output(&simulator, second), Value::new(0, 1));
+
+        // The change moves one gate further along the chain with each step
+        set_input(&mut simulator, input, Value::new(1, 1));
+        assert!(simulator.step());
+        assert_eq!(output(&simulator, input), Value::new(1, 1));
+        assert_eq!(output(&simulator, first), Value::new(1, 1));
+        assert!(simulator.step());
+        assert_eq!(output(&simulator, first), Value::new(0, 1));
+        assert_eq!(output(&simulator, second), Value::new(0, 1));
+        assert!(!simulator.step());
+        assert_eq!(output(&simulator, second), Value::new(1, 1));
+    }
+
+    #[test]
+    fn settle_reaches_a_fixpoint() {
+        let mut circuit = Circuit::new();
+        let a = circuit.add_input(Box::new(Input::new(4)));
+        let b = circuit.add_input(Box::new(Input::new(4)));
+        let and = circuit.add_node(Box::new(Gate::new(GateKind::And, 2, 4)));
+        circuit
+            .connect(OutputPin::new(a, 0), InputPin::new(and, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(b, 0), InputPin::new(and, 1))
+            .unwrap();
+        let mut simulator = Simulator::new(circuit);
+        simulator.settle().unwrap();
+
+        set_input(&mut simulator, a, Value::new(0b1100, 4));
+        set_input(&mut simulator, b, Value::new(0b1010, 4));
+        assert_eq!(simulator.settle(), Ok(2));
+        assert!(simulator.is_settled());
+        assert_eq!(output(&simulator, and), Value::new(0b1000, 4));
+
+        // Nothing changed, so there is nothing left to do
+        assert_eq!(simulator.settle(), Ok(0));
+    }
+
+    #[test]
+    fn tick_reevaluates_clocked_nodes() {
+        let mut circuit = Circuit::new();
+        let clock = circuit.add_node(Box::new(Clock::new(ClockTiming::default())));
+        let not = circuit.add_node(Box::new(Gate::new(GateKind::Not, 1, 1)));
+        circuit
+            .connect(OutputPin::new(clock, 0), InputPin::new(not, 0))
+            .unwrap();
+        let mut simulator = Simulator::new(circuit);
+        simulator.settle().unwrap();
+        assert_eq!(simulator.tick_count(), 0);
+        assert_eq!(output(&simulator, not), Value::new(1, 1));
+
+        simulator.tick().unwrap();
+        assert_eq!(simulator.tick_count(), 1);
+        assert_eq!(output(&simulator, clock), Value::new(1, 1));
+        assert_eq!(output(&simulator, not), Value::new(0, 1));
+
+        simulator.run_ticks(3).unwrap();
+        assert_eq!(simulator.tick_count(), 4);
+        assert_eq!(output(&simulator, not), Value::new(1, 1));
+    }
+
+    /// Expect `settle` to report an oscillation between exactly `nodes`
+    fn assert_oscillates(simulator: &mut Simulator, nodes: &[NodeId]) {
+        match simulator.settle() {
+            Err(SimulationError::Oscillation {
+                nodes: found,
+                cycle,
+                ..
+            }) => {
+                assert_eq!(found, nodes);
+                assert!(!cycle.is_empty());
+            }
+            other => panic!("expected an oscillation, got {:?}", other),
+        }
+        assert!(simulator.is_settled());
+    }
+
+    #[test]
+    fn ring_of_inverters_oscillates() {
+        let mut circuit = Circuit::new();
+        let enable = circuit.add_input(Box::new(Input::new(1)));
+        let nand = circuit.add_node(Box::new(Gate::new(GateKind::Nand, 2, 1)));
+        let first = circuit.add_node(Box::new(Gate::new(GateKind::Not, 1, 1)));
+        let second = circuit.add_node(Box::new(Gate::new(GateKind::Not, 1, 1)));
+        circuit
+            .connect(OutputPin::new(enable, 0), InputPin::new(nand, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(nand, 0), InputPin::new(first, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(first, 0), InputPin::new(second, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(second, 0), InputPin::new(nand, 1))
+            .unwrap();
+        let mut simulator = Simulator::new(circuit);
+        simulator.set_iteration_limit(50);
+
+        // Held low, the NAND breaks the ring
+        assert!(simulator.settle().is_ok());
+        set_input(&mut simulator, enable, Value::new(1, 1));
+        assert_oscillates(&mut simulator, &[nand, first, second]);
+    }
+
+    #[test]
+    fn sr_latch_races_when_both_inputs_release() {
+        let mut circuit = Circuit::new();
+        let set = circuit.add_input(Box::new(Input::new(1)));
+        let reset = circuit.add_input(Box::new(Input::new(1)));
+        let q = circuit.add_node(Box::new(Gate::new(GateKind::Nor, 2, 1)));
+        let q_bar = circuit.add_node(Box::new(Gate::new(GateKind::Nor, 2, 1)));
+        circuit
+            .connect(OutputPin::new(reset, 0), InputPin::new(q, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(set, 0), InputPin::new(q_bar, 0))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(q_bar, 0), InputPin::new(q, 1))
+            .unwrap();
+        circuit
+            .connect(OutputPin::new(q, 0), InputPin::new(q_bar, 1))
+            .unwrap();
+        let mut simulator = Simulator::new(circuit);
+        simulator.set_iteration_limit(50);
+
+        set_input(&mut simulator, set, Value::new(1, 1));
+        set_input(&mut simulator, reset, Value::new(1, 1));
+        assert!(simulator.settle().is_ok());
+        assert_eq!(output(&simulator, q), Value::new(0, 1));
+        assert_eq!(output(&simulator, q_bar), Value::new(0, 1));
+
+        // Releasing both at once makes the gates flip in lockstep forever
+        set_input(&mut simulator, set, Value::new(0, 1));
+        set_input(&mut simulator, reset, Value::new(0, 1));
+        assert_oscillates(&mut simulator, &[q, q_bar]);
+    }
+}
@@ -1,14 +1,43 @@
 use std::fmt;
+use std::ops::{BitAnd, BitOr, BitXor, Not};
 
-fn mask(size: u8) -> u32 {
-    if size == 32 {
-        u32::MAX
+const WORD_BITS: usize = 64;
+
+/// Values up to this width are stored inline, wider ones on the heap
+pub const INLINE_WIDTH: u8 = 64;
+
+/// Widest value there can be
+pub const MAX_WIDTH: u8 = u8::MAX;
+
+/// Mask of the bits of word `index` that lie within `width`
+fn word_mask(width: u8, index: usize) -> u64 {
+    let remaining = width as usize - index * WORD_BITS;
+    if remaining >= WORD_BITS {
+        u64::MAX
     } else {
-        (1 << size) - 1
+        (1 << remaining) - 1
     }
 }
 
-/// Four-state logic value using three 32-bit masks
+fn word_count(width: u8) -> usize {
+    (width as usize).div_ceil(WORD_BITS)
+}
+
+/// The three masks for 64 consecutive bits of a value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+struct Word {
+    logic_bits: u64,   // Logic values (0/1) - only valid where other masks are 0
+    high_z_mask: u64,  // 1 = high-impedance, 0 = not high-impedance
+    unknown_mask: u64, // 1 = unknown, 0 = not unknown
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Words {
+    Inline(Word),
+    Heap(Box<[Word]>),
+}
+
+/// Four-state logic value using three bit masks, stored in 64-bit words
 /// Each bit can be: 0, 1, Z (high-impedance), or X (unknown/short circuit)
 ///
 /// Encoding using three bit masks:
@@ -18,78 +47,143 @@ fn mask(size: u8) -> u32 {
 ///
 /// Bit state priority: unknown > high_z > logic
 /// If unknown_mask[i] = 1: bit i is unknown (X)
-/// Else if high_z_mask[i] = 1: bit i is high-impedance (Z)  
+/// Else if high_z_mask[i] = 1: bit i is high-impedance (Z)
 /// Else: bit i is logic (0 or 1 from logic_bits[i])
+///
+/// Widths up to `INLINE_WIDTH` fit in a single inline word; wider values (up to `MAX_WIDTH`
+/// bits) keep one word per 64 bits on the heap. Use `try_from_u64` for widths that come from
+/// outside and may be out of range. A value of width 0 has no bits at all.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Value {
-    logic_bits: u32,   // Logic values (0/1) - only valid where other masks are 0
-    high_z_mask: u32,  // 1 = high-impedance, 0 = not high-impedance
-    unknown_mask: u32, // 1 = unknown, 0 = not unknown
+    words: Words,
     width: u8,
 }
 
 impl Value {
+    /// Build a value word by word. Bits beyond `width` are cleared, as are the masks a higher
+    /// priority state overrides, so equal values always compare and hash the same.
+    fn from_words(width: u8, mut word: impl FnMut(usize) -> Word) -> Self {
+        let mut masked = |index: usize| {
+            let mask = word_mask(width, index);
+            let word = word(index);
+            let unknown_mask = word.unknown_mask & mask;
+            let high_z_mask = word.high_z_mask & mask & !unknown_mask;
+            Word {
+                logic_bits: word.logic_bits & mask & !(high_z_mask | unknown_mask),
+                high_z_mask,
+                unknown_mask,
+            }
+        };
+        let words = if width <= INLINE_WIDTH {
+            Words::Inline(masked(0))
+        } else {
+            Words::Heap((0..word_count(width)).map(masked).collect())
+        };
+        Self { words, width }
+    }
+
+    fn words(&self) -> &[Word] {
+        match &self.words {
+            Words::Inline(word) => std::slice::from_ref(word),
+            Words::Heap(words) => words,
+        }
+    }
+
+    fn words_mut(&mut self) -> &mut [Word] {
+        match &mut self.words {
+            Words::Inline(word) => std::slice::from_mut(word),
+            Words::Heap(words) => words,
+        }
+    }
+
+    /// Word and bit mask holding bit `bit_index`
+    fn locate(&self, bit_index: u8) -> (usize, u64) {
+        assert!(bit_index < self.width);
+        let index = bit_index as usize;
+        (index / WORD_BITS, 1u64 << (index % WORD_BITS))
+    }
+
     /// Create a pure logic value
     pub fn new_logic(value: u32, width: u8) -> Self {
-        assert!(width > 0 && width <= 32);
-        let mask = mask(width);
-        Self {
-            logic_bits: value & mask,
-            high_z_mask: 0,
-            unknown_mask: 0,
-            width,
-        }
+        Self::from_u64(value as u64, width)
+    }
+
+    /// Create a pure logic value from up to 64 bits
+    pub fn from_u64(value: u64, width: u8) -> Self {
+        Self::from_words(width, |index| Word {
+            logic_bits: if index == 0 { value } else { 0 },
+            ..Word::default()
+        })
+    }
+
+    /// Like `from_u64`, but None if `width` is wider than `MAX_WIDTH`
+    pub fn try_from_u64(value: u64, width: u32) -> Option<Self> {
+        u8::try_from(width)
+            .ok()
+            .map(|width| Self::from_u64(value, width))
     }
 
     /// Create a value with all bits in high-impedance state
     pub fn new_high_z(width: u8) -> Self {
-        assert!(width > 0 && width <= 32);
-				let mask = mask(width);
-        Self {
-            logic_bits: 0,
-            high_z_mask: mask,
-            unknown_mask: 0,
-            width,
-        }
+        Self::from_words(width, |_| Word {
+            high_z_mask: u64::MAX,
+            ..Word::default()
+        })
     }
 
-    /// Create a value with all bits in unknown state  
+    /// Create a value with all bits in unknown state
     pub fn new_unknown(width: u8) -> Self {
-        assert!(width > 0 && width <= 32);
-				let mask = mask(width);
-        Self {
-            logic_bits: 0,
-            high_z_mask: 0,
-            unknown_mask: mask,
-            width,
-        }
+        Self::from_words(width, |_| Word {
+            unknown_mask: u64::MAX,
+            ..Word::default()
+        })
     }
 
-    /// Create mixed-state value from masks
+    /// Create mixed-state value from masks. Bits above 32 are logic 0.
     pub fn from_masks(
         logic_bits: u32,
         high_z_mask: u32,
         unknown_mask: u32,
         width: u8,
     ) -> Self {
-        assert!(width > 0 && width <= 32);
-				let mask = mask(width);
-        Self {
-            logic_bits: logic_bits & mask,
-            high_z_mask: high_z_mask & mask,
-            unknown_mask: unknown_mask & mask,
+        Self::from_mask_words(
+            &[logic_bits as u64],
+            &[high_z_mask as u64],
+            &[unknown_mask as u64],
             width,
-        }
+        )
+    }
+
+    /// Create mixed-state value from masks of any width, least significant word first.
+    /// Missing words are treated as 0.
+    pub fn from_mask_words(
+        logic_bits: &[u64],
+        high_z_mask: &[u64],
+        unknown_mask: &[u64],
+        width: u8,
+    ) -> Self {
+        let at = |words: &[u64], index: usize| words.get(index).copied().unwrap_or(0);
+        Self::from_words(width, |index| Word {
+            logic_bits: at(logic_bits, index),
+            high_z_mask: at(high_z_mask, index),
+            unknown_mask: at(unknown_mask, index),
+        })
     }
 
     pub fn width(&self) -> u8 {
         self.width
     }
 
-    /// Get logic value if all bits are in logic state, None otherwise
+    /// Get logic value if all bits are in logic state and it fits in 32 bits, None otherwise
     pub fn as_logic(&self) -> Option<u32> {
-        if self.is_all_logic() {
-            Some(self.logic_bits)
+        self.as_u64().and_then(|value| u32::try_from(value).ok())
+    }
+
+    /// Get logic value if all bits are in logic state and it fits in 64 bits, None otherwise
+    pub fn as_u64(&self) -> Option<u64> {
+        let words = self.words();
+        if self.is_all_logic() && words[1..].iter().all(|word| word.logic_bits == 0) {
+            Some(words[0].logic_bits)
         } else {
             None
         }
@@ -97,29 +191,31 @@ impl Value {
 
     /// Check if all bits are in logic state (0 or 1)
     pub fn is_all_logic(&self) -> bool {
-        (self.high_z_mask | self.unknown_mask) == 0
+        self.words()
+            .iter()
+            .all(|word| (word.high_z_mask | word.unknown_mask) == 0)
     }
 
     /// Check if any bit is in high-impedance state
     pub fn has_high_z(&self) -> bool {
-        self.high_z_mask != 0
+        self.words().iter().any(|word| word.high_z_mask != 0)
     }
 
     /// Check if any bit is in unknown state
     pub fn has_unknown(&self) -> bool {
-        self.unknown_mask != 0
+        self.words().iter().any(|word| word.unknown_mask != 0)
     }
 
     /// Get the state of a specific bit
     pub fn get_bit_state(&self, bit_index: u8) -> BitState {
-        assert!(bit_index < 32);
-        let bit_mask = 1u32 << bit_index;
+        let (index, bit_mask) = self.locate(bit_index);
+        let word = &self.words()[index];
 
-        if (self.unknown_mask & bit_mask) != 0 {
+        if (word.unknown_mask & bit_mask) != 0 {
             BitState::Unknown
-        } else if (self.high_z_mask & bit_mask) != 0 {
+        } else if (word.high_z_mask & bit_mask) != 0 {
             BitState::HighZ
-        } else if (self.logic_bits & bit_mask) != 0 {
+        } else if (word.logic_bits & bit_mask) != 0 {
             BitState::Logic1
         } else {
             BitState::Logic0
@@ -128,39 +224,39 @@ impl Value {
 
     /// Set a specific bit to a logic value
     pub fn set_logic_bit(&mut self, bit_index: u8, value: bool) {
-        assert!(bit_index < 32);
-        let bit_mask = 1u32 << bit_index;
+        let (index, bit_mask) = self.locate(bit_index);
+        let word = &mut self.words_mut()[index];
 
         // Clear from special states
-        self.high_z_mask &= !bit_mask;
-        self.unknown_mask &= !bit_mask;
+        word.high_z_mask &= !bit_mask;
+        word.unknown_mask &= !bit_mask;
 
         // Set logic value
         if value {
-            self.logic_bits |= bit_mask;
+            word.logic_bits |= bit_mask;
         } else {
-            self.logic_bits &= !bit_mask;
+            word.logic_bits &= !bit_mask;
         }
     }
 
     /// Set a specific bit to high-impedance
     pub fn set_high_z_bit(&mut self, bit_index: u8) {
-        assert!(bit_index < 32);
-        let bit_mask = 1u32 << bit_index;
+        let (index, bit_mask) = self.locate(bit_index);
+        let word = &mut self.words_mut()[index];
 
-        self.unknown_mask &= !bit_mask; // Clear unknown
-        self.high_z_mask |= bit_mask; // Set high-Z
-        // self.logic_bits &= !bit_mask; // Clear logic bit
+        word.unknown_mask &= !bit_mask; // Clear unknown
+        word.high_z_mask |= bit_mask; // Set high-Z
+        word.logic_bits &= !bit_mask; // Clear logic bit
     }
 
     /// Set a specific bit to unknown
     pub fn set_unknown_bit(&mut self, bit_index: u8) {
-        assert!(bit_index < 32);
-        let bit_mask = 1u32 << bit_index;
+        let (index, bit_mask) = self.locate(bit_index);
+        let word = &mut self.words_mut()[index];
 
-        self.unknown_mask |= bit_mask; // Set unknown
-        self.high_z_mask &= !bit_mask; // Clear high-Z
-        self.logic_bits &= !bit_mask; // Clear logic bit
+        word.unknown_mask |= bit_mask; // Set unknown
+        word.high_z_mask &= !bit_mask; // Clear high-Z
+        word.logic_bits &= !bit_mask; // Clear logic bit
     }
 }
 
@@ -212,3 +308,475 @@ impl Value {
         self.has_unknown()
     }
 }
+
+// Four-state logic operators
+//
+// Z inputs are treated like X. Each operand is split into the bits that are a definite 1 and
+// the bits that are a definite 0; any bit that is neither comes out as X.
+impl Value {
+    /// Bits of word `index` that are a definite logic 1
+    fn ones(&self, index: usize) -> u64 {
+        let word = &self.words()[index];
+        word.logic_bits & !(word.high_z_mask | word.unknown_mask)
+    }
+
+    /// Bits of word `index` that are a definite logic 0
+    fn zeros(&self, index: usize) -> u64 {
+        let word = &self.words()[index];
+        !word.logic_bits & !(word.high_z_mask | word.unknown_mask) & word_mask(self.width, index)
+    }
+
+    /// Build a value from the definite 1 and 0 bits of each word. Every other bit is unknown.
+    fn from_ones_zeros(width: u8, mut ones_zeros: impl FnMut(usize) -> (u64, u64)) -> Self {
+        Self::from_words(width, |index| {
+            let (ones, zeros) = ones_zeros(index);
+            Word {
+                logic_bits: ones,
+                high_z_mask: 0,
+                unknown_mask: !(ones | zeros),
+            }
+        })
+    }
+
+    fn word_indices(&self) -> std::ops::Range<usize> {
+        0..self.words().len()
+    }
+
+    /// AND of all bits: 0 if any bit is 0, 1 if every bit is 1, X otherwise
+    pub fn reduce_and(&self) -> Value {
+        let any_zero = self.word_indices().fold(0, |acc, i| acc | self.zeros(i)) != 0;
+        let all_ones = self
+            .word_indices()
+            .fold(true, |acc, i| acc & (self.ones(i) == word_mask(self.width, i)));
+        Self::from_ones_zeros(1, |_| (all_ones as u64, any_zero as u64))
+    }
+
+    /// OR of all bits: 1 if any bit is 1, 0 if every bit is 0, X otherwise
+    pub fn reduce_or(&self) -> Value {
+        let any_one = self.word_indices().fold(0, |acc, i| acc | self.ones(i)) != 0;
+        let all_zeros = self
+            .word_indices()
+            .fold(true, |acc, i| acc & (self.zeros(i) == word_mask(self.width, i)));
+        Self::from_ones_zeros(1, |_| (any_one as u64, all_zeros as u64))
+    }
+
+    /// XOR (parity) of all bits: X if any bit is not a definite 0 or 1
+    pub fn reduce_xor(&self) -> Value {
+        let known = self.word_indices().fold(true, |acc, i| {
+            acc & ((self.ones(i) | self.zeros(i)) == word_mask(self.width, i))
+        }) as u64;
+        let parity = self
+            .word_indices()
+            .fold(0, |acc, i| acc ^ self.ones(i).count_ones() as u64)
+            & 1;
+        Self::from_ones_zeros(1, |_| (parity & known, !parity & known))
+    }
+}
+
+// Multi-driver resolution
+impl Value {
+    /// Merge the values of several drivers sharing one net, bit by bit
+    ///
+    /// A high-impedance driver yields to any other driver. Drivers disagreeing on a bit (a short)
+    /// or driving X make that bit X. A bit that nobody drives stays Z.
+    ///
+    /// Panics if `drivers` is empty or the widths differ.
+    pub fn resolve(drivers: &[Value]) -> Value {
+        assert!(!drivers.is_empty(), "cannot resolve a net without drivers");
+        let width = drivers[0].width;
+        for driver in drivers {
+            assert_eq!(driver.width, width, "width mismatch");
+        }
+
+        Self::from_words(width, |index| {
+            let mut ones = 0;
+            let mut zeros = 0;
+            let mut unknown = 0;
+            for driver in drivers {
+                ones |= driver.ones(index);
+                zeros |= driver.zeros(index);
+                unknown |= driver.words()[index].unknown_mask;
+            }
+
+            let unknown = unknown | (ones & zeros);
+            Word {
+                logic_bits: ones & !unknown,
+                high_z_mask: !(ones | zeros | unknown),
+                unknown_mask: unknown,
+            }
+        })
+    }
+
+    /// Bits every value agrees on, whatever their state, and X where any two differ. Useful
+    /// when an unknown control leaves several results possible.
+    ///
+    /// Panics if `values` is empty or the widths differ.
+    pub fn merge(values: &[Value]) -> Value {
+        assert!(!values.is_empty(), "cannot merge no values");
+        let width = values[0].width;
+        for value in values {
+            assert_eq!(value.width, width, "width mismatch");
+        }
+
+        Self::from_words(width, |index| {
+            let mut ones = u64::MAX;
+            let mut zeros = u64::MAX;
+            let mut high_z = u64::MAX;
+            for value in values {
+                let word = value.words()[index];
+                ones &= value.ones(index);
+                zeros &= value.zeros(index);
+                high_z &= word.high_z_mask & !word.unknown_mask;
+            }
+            Word {
+                logic_bits: ones,
+                high_z_mask: high_z,
+                unknown_mask: !(ones | zeros | high_z),
+            }
+        })
+    }
+
+    /// Give the bits nobody drives the value of `weak`, the way a pull resistor does. Driven
+    /// and unknown bits are left alone.
+    ///
+    /// Panics if the widths differ.
+    pub fn pull(&self, weak: &Value) -> Value {
+        assert_eq!(self.width, weak.width, "width mismatch");
+        Self::from_words(self.width, |index| {
+            let word = self.words()[index];
+            let weak = weak.words()[index];
+            let floating = word.high_z_mask & !word.unknown_mask;
+            Word {
+                logic_bits: (word.logic_bits & !floating) | (weak.logic_bits & floating),
+                high_z_mask: weak.high_z_mask & floating,
+                unknown_mask: word.unknown_mask | (weak.unknown_mask & floating),
+            }
+        })
+    }
+}
+
+impl BitAnd for &Value {
+    type Output = Value;
+
+    fn bitand(self, rhs: &Value) -> Value {
+        assert_eq!(self.width, rhs.width, "width mismatch");
+        Value::from_ones_zeros(self.width, |i| {
+            (self.ones(i) & rhs.ones(i), self.zeros(i) | rhs.zeros(i))
+        })
+    }
+}
+
+impl BitOr for &Value {
+    type Output = Value;
+
+    fn bitor(self, rhs: &Value) -> Value {
+        assert_eq!(self.width, rhs.width, "width mismatch");
+        Value::from_ones_zeros(self.width, |i| {
+            (self.ones(i) | rhs.ones(i), self.zeros(i) & rhs.zeros(i))
+        })
+    }
+}
+
+impl BitXor for &Value {
+    type Output = Value;
+
+    fn bitxor(self, rhs: &Value) -> Value {
+        assert_eq!(self.width, rhs.width, "width mismatch");
+        Value::from_ones_zeros(self.width, |i| {
+            let known = (self.ones(i) | self.zeros(i)) & (rhs.ones(i) | rhs.zeros(i));
+            let ones = (self.ones(i) ^ rhs.ones(i)) & known;
+            (ones, !ones & known)
+        })
+    }
+}
+
+impl Not for &Value {
+    type Output = Value;
+
+    fn not(self) -> Value {
+        Value::from_ones_zeros(self.width, |i| (self.zeros(i), self.ones(i)))
+    }
+}
+
+impl BitAnd for Value {
+    type Output = Value;
+
+    fn bitand(self, rhs: Value) -> Value {
+        &self & &rhs
+    }
+}
+
+impl BitOr for Value {
+    type Output = Value;
+
+    fn bitor(self, rhs: Value) -> Value {
+        &self | &rhs
+    }
+}
+
+impl BitXor for Value {
+    type Output = Value;
+
+    fn bitxor(self, rhs: Value) -> Value {
+        &self ^ &rhs
+    }
+}
+
+impl Not for Value {
+    type Output = Value;
+
+    fn not(self) -> Value {
+        !&self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATES: [BitState; 4] = [
+        BitState::Logic0,
+        BitState::Logic1,
+        BitState::HighZ,
+        BitState::Unknown,
+    ];
+
+    fn value_of(bits: &[BitState]) -> Value {
+        let mut value = Value::new(0, bits.len() as u8);
+        for (i, state) in bits.iter().enumerate() {
+            match state {
+                BitState::Logic0 => value.set_logic_bit(i as u8, false),
+                BitState::Logic1 => value.set_logic_bit(i as u8, true),
+                BitState::HighZ => value.set_high_z_bit(i as u8),
+                BitState::Unknown => value.set_unknown_bit(i as u8),
+            }
+        }
+        value
+    }
+
+    fn known(state: BitState) -> Option<bool> {
+        match state {
+            BitState::Logic0 => Some(false),
+            BitState::Logic1 => Some(true),
+            BitState::HighZ | BitState::Unknown => None,
+        }
+    }
+
+    fn state_of(bit: Option<bool>) -> BitState {
+        match bit {
+            Some(false) => BitState::Logic0,
+            Some(true) => BitState::Logic1,
+            None => BitState::Unknown,
+        }
+    }
+
+    fn and_ref(a: BitState, b: BitState) -> BitState {
+        match (known(a), known(b)) {
+            (Some(false), _) | (_, Some(false)) => BitState::Logic0,
+            (Some(true), Some(true)) => BitState::Logic1,
+            _ => BitState::Unknown,
+        }
+    }
+
+    fn or_ref(a: BitState, b: BitState) -> BitState {
+        match (known(a), known(b)) {
+            (Some(true), _) | (_, Some(true)) => BitState::Logic1,
+            (Some(false), Some(false)) => BitState::Logic0,
+            _ => BitState::Unknown,
+        }
+    }
+
+    fn xor_ref(a: BitState, b: BitState) -> BitState {
+        match (known(a), known(b)) {
+            (Some(a), Some(b)) => state_of(Some(a ^ b)),
+            _ => BitState::Unknown,
+        }
+    }
+
+    /// Every pair of bit states, packed side by side into two 16-bit values
+    fn all_pairs() -> (Vec<BitState>, Vec<BitState>) {
+        let mut lhs = Vec::new();
+        let mut rhs = Vec::new();
+        for a in STATES {
+            for b in STATES {
+                lhs.push(a);
+                rhs.push(b);
+            }
+        }
+        (lhs, rhs)
+    }
+
+    fn check_binary(op: fn(&Value, &Value) -> Value, reference: fn(BitState, BitState) -> BitState) {
+        let (lhs, rhs) = all_pairs();
+        let result = op(&value_of(&lhs), &value_of(&rhs));
+        assert_eq!(result.width(), 16);
+        for i in 0..16 {
+            assert_eq!(
+                result.get_bit_state(i as u8),
+                reference(lhs[i], rhs[i]),
+                "{:?} op {:?}",
+                lhs[i],
+                rhs[i]
+            );
+        }
+
+        // Single-bit operands must agree with the packed result
+        for i in 0..16 {
+            let result = op(&value_of(&lhs[i..=i]), &value_of(&rhs[i..=i]));
+            assert_eq!(result.get_bit_state(0), reference(lhs[i], rhs[i]));
+        }
+    }
+
+    #[test]
+    fn and_all_states() {
+        check_binary(|a, b| a & b, and_ref);
+        assert_eq!(Value::new(0, 1) & Value::unknown(1), Value::new(0, 1));
+    }
+
+    #[test]
+    fn or_all_states() {
+        check_binary(|a, b| a | b, or_ref);
+        assert_eq!(Value::new(1, 1) | Value::high_z(1), Value::new(1, 1));
+    }
+
+    #[test]
+    fn xor_all_states() {
+        check_binary(|a, b| a ^ b, xor_ref);
+        assert_eq!(Value::unknown(1) ^ Value::new(1, 1), Value::unknown(1));
+    }
+
+    #[test]
+    fn not_all_states() {
+        let result = !value_of(&STATES);
+        for (i, state) in STATES.iter().enumerate() {
+            assert_eq!(result.get_bit_state(i as u8), state_of(known(*state).map(|b| !b)));
+        }
+    }
+
+    #[test]
+    fn operators_keep_full_width() {
+        let a = Value::new(0xFFFF_0000, 32);
+        let b = Value::new(0x0F0F_0F0F, 32);
+        assert_eq!((&a & &b).as_u32(), Some(0x0F0F_0000));
+        assert_eq!((&a | &b).as_u32(), Some(0xFFFF_0F0F));
+        assert_eq!((&a ^ &b).as_u32(), Some(0xF0F0_0F0F));
+        assert_eq!((!&a).as_u32(), Some(0x0000_FFFF));
+    }
+
+    #[test]
+    #[should_panic(expected = "width mismatch")]
+    fn operators_reject_width_mismatch() {
+        let _ = Value::new(0, 2) & Value::new(0, 3);
+    }
+
+    #[test]
+    fn resolve_all_states() {
+        let (lhs, rhs) = all_pairs();
+        let result = Value::resolve(&[value_of(&lhs), value_of(&rhs)]);
+        for i in 0..16 {
+            let expected = match (lhs[i], rhs[i]) {
+                (BitState::HighZ, other) | (other, BitState::HighZ) => other,
+                (a, b) if a == b => a,
+                _ => BitState::Unknown,
+            };
+            assert_eq!(result.get_bit_state(i as u8), expected, "{:?} with {:?}", lhs[i], rhs[i]);
+        }
+
+        let bus = [Value::high_z(4), Value::new(0b1010, 4), Value::high_z(4)];
+        assert_eq!(Value::resolve(&bus), Value::new(0b1010, 4));
+        assert_eq!(Value::resolve(&[Value::high_z(4)]), Value::high_z(4));
+    }
+
+    #[test]
+    fn wide_values() {
+        let a = Value::from_mask_words(&[u64::MAX, 0b101], &[], &[], 100);
+        let b = Value::from_mask_words(&[0, 0b110], &[], &[], 100);
+        assert_eq!(a.width(), 100);
+        assert_eq!(a.as_u64(), None);
+        assert_eq!((&a & &b), Value::from_mask_words(&[0, 0b100], &[], &[], 100));
+        assert_eq!((&a ^ &b), Value::from_mask_words(&[u64::MAX, 0b011], &[], &[], 100));
+        assert_eq!((!&b).reduce_and(), Value::new(0, 1));
+        assert_eq!(a.reduce_xor(), Value::new(0, 1));
+
+        let mut c = Value::new(0, 65);
+        c.set_high_z_bit(64);
+        assert_eq!(c.get_bit_state(64), BitState::HighZ);
+        assert_eq!(c.to_string(), format!("Z{}", "0".repeat(64)));
+        assert_eq!(Value::resolve(&[c, Value::high_z(65)]).get_bit_state(64), BitState::HighZ);
+
+        assert_eq!(Value::from_u64(u64::MAX, 64).as_u64(), Some(u64::MAX));
+        assert_eq!(Value::from_u64(u64::MAX, 64).as_u32(), None);
+        assert_eq!(Value::new(7, 255).as_u32(), Some(7));
+        assert_eq!(Value::try_from_u64(7, 255), Some(Value::new(7, 255)));
+        assert_eq!(Value::try_from_u64(7, 256), None);
+    }
+
+    #[test]
+    fn zero_width_values_have_no_bits() {
+        let empty = Value::new(5, 0);
+        assert_eq!(empty.width(), 0);
+        assert_eq!(empty.as_u64(), Some(0));
+        assert_eq!(empty.to_string(), "");
+        assert_eq!(empty, Value::unknown(0));
+    }
+
+    #[test]
+    fn floating_bits_forget_their_logic_value() {
+        let mut one = Value::new(1, 1);
+        one.set_high_z_bit(0);
+        assert_eq!(one, Value::high_z(1));
+        assert_eq!(
+            Value::from_masks(0b11, 0b01, 0b10, 2),
+            value_of(&[BitState::HighZ, BitState::Unknown])
+        );
+    }
+
+    /// Every 3-bit combination of bit states
+    fn all_triples() -> Vec<Vec<BitState>> {
+        let mut result = Vec::new();
+        for a in STATES {
+            for b in STATES {
+                for c in STATES {
+                    result.push(vec![a, b, c]);
+                }
+            }
+        }
+        result
+    }
+
+    fn check_reduction(op: fn(&Value) -> Value, reference: fn(BitState, BitState) -> BitState) {
+        for bits in all_triples() {
+            let expected = bits[1..].iter().fold(bits[0], |acc, b| reference(acc, *b));
+            let result = op(&value_of(&bits));
+            assert_eq!(result.width(), 1);
+            assert_eq!(result.get_bit_state(0), expected, "{:?}", bits);
+        }
+    }
+
+    #[test]
+    fn reduce_and_all_states() {
+        check_reduction(Value::reduce_and, and_ref);
+        assert_eq!(Value::new(u32::MAX, 32).reduce_and(), Value::new(1, 1));
+    }
+
+    #[test]
+    fn reduce_or_all_states() {
+        check_reduction(Value::reduce_or, or_ref);
+        assert_eq!(Value::new(0, 32).reduce_or(), Value::new(0, 1));
+    }
+
+    #[test]
+    fn reduce_xor_all_states() {
+        check_reduction(Value::reduce_xor, xor_ref);
+        assert_eq!(Value::new(0b1011, 4).reduce_xor(), Value::new(1, 1));
+    }
+
+    #[test]
+    fn merge_keeps_agreeing_bits() {
+        let merged = Value::merge(&[Value::new(0b0110, 4), Value::new(0b0011, 4)]);
+        assert_eq!(merged.to_string(), "0X1X");
+        let merged = Value::merge(&[Value::high_z(2), Value::new(1, 2)]);
+        assert_eq!(merged.to_string(), "XX");
+        assert_eq!(Value::merge(&[Value::high_z(2)]), Value::high_z(2));
+    }
+}
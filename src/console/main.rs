@@ -0,0 +1,477 @@
+// Headless runner: loads a project file, drives its input pins and prints its output pins
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use backend::circuit::InputPin;
+use backend::component::io::{Radix, VALUE_KEY};
+use backend::node::NodeId;
+use backend::schematic::file::ProjectFile;
+use backend::schematic::{CompiledSchematic, Schematic};
+use backend::simulation::Simulator;
+use backend::value::Value;
+
+const USAGE: &str = "\
+usage: console <project> [options]
+
+Options:
+  --set NAME=VALUE       drive input pin NAME from the start
+  --stimulus FILE        drive input pins at the ticks listed in FILE
+  --ticks N              ticks to run (default: the last tick in the stimulus file, or 0 to
+                         only settle)
+  --radix RADIX          print outputs in binary, hex, decimal or signed (default binary)
+  --trace                print the outputs after every tick, not only at the end
+  --iteration-limit N    steps a settle may take before the circuit counts as oscillating
+
+Pins are named by their labels, or in0, in1, ... and out0, out1, ... in pin order when they
+have none. Values are decimal (12, -5), hex (0x1f) or binary (0b10zx), or z or x for every bit.
+
+A stimulus file has a line per tick that changes inputs, '<tick> NAME=VALUE ...', applied once
+that many ticks have run. '#' starts a comment.
+
+Exit status: 0 on success, 1 for bad arguments, files or circuits, 2 when the circuit
+oscillates, 3 when an output ends with an unknown (X) bit.";
+
+const EXIT_ERROR: u8 = 1;
+const EXIT_OSCILLATION: u8 = 2;
+const EXIT_UNKNOWN_OUTPUT: u8 = 3;
+
+/// Command line settings
+#[derive(Default)]
+struct Options {
+    project: PathBuf,
+    sets: Vec<String>, // NAME=VALUE
+    stimulus: Option<PathBuf>,
+    ticks: Option<u64>,
+    radix: Radix,
+    trace: bool,
+    iteration_limit: Option<usize>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut project = None;
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+        match arg.as_str() {
+            "--set" => options.sets.push(value()?),
+            "--stimulus" => options.stimulus = Some(PathBuf::from(value()?)),
+            "--ticks" => {
+                let ticks = value()?;
+                let ticks = ticks
+                    .parse()
+                    .map_err(|_| format!("bad tick count '{ticks}'"))?;
+                options.ticks = Some(ticks);
+            }
+            "--radix" => {
+                let name = value()?;
+                options.radix = Radix::ALL
+                    .into_iter()
+                    .find(|radix| radix.label().eq_ignore_ascii_case(&name))
+                    .ok_or_else(|| format!("unknown radix '{name}'"))?;
+            }
+            "--trace" => options.trace = true,
+            "--iteration-limit" => {
+                let limit = value()?;
+                let limit = limit.parse().map_err(|_| format!("bad limit '{limit}'"))?;
+                options.iteration_limit = Some(limit);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option '{arg}'")),
+            _ if project.is_none() => project = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument '{arg}'")),
+        }
+    }
+    options.project = project.ok_or("no project file given")?;
+    Ok(options)
+}
+
+/// Parse `text` as a value `width` bits wide. See `USAGE` for the forms accepted.
+fn parse_value(text: &str, width: u8) -> Result<Value, String> {
+    let too_wide = || format!("'{text}' does not fit in {width} bits");
+    let lower = text.to_ascii_lowercase();
+    if lower == "x" {
+        return Ok(Value::unknown(width));
+    }
+    if lower == "z" {
+        return Ok(Value::high_z(width));
+    }
+    if let Some(bits) = lower.strip_prefix("0b") {
+        if bits.is_empty() || bits.len() > width as usize {
+            return Err(too_wide());
+        }
+        let mut value = Value::new(0, width);
+        for (bit, digit) in bits.chars().rev().enumerate() {
+            let bit = bit as u8;
+            match digit {
+                '0' => {}
+                '1' => value.set_logic_bit(bit, true),
+                'z' => value.set_high_z_bit(bit),
+                'x' => value.set_unknown_bit(bit),
+                _ => return Err(format!("bad binary digit '{digit}' in '{text}'")),
+            }
+        }
+        return Ok(value);
+    }
+
+    let (magnitude, negative) = match lower.strip_prefix("0x") {
+        Some(hex) => (u128::from_str_radix(hex, 16), false),
+        None => match lower.strip_prefix('-') {
+            Some(decimal) => (decimal.parse(), true),
+            None => (lower.parse(), false),
+        },
+    };
+    let magnitude = magnitude.map_err(|_| format!("bad value '{text}'"))?;
+    // Negative values are two's complement, so they have a bit less room
+    let room = width as u32 - negative as u32;
+    let limit = 1u128.checked_shl(room).unwrap_or(u128::MAX);
+    if magnitude >= limit && !(negative && magnitude == limit) {
+        return Err(too_wide());
+    }
+
+    let bits = if negative {
+        magnitude.wrapping_neg()
+    } else {
+        magnitude
+    };
+    let mut value = Value::from_u64(bits as u64, width);
+    for bit in 64..width {
+        let set = bits
+            .checked_shr(bit as u32)
+            .map_or(negative, |bits| bits & 1 == 1);
+        value.set_logic_bit(bit, set);
+    }
+    Ok(value)
+}
+
+/// Input or output pin of the main circuit
+struct Port {
+    name: String,
+    node: NodeId,
+    width: u8,
+}
+
+/// Inputs to drive at each tick, from `--set` (at tick 0) and the stimulus file
+fn schedule_inputs(
+    options: &Options,
+    inputs: &[Port],
+) -> Result<BTreeMap<u64, Vec<(NodeId, Value)>>, String> {
+    let mut changes: Vec<(u64, String)> = options.sets.iter().map(|set| (0, set.clone())).collect();
+    if let Some(path) = &options.stimulus {
+        let text = fs::read_to_string(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(tick) = words.next() else {
+                continue;
+            };
+            let tick = tick.parse().map_err(|_| {
+                format!("{}:{}: bad tick '{tick}'", path.display(), line_number + 1)
+            })?;
+            changes.extend(words.map(|word| (tick, word.to_string())));
+        }
+    }
+
+    let mut schedule: BTreeMap<u64, Vec<(NodeId, Value)>> = BTreeMap::new();
+    for (tick, change) in changes {
+        let (name, text) = change
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE, got '{change}'"))?;
+        let port = inputs
+            .iter()
+            .find(|port| port.name == name)
+            .ok_or_else(|| format!("no input pin named '{name}'"))?;
+        let value = parse_value(text, port.width).map_err(|err| format!("{name}: {err}"))?;
+        schedule.entry(tick).or_default().push((port.node, value));
+    }
+    Ok(schedule)
+}
+
+/// `NAME=VALUE` for every output pin
+fn output_line(simulator: &Simulator, outputs: &[Port], radix: Radix) -> String {
+    outputs
+        .iter()
+        .map(|port| {
+            let value = simulator.input_value(InputPin::new(port.node, 0));
+            let text = value.map_or("-".to_string(), |value| radix.format(value));
+            format!("{}={}", port.name, text)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Load and compile the project, then `simulate` it, printing to stdout
+fn run(options: &Options) -> Result<u8, String> {
+    let path = &options.project;
+    let file = ProjectFile::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    let schematic = file.schematic();
+    let compiled = schematic
+        .compile(&file.library())
+        .map_err(|err| format!("{}: {err}", path.display()))?;
+    simulate(&schematic, compiled, options, &mut io::stdout().lock())
+}
+
+/// Drive the inputs of `schematic`, compiled as `compiled`, for every tick, writing the outputs
+/// to `out`. Returns the exit status.
+fn simulate(
+    schematic: &Schematic,
+    compiled: CompiledSchematic,
+    options: &Options,
+    out: &mut impl Write,
+) -> Result<u8, String> {
+    // Top-level pins always have nodes of their own
+    let (input_indices, output_indices) = schematic.ports();
+    let ports = |indices: Vec<usize>, prefix: &str| -> Vec<Port> {
+        indices
+            .into_iter()
+            .enumerate()
+            .filter_map(|(position, index)| {
+                let component = &schematic.components[index];
+                let name = match component.label.as_str() {
+                    "" => format!("{prefix}{position}"),
+                    label => label.to_string(),
+                };
+                Some(Port {
+                    name,
+                    node: compiled.root.component_nodes[index]?,
+                    width: component.bit_width,
+                })
+            })
+            .collect()
+    };
+    let inputs = ports(input_indices, "in");
+    let outputs = ports(output_indices, "out");
+    let schedule = schedule_inputs(options, &inputs)?;
+    let ticks = options
+        .ticks
+        .unwrap_or_else(|| schedule.keys().last().copied().unwrap_or(0));
+
+    let mut simulator = Simulator::new(compiled.circuit);
+    if let Some(limit) = options.iteration_limit {
+        simulator.set_iteration_limit(limit);
+    }
+    for tick in 0..=ticks {
+        let mut settled = if tick > 0 {
+            simulator.tick().map(|_| ())
+        } else {
+            Ok(())
+        };
+        for (node, value) in schedule.get(&tick).into_iter().flatten() {
+            if let Some(node) = simulator.circuit_mut().node_mut(*node) {
+                node.state
+                    .metadata
+                    .insert(VALUE_KEY.to_string(), value.clone());
+            }
+            simulator.schedule(*node);
+        }
+        settled = settled.and_then(|_| simulator.settle().map(|_| ()));
+        if let Err(err) = settled {
+            eprintln!("tick {tick}: {err}");
+            return Ok(EXIT_OSCILLATION);
+        }
+        if options.trace {
+            let line = output_line(&simulator, &outputs, options.radix);
+            writeln!(out, "{tick}: {line}").map_err(|err| err.to_string())?;
+        }
+    }
+    if !options.trace {
+        let line = output_line(&simulator, &outputs, options.radix);
+        writeln!(out, "{line}").map_err(|err| err.to_string())?;
+    }
+
+    let unknown: Vec<&str> = outputs
+        .iter()
+        .filter(|port| {
+            simulator
+                .input_value(InputPin::new(port.node, 0))
+                .is_some_and(Value::has_unknown)
+        })
+        .map(|port| port.name.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        eprintln!("unknown outputs: {}", unknown.join(", "));
+        return Ok(EXIT_UNKNOWN_OUTPUT);
+    }
+    Ok(0)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let options = match parse_args(args.into_iter()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("console: {err}\n\n{USAGE}");
+            return ExitCode::from(EXIT_ERROR);
+        }
+    };
+    match run(&options) {
+        Ok(code) => ExitCode::from(code),
+        Err(err) => {
+            eprintln!("console: {err}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::schematic::Library;
+    use backend::schematic::components::{ComponentKind, PlacedComponent};
+    use backend::schematic::file::EditorState;
+    use backend::schematic::nets::{Direction, GridPos};
+
+    /// Lay a wire through `corners`, going straight from each to the next
+    fn wire(schematic: &mut Schematic, corners: &[GridPos]) {
+        for pair in corners.windows(2) {
+            let (mut from, to) = (pair[0], pair[1]);
+            while from != to {
+                let step = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+                let direction = Direction::ALL
+                    .into_iter()
+                    .find(|direction| direction.offset() == step)
+                    .expect("wires run straight");
+                let next = direction.step(from);
+                for (cell, side) in [(from, direction), (next, direction.opposite())] {
+                    let cell = schematic.wires.entry(cell).or_default();
+                    match side {
+                        Direction::North => cell.north = true,
+                        Direction::East => cell.east = true,
+                        Direction::South => cell.south = true,
+                        Direction::West => cell.west = true,
+                    }
+                }
+                from = next;
+            }
+        }
+    }
+
+    /// Input pin wired straight to an output pin
+    fn feedthrough() -> Schematic {
+        let input = PlacedComponent::new(ComponentKind::Input, (0, 0));
+        let output = PlacedComponent::new(ComponentKind::Output, (6, 0));
+        let mut schematic = Schematic::default();
+        wire(
+            &mut schematic,
+            &[input.output_pins()[0], output.input_pins()[0]],
+        );
+        schematic.components = vec![input, output];
+        schematic
+    }
+
+    /// Input pin enabling a NAND gate whose output feeds its other input, which oscillates
+    /// once the input is 1
+    fn enabled_ring() -> Schematic {
+        let nand = PlacedComponent::new(ComponentKind::NandGate, (4, 0));
+        let [enable, feedback] = nand.input_pins()[..] else {
+            panic!("NAND gates have two inputs");
+        };
+        let output = nand.output_pins()[0];
+        let input = PlacedComponent::new(ComponentKind::Input, (enable.0 - 4, enable.1));
+        let mut schematic = Schematic::default();
+        wire(&mut schematic, &[input.output_pins()[0], enable]);
+        let (right, below) = (output.0 + 1, feedback.1 + 1);
+        let left = feedback.0 - 1;
+        wire(
+            &mut schematic,
+            &[
+                output,
+                (right, output.1),
+                (right, below),
+                (left, below),
+                (left, feedback.1),
+                feedback,
+            ],
+        );
+        schematic.components = vec![input, nand];
+        schematic
+    }
+
+    /// Write `text` to a file only this test process uses
+    fn temp_file(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("console-{}-{name}", std::process::id()));
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    /// Exit status and output of simulating `schematic`
+    fn simulate_text(schematic: &Schematic, options: &Options) -> (u8, String) {
+        let compiled = schematic.compile(&Library::new()).unwrap();
+        let mut out = Vec::new();
+        let code = simulate(schematic, compiled, options, &mut out).unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn stimulus_drives_inputs_at_its_ticks() {
+        let stimulus = "# released after two ticks\n2 in0=0\n";
+        let options = Options {
+            sets: vec!["in0=1".to_string()],
+            stimulus: Some(temp_file("stimulus.txt", stimulus)),
+            trace: true,
+            ..Options::default()
+        };
+        let (code, out) = simulate_text(&feedthrough(), &options);
+        assert_eq!(code, 0);
+        assert_eq!(out, "0: out0=1\n1: out0=1\n2: out0=0\n");
+    }
+
+    #[test]
+    fn unknown_outputs_exit_with_3() {
+        let options = Options {
+            sets: vec!["in0=x".to_string()],
+            ..Options::default()
+        };
+        let (code, out) = simulate_text(&feedthrough(), &options);
+        assert_eq!(code, EXIT_UNKNOWN_OUTPUT);
+        assert_eq!(out, "out0=X\n");
+    }
+
+    #[test]
+    fn oscillation_exits_with_2() {
+        let mut options = Options {
+            sets: vec!["in0=0".to_string()],
+            iteration_limit: Some(50),
+            ..Options::default()
+        };
+        assert_eq!(simulate_text(&enabled_ring(), &options).0, 0);
+
+        options.stimulus = Some(temp_file("enable.txt", "1 in0=1\n"));
+        assert_eq!(simulate_text(&enabled_ring(), &options).0, EXIT_OSCILLATION);
+    }
+
+    #[test]
+    fn bad_components_are_errors() {
+        let mut schematic = feedthrough();
+        schematic.components[0].bit_width = 0;
+        let file = ProjectFile::new(&schematic, &Library::new(), EditorState::default());
+        let options = Options {
+            project: temp_file("bad.json", &file.to_json()),
+            ..Options::default()
+        };
+        let err = run(&options).unwrap_err();
+        assert!(err.ends_with("component 0: bit width 0 is not between 1 and 255"));
+    }
+
+    #[test]
+    fn parses_values_in_every_form() {
+        assert_eq!(parse_value("12", 4), Ok(Value::new(12, 4)));
+        assert_eq!(parse_value("-1", 4), Ok(Value::new(0xF, 4)));
+        assert_eq!(parse_value("-8", 4), Ok(Value::new(0x8, 4)));
+        assert_eq!(parse_value("0x1F", 8), Ok(Value::new(0x1F, 8)));
+        assert_eq!(parse_value("0b1z", 3).unwrap().to_string(), "01Z");
+        assert_eq!(parse_value("x", 2), Ok(Value::unknown(2)));
+        assert!(parse_value("16", 4).is_err());
+        assert!(parse_value("-9", 4).is_err());
+        assert!(parse_value("0b102", 4).is_err());
+
+        let wide = parse_value("-1", 100).unwrap();
+        assert_eq!(Radix::Hex.format(&wide), "F".repeat(25));
+    }
+}
@@ -162,6 +162,22 @@ impl GridCamera {
             });
     }
 
+    pub fn get_position(&self) -> Vec2 {
+        self.target_position
+    }
+
+    pub fn get_zoom(&self) -> f32 {
+        self.target_zoom
+    }
+
+    /// Jump to a view without animating
+    pub fn set_view(&mut self, position: Vec2, zoom: f32) {
+        self.position = position;
+        self.target_position = position;
+        self.zoom = zoom.clamp(10., 200.);
+        self.target_zoom = self.zoom;
+    }
+
     pub fn get_pixel_thickness(&self) -> f32 {
         let screen_point1 = Vec2::new(0.0, 0.0);
         let screen_point2 = Vec2::new(1.0, 0.0);
@@ -0,0 +1,657 @@
+use std::collections::BTreeMap;
+
+use backend::component::io::Radix;
+use backend::schematic::Library;
+use backend::schematic::components::{ComponentKind, ComponentSettings, PlacedComponent};
+use backend::schematic::nets::{Direction, GridPos};
+use backend::value::{BitState, Value};
+use egui_macroquad::macroquad::prelude::*;
+
+use crate::gui::component_utils::{ComponentGraphics, DrawInstruction};
+
+use super::camera::GridCamera;
+use super::history::{ComponentChange, Edit};
+
+const BEZIER_SEGMENTS: usize = 16;
+/// Labels are hidden when zoomed out so far that text would be smaller than this
+const MIN_LABEL_PIXELS: f32 = 8.0;
+const TUNNEL_HIGHLIGHT_COLOR: Color = Color::new(1.0, 0.8, 0.2, 0.25);
+
+/// Identifies a placed component for as long as it exists. Never reused.
+pub type ComponentId = u32;
+
+pub struct ComponentSystem {
+    components: BTreeMap<ComponentId, PlacedComponent>,
+    next_id: ComponentId,
+    revision: u64,     // bumped on every edit
+    facing: Direction, // of the next placed component
+    edits: Vec<Edit>,  // made by the user since the last `take_edits`
+}
+
+impl ComponentSystem {
+    pub fn new() -> Self {
+        Self {
+            components: BTreeMap::new(),
+            next_id: 0,
+            revision: 0,
+            facing: Direction::East,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Every component, in the order they were placed
+    pub fn components(&self) -> impl Iterator<Item = &PlacedComponent> {
+        self.components.values()
+    }
+
+    /// Replace every component
+    pub fn load(&mut self, components: Vec<PlacedComponent>) {
+        self.components.clear();
+        for component in components {
+            self.components.insert(self.next_id, component);
+            self.next_id += 1;
+        }
+        self.revision += 1;
+    }
+
+    /// Changes every time a component is added or removed
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Edits made by user input since the last call, for the undo history
+    pub fn take_edits(&mut self) -> Vec<Edit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Set each changed component to its `after` state
+    pub fn apply_changes(&mut self, changes: &[ComponentChange]) {
+        for change in changes {
+            match &change.after {
+                Some(component) => {
+                    self.components.insert(change.id, component.clone());
+                }
+                None => {
+                    self.components.remove(&change.id);
+                }
+            }
+            self.next_id = self.next_id.max(change.id + 1);
+        }
+        self.revision += 1;
+    }
+
+    pub fn get(&self, id: ComponentId) -> Option<&PlacedComponent> {
+        self.components.get(&id)
+    }
+
+    /// Every component with its id, in the order they were placed
+    pub fn iter(&self) -> impl Iterator<Item = (ComponentId, &PlacedComponent)> {
+        self.components
+            .iter()
+            .map(|(id, component)| (*id, component))
+    }
+
+    /// Component whose body covers `pos`
+    pub fn component_at(&self, pos: GridPos) -> Option<ComponentId> {
+        self.iter()
+            .find(|(_, component)| component.contains(pos))
+            .map(|(id, _)| id)
+    }
+
+    /// Add a component under a new id and return the change made
+    pub fn add(&mut self, component: PlacedComponent) -> ComponentChange {
+        let change = ComponentChange {
+            id: self.next_id,
+            before: None,
+            after: Some(component),
+        };
+        self.apply_changes(std::slice::from_ref(&change));
+        change
+    }
+
+    /// Replace or remove (`None`) a component and return the change made, if any
+    pub fn set(
+        &mut self,
+        id: ComponentId,
+        component: Option<PlacedComponent>,
+    ) -> Option<ComponentChange> {
+        let before = self.components.get(&id).cloned();
+        if before == component {
+            return None;
+        }
+        let change = ComponentChange {
+            id,
+            before,
+            after: component,
+        };
+        self.apply_changes(std::slice::from_ref(&change));
+        Some(change)
+    }
+
+    /// Remove the component covering `pos`, if any, and return the change made
+    pub fn erase_at(&mut self, pos: GridPos) -> Option<ComponentChange> {
+        let id = self.component_at(pos)?;
+        self.set(id, None)
+    }
+
+    /// Give every subcircuit instance the pins of its definition in `library`, returning the
+    /// changes made
+    pub fn match_library(&mut self, library: &Library) -> Vec<ComponentChange> {
+        let updated: Vec<_> = self
+            .iter()
+            .filter_map(|(id, component)| {
+                let definition = library.get(component.subcircuit.as_ref()?)?;
+                let mut updated = component.clone();
+                updated.match_definition(definition);
+                Some((id, updated))
+            })
+            .collect();
+        updated
+            .into_iter()
+            .filter_map(|(id, component)| self.set(id, Some(component)))
+            .collect()
+    }
+
+    /// Give every tunnel named `from` the name `to`, returning the changes made
+    pub fn rename_tunnels(&mut self, from: &str, to: &str) -> Vec<ComponentChange> {
+        let renamed: Vec<_> = self
+            .iter()
+            .filter(|(_, component)| {
+                component.kind == ComponentKind::Tunnel && component.label == from
+            })
+            .map(|(id, component)| {
+                let mut renamed = component.clone();
+                renamed.label = to.to_string();
+                (id, renamed)
+            })
+            .collect();
+        renamed
+            .into_iter()
+            .filter_map(|(id, component)| self.set(id, Some(component)))
+            .collect()
+    }
+
+    /// Every tunnel with the same name as the one covering `pos`, itself included
+    pub fn matching_tunnels(&self, pos: GridPos) -> Vec<&PlacedComponent> {
+        let hovered = self
+            .component_at(pos)
+            .and_then(|id| self.get(id))
+            .filter(|component| component.kind == ComponentKind::Tunnel);
+        let Some(name) = hovered.map(|tunnel| &tunnel.label) else {
+            return Vec::new();
+        };
+        self.components()
+            .filter(|component| component.kind == ComponentKind::Tunnel && component.label == *name)
+            .collect()
+    }
+
+    /// Index of component `id` in placement order, as used by `CompiledInstance`
+    pub fn index_of(&self, id: ComponentId) -> Option<usize> {
+        self.components.keys().position(|key| *key == id)
+    }
+
+    /// Index of the component covering `pos` in placement order, as used by
+    /// `CompiledInstance::children`
+    pub fn index_at(&self, pos: GridPos) -> Option<usize> {
+        self.components()
+            .position(|component| component.contains(pos))
+    }
+
+    /// Where `template` would be placed with the mouse over `cell`, centring the body on the
+    /// cursor
+    fn placement_at(&self, template: &PlacedComponent, cell: GridPos) -> PlacedComponent {
+        let mut component = template.clone();
+        component.facing = self.facing;
+        let (width, height) = component.size();
+        component.position = (cell.0 - width / 2, cell.1 - height / 2);
+        component
+    }
+
+    fn hovered_cell(camera: &GridCamera) -> GridPos {
+        let mouse_screen = Vec2::new(mouse_position().0, mouse_position().1);
+        let mouse_world = camera.screen_to_world(mouse_screen);
+        (mouse_world.x.floor() as i32, mouse_world.y.floor() as i32)
+    }
+
+    fn can_place(&self, component: &PlacedComponent) -> bool {
+        !self.components().any(|other| other.overlaps(component))
+    }
+
+    /// Place a copy of `selected` under the mouse on left click. R rotates it clockwise.
+    pub fn handle_input(&mut self, camera: &GridCamera, selected: &PlacedComponent) {
+        if is_key_pressed(KeyCode::R) {
+            self.facing = match self.facing {
+                Direction::North => Direction::East,
+                Direction::East => Direction::South,
+                Direction::South => Direction::West,
+                Direction::West => Direction::North,
+            };
+        }
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let component = self.placement_at(selected, Self::hovered_cell(camera));
+            if self.can_place(&component) {
+                let change = self.add(component);
+                self.edits.push(Edit {
+                    components: vec![change],
+                    ..Edit::default()
+                });
+            }
+        }
+    }
+
+    pub fn draw_components(&self, camera: &GridCamera) {
+        let (view_min, view_max) = camera.get_view_bounds();
+
+        for component in self.components() {
+            let (width, height) = component.size();
+            let (x, y) = (component.position.0 as f32, component.position.1 as f32);
+            if x + width as f32 + 1.0 >= view_min.x
+                && x - 1.0 <= view_max.x
+                && y + height as f32 >= view_min.y
+                && y <= view_max.y
+            {
+                draw_component(component, camera, WHITE);
+            }
+        }
+    }
+
+    /// Highlight every tunnel with the same name as the one under the mouse, showing which
+    /// nets it joins
+    pub fn draw_tunnel_highlights(&self, camera: &GridCamera) {
+        for tunnel in self.matching_tunnels(Self::hovered_cell(camera)) {
+            let (width, height) = tunnel.size();
+            let (x, y) = (tunnel.position.0 as f32, tunnel.position.1 as f32);
+            draw_rectangle(x, y, width as f32, height as f32, TUNNEL_HIGHLIGHT_COLOR);
+        }
+    }
+
+    /// Ghost of the selected component snapped to the grid under the mouse
+    pub fn draw_preview(&self, camera: &GridCamera, selected: &PlacedComponent) {
+        let component = self.placement_at(selected, Self::hovered_cell(camera));
+        let color = if self.can_place(&component) {
+            Color::new(1.0, 1.0, 1.0, 0.4)
+        } else {
+            Color::new(1.0, 0.2, 0.2, 0.4)
+        };
+        draw_component(&component, camera, color);
+    }
+
+    /// Names of subcircuit instances and their pins, labels of pins and tunnels, and the
+    /// values shown by pins, probes and constants. `value_of` gives the live value of the
+    /// component at an index. Drawn in screen space, after the canvas.
+    pub fn draw_labels<'a>(
+        &self,
+        camera: &GridCamera,
+        library: &Library,
+        value_of: impl Fn(usize, &PlacedComponent) -> Option<&'a Value>,
+    ) {
+        let font_size = camera.get_cell_pixels() * 0.6;
+        if font_size < MIN_LABEL_PIXELS {
+            return;
+        }
+        let (view_min, view_max) = camera.get_view_bounds();
+
+        for (index, component) in self.components().enumerate() {
+            let (width, height) = component.size();
+            let (x, y) = (component.position.0 as f32, component.position.1 as f32);
+            if x + width as f32 + 2.0 < view_min.x
+                || x - 2.0 > view_max.x
+                || y + height as f32 + 1.0 < view_min.y
+                || y - 1.0 > view_max.y
+            {
+                continue;
+            }
+            let center = vec2(x + width as f32 / 2.0, y + height as f32 / 2.0);
+
+            if let ComponentSettings::Value(value) = component.settings {
+                let value = Value::from_u64(value, component.bit_width);
+                draw_value(camera, component, &value, font_size);
+            } else if (component.kind.shows_bits() || component.kind == ComponentKind::Probe)
+                && let Some(value) = value_of(index, component)
+            {
+                draw_value(camera, component, value, font_size);
+            }
+
+            match component.kind {
+                ComponentKind::Input | ComponentKind::Output | ComponentKind::Clock
+                    if !component.label.is_empty() =>
+                {
+                    // On the side away from the pin
+                    let away = match component.kind {
+                        ComponentKind::Output => component.facing,
+                        _ => component.facing.opposite(),
+                    };
+                    let (dx, dy) = away.offset();
+                    let half = vec2(width as f32, height as f32) / 2.0;
+                    let edge = center + vec2(dx as f32 * half.x, dy as f32 * half.y);
+                    draw_label(camera, &component.label, edge, away, font_size);
+                }
+                ComponentKind::Subcircuit => {
+                    let Some(name) = &component.subcircuit else {
+                        continue;
+                    };
+                    // Name above the body on screen, whichever way up the world is
+                    let corners = [vec2(x, y), vec2(x + width as f32, y + height as f32)]
+                        .map(|corner| camera.world_to_screen(corner));
+                    let top = corners[0].y.min(corners[1].y);
+                    let size = measure_text(name, None, font_size as u16, 1.0);
+                    let screen_center = camera.world_to_screen(center);
+                    draw_text(
+                        name,
+                        screen_center.x - size.width / 2.0,
+                        top - size.height * 0.5,
+                        font_size,
+                        WHITE,
+                    );
+
+                    let Some(definition) = library.get(name) else {
+                        continue;
+                    };
+                    let (inputs, outputs) = definition.ports();
+                    let labels = inputs
+                        .into_iter()
+                        .chain(outputs)
+                        .map(|port| definition.components[port].label.as_str());
+                    draw_pin_labels(camera, component, labels, font_size * 0.8);
+                }
+                ComponentKind::Tunnel => {
+                    draw_centered_text(camera, &component.label, center, font_size, WHITE);
+                }
+                kind if kind.is_splitter() => {
+                    let labels = splitter_pin_labels(component);
+                    let labels = labels.iter().map(String::as_str);
+                    draw_pin_labels(camera, component, labels, font_size * 0.8);
+                }
+                kind if kind.is_plexer() => {
+                    let labels = plexer_pin_labels(component);
+                    let labels = labels.iter().map(String::as_str);
+                    draw_pin_labels(camera, component, labels, font_size * 0.8);
+                }
+                kind => {
+                    let (inputs, outputs) = kind.get_pin_labels();
+                    let labels = inputs.iter().chain(outputs).copied();
+                    draw_pin_labels(camera, component, labels, font_size * 0.8);
+                }
+            }
+        }
+    }
+}
+
+/// Colour of a wire carrying a single bit in this state
+fn bit_color(state: BitState) -> Color {
+    match state {
+        BitState::Logic0 => DARKGREEN,
+        BitState::Logic1 => GREEN,
+        BitState::HighZ => BLUE,
+        BitState::Unknown => RED,
+    }
+}
+
+/// Write out the value inside the body: a digit per bit cell of an input or output pin (a
+/// lamp for a single-bit output), a probe's value in its radix, or a constant in hex
+fn draw_value(camera: &GridCamera, component: &PlacedComponent, value: &Value, font_size: f32) {
+    let (width, height) = component.size();
+    let center = vec2(
+        component.position.0 as f32 + width as f32 / 2.0,
+        component.position.1 as f32 + height as f32 / 2.0,
+    );
+    match component.kind {
+        ComponentKind::Output if value.width() == 1 => {
+            let screen = camera.world_to_screen(center);
+            let radius = camera.get_cell_pixels() * 0.3;
+            draw_circle(
+                screen.x,
+                screen.y,
+                radius,
+                bit_color(value.get_bit_state(0)),
+            );
+        }
+        kind if kind.shows_bits() => {
+            for bit in 0..value.width().min(component.bit_width) {
+                let state = value.get_bit_state(bit);
+                let digit = match state {
+                    BitState::Logic0 => "0",
+                    BitState::Logic1 => "1",
+                    BitState::HighZ => "Z",
+                    BitState::Unknown => "X",
+                };
+                let cell = match component.bit_width {
+                    1 => center,
+                    _ => {
+                        let (x, y) = component.bit_cell(bit);
+                        vec2(x as f32 + 0.5, y as f32 + 0.5)
+                    }
+                };
+                draw_centered_text(camera, digit, cell, font_size, bit_color(state));
+            }
+        }
+        ComponentKind::Probe => {
+            let radix = match component.settings {
+                ComponentSettings::Radix(radix) => radix,
+                _ => Radix::default(),
+            };
+            let text = radix.format(value);
+            draw_centered_text(camera, &text, center, font_size, WHITE);
+        }
+        ComponentKind::Constant => {
+            draw_centered_text(camera, &Radix::Hex.format(value), center, font_size, WHITE);
+        }
+        _ => {}
+    }
+}
+
+/// Draw `text` in screen space centred on the world point `center`
+fn draw_centered_text(camera: &GridCamera, text: &str, center: Vec2, font_size: f32, color: Color) {
+    let screen = camera.world_to_screen(center);
+    let size = measure_text(text, None, font_size as u16, 1.0);
+    let x = screen.x - size.width / 2.0;
+    draw_text(text, x, screen.y + size.height / 2.0, font_size, color);
+}
+
+/// Bus bits on each fan of a splitter or joiner, such as "0-3" or "1,4". The bus pin, and the
+/// output pins on the same cells, get no label.
+fn splitter_pin_labels(component: &PlacedComponent) -> Vec<String> {
+    let fan_map = component.fan_map();
+    let fans = (0..component.fan_count()).map(|fan| {
+        let bits: Vec<usize> = (0..fan_map.len())
+            .filter(|&bit| fan_map[bit] == Some(fan))
+            .collect();
+        let contiguous = bits.windows(2).all(|pair| pair[1] == pair[0] + 1);
+        match bits.as_slice() {
+            [] => String::new(),
+            [bit] => bit.to_string(),
+            [first, .., last] if contiguous => format!("{first}-{last}"),
+            _ => bits
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        }
+    });
+    std::iter::once(String::new()).chain(fans).collect()
+}
+
+/// Names of a plexer's pins in pin order, numbering its ways
+fn plexer_pin_labels(component: &PlacedComponent) -> Vec<String> {
+    let ways = (0..1u32 << component.select_width()).map(|way| way.to_string());
+    let named = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+    };
+    match component.kind {
+        ComponentKind::Multiplexer => ways.chain(named(&["S"])).collect(),
+        ComponentKind::Demultiplexer => named(&["", "S"]).into_iter().chain(ways).collect(),
+        ComponentKind::Decoder => named(&["S", "en"]).into_iter().chain(ways).collect(),
+        _ => ways.chain(named(&["#", "v"])).collect(),
+    }
+}
+
+/// Direction from a pin's cell to the body cell next to it
+fn towards_body(component: &PlacedComponent, (x, y): GridPos) -> Direction {
+    Direction::ALL
+        .into_iter()
+        .find(|direction| {
+            let (dx, dy) = direction.offset();
+            component.contains((x + dx, y + dy))
+        })
+        .unwrap_or(component.facing)
+}
+
+/// Label the inside of the body next to each pin, inputs then outputs
+fn draw_pin_labels<'a>(
+    camera: &GridCamera,
+    component: &PlacedComponent,
+    labels: impl Iterator<Item = &'a str>,
+    font_size: f32,
+) {
+    let pins = component
+        .input_pins()
+        .into_iter()
+        .chain(component.output_pins());
+    for (pin, label) in pins.zip(labels) {
+        let inwards = towards_body(component, pin);
+        let (dx, dy) = inwards.offset();
+        let edge = vec2(pin.0 as f32 + 0.5, pin.1 as f32 + 0.5) + vec2(dx as f32, dy as f32) * 0.6;
+        draw_label(camera, label, edge, inwards, font_size);
+    }
+}
+
+/// Draw `text` in screen space with its box touching the world point `anchor` and extending
+/// towards `away`
+fn draw_label(camera: &GridCamera, text: &str, anchor: Vec2, away: Direction, font_size: f32) {
+    if text.is_empty() {
+        return;
+    }
+    let (dx, dy) = away.offset();
+    let screen = camera.world_to_screen(anchor);
+    let direction =
+        (camera.world_to_screen(anchor + vec2(dx as f32, dy as f32)) - screen).normalize_or_zero();
+    let size = measure_text(text, None, font_size as u16, 1.0);
+    let half = vec2(size.width, size.height) / 2.0;
+    let padding = font_size * 0.2;
+    let center =
+        screen + direction * (half.x * direction.x.abs() + half.y * direction.y.abs() + padding);
+    draw_text(text, center.x - half.x, center.y + half.y, font_size, WHITE);
+}
+
+/// Draw the body geometry stretched over the component's cells, with a stub out to every pin
+fn draw_component(component: &PlacedComponent, camera: &GridCamera, color: Color) {
+    let thickness = camera.get_pixel_thickness() * 2.0;
+    let (width, height) = component.size();
+    let origin = vec2(component.position.0 as f32, component.position.1 as f32);
+    let size = vec2(width as f32, height as f32);
+    // The geometry faces east, so turn it the same way as the pin layout
+    let to_world = |p: &epaint::Pos2| {
+        let rotated = match component.facing {
+            Direction::East => vec2(p.x, p.y),
+            Direction::South => vec2(1.0 - p.y, p.x),
+            Direction::West => vec2(1.0 - p.x, 1.0 - p.y),
+            Direction::North => vec2(p.y, 1.0 - p.x),
+        };
+        origin + rotated * size
+    };
+    // Radii scale with the body's length along its facing
+    let length = if component.facing.is_vertical() {
+        size.y
+    } else {
+        size.x
+    };
+
+    for instruction in component.kind.get_draw_instructions() {
+        match instruction {
+            DrawInstruction::Line([a, b]) => {
+                let (a, b) = (to_world(a), to_world(b));
+                draw_line(a.x, a.y, b.x, b.y, thickness, color);
+            }
+            DrawInstruction::CubicBezierCurve([a, c1, c2, b]) => {
+                let points = [to_world(a), to_world(c1), to_world(c2), to_world(b)];
+                let mut previous = points[0];
+                for i in 1..=BEZIER_SEGMENTS {
+                    let point = cubic_bezier(&points, i as f32 / BEZIER_SEGMENTS as f32);
+                    draw_line(previous.x, previous.y, point.x, point.y, thickness, color);
+                    previous = point;
+                }
+            }
+            DrawInstruction::Circle(center, radius) => {
+                let center = to_world(center);
+                draw_circle_lines(center.x, center.y, radius * length, thickness, color);
+            }
+        }
+    }
+
+    let pin_radius = 0.12;
+    for (x, y) in component
+        .input_pins()
+        .into_iter()
+        .chain(component.output_pins())
+    {
+        let pin = vec2(x as f32 + 0.5, y as f32 + 0.5);
+        let (dx, dy) = towards_body(component, (x, y)).offset();
+        let edge = pin + vec2(dx as f32, dy as f32) * 0.5;
+        draw_line(pin.x, pin.y, edge.x, edge.y, thickness, color);
+        draw_circle(pin.x, pin.y, pin_radius, color);
+    }
+}
+
+fn cubic_bezier(points: &[Vec2; 4], t: f32) -> Vec2 {
+    let u = 1.0 - t;
+    points[0] * (u * u * u)
+        + points[1] * (3.0 * u * u * t)
+        + points[2] * (3.0 * u * t * t)
+        + points[3] * (t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tunnels named "a", "b" and "a" in a column, then an input pin also labelled "a"
+    fn components() -> ComponentSystem {
+        let mut components = ComponentSystem::new();
+        let placed = ["a", "b", "a"].into_iter().enumerate().map(|(row, name)| {
+            let mut tunnel = PlacedComponent::new(ComponentKind::Tunnel, (0, row as i32 * 4));
+            tunnel.label = name.to_string();
+            tunnel
+        });
+        let mut input = PlacedComponent::new(ComponentKind::Input, (0, 12));
+        input.label = "a".to_string();
+        components.load(placed.chain([input]).collect());
+        components
+    }
+
+    #[test]
+    fn renaming_changes_every_tunnel_of_the_name() {
+        let mut components = components();
+        let before: Vec<PlacedComponent> = components.components().cloned().collect();
+        let changes = components.rename_tunnels("a", "c");
+        assert_eq!(changes.len(), 2);
+        let labels: Vec<&str> = components.components().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["c", "b", "c", "a"]);
+
+        // One undo puts every name back
+        let edit = Edit {
+            components: changes,
+            ..Edit::default()
+        };
+        components.apply_changes(&edit.inverse().components);
+        assert!(components.components().eq(&before));
+        assert!(components.rename_tunnels("missing", "d").is_empty());
+    }
+
+    #[test]
+    fn hovering_a_tunnel_matches_those_of_its_name() {
+        let components = components();
+        let rows = |pos| -> Vec<i32> {
+            let tunnels = components.matching_tunnels(pos);
+            tunnels.iter().map(|tunnel| tunnel.position.1).collect()
+        };
+        assert_eq!(rows((0, 0)), [0, 8]);
+        assert_eq!(rows((0, 8)), [0, 8]);
+        assert_eq!(rows((0, 4)), [4]);
+        // Neither the input pin of the same name nor empty cells match anything
+        assert!(rows((0, 12)).is_empty());
+        assert!(rows((40, 40)).is_empty());
+    }
+}
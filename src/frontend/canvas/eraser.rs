@@ -0,0 +1,106 @@
+use backend::schematic::nets::{Channel, GridPos};
+use egui_macroquad::macroquad::prelude::*;
+
+use super::camera::GridCamera;
+use super::components::ComponentSystem;
+use super::history::Edit;
+use super::wiring::WireSystem;
+
+/// Removes wires and components under the mouse. Dragging erases everything along the path as
+/// one edit; shift-clicking a wire erases its whole net.
+pub struct EraseTool {
+    stroke: Edit,
+    last_position: Option<Vec2>, // mouse position last frame of the current stroke
+}
+
+impl EraseTool {
+    pub fn new() -> Self {
+        Self {
+            stroke: Edit::default(),
+            last_position: None,
+        }
+    }
+
+    fn mouse_world(camera: &GridCamera) -> Vec2 {
+        camera.screen_to_world(Vec2::new(mouse_position().0, mouse_position().1))
+    }
+
+    fn cell_at(position: Vec2) -> GridPos {
+        (position.x.floor() as i32, position.y.floor() as i32)
+    }
+
+    fn erase_cells(
+        &mut self,
+        cells: &[(GridPos, Channel)],
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+    ) {
+        self.stroke.wires.extend(wires.erase(cells));
+        for (pos, channel) in cells {
+            if *channel == Channel::Whole
+                && let Some(change) = components.erase_at(*pos)
+            {
+                self.stroke.components.push(change);
+            }
+        }
+    }
+
+    /// Erase under the mouse. Returns the edit once a stroke or net deletion is finished.
+    pub fn handle_input(
+        &mut self,
+        camera: &GridCamera,
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+    ) -> Option<Edit> {
+        let position = Self::mouse_world(camera);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+
+        if is_mouse_button_pressed(MouseButton::Left) && shift {
+            let net = wires.net_cells_at(Self::cell_at(position));
+            self.erase_cells(&net, wires, components);
+            return Some(std::mem::take(&mut self.stroke));
+        }
+
+        if is_mouse_button_down(MouseButton::Left) {
+            // Visit every cell between the last and current mouse positions, so fast drags
+            // don't skip any
+            let start = self.last_position.unwrap_or(position);
+            let steps = (start.distance(position) * 4.0).ceil().max(1.0) as usize;
+            let mut cells: Vec<(GridPos, Channel)> = Vec::new();
+            for i in 0..=steps {
+                let cell = Self::cell_at(start.lerp(position, i as f32 / steps as f32));
+                if !cells.contains(&(cell, Channel::Whole)) {
+                    cells.push((cell, Channel::Whole));
+                }
+            }
+            self.erase_cells(&cells, wires, components);
+            self.last_position = Some(position);
+            None
+        } else {
+            self.last_position = None;
+            (!self.stroke.is_empty()).then(|| std::mem::take(&mut self.stroke))
+        }
+    }
+
+    /// Outline what a click would erase
+    pub fn draw_preview(&self, camera: &GridCamera, wires: &WireSystem) {
+        let hovered = Self::cell_at(Self::mouse_world(camera));
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        let cells = if shift {
+            wires.net_cells_at(hovered)
+        } else {
+            vec![(hovered, Channel::Whole)]
+        };
+
+        for ((x, y), _) in cells {
+            draw_rectangle_lines(
+                x as f32,
+                y as f32,
+                1.0,
+                1.0,
+                camera.get_pixel_thickness() * 2.0,
+                Color::new(1.0, 0.2, 0.2, 0.8),
+            );
+        }
+    }
+}
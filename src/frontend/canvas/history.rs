@@ -0,0 +1,272 @@
+use std::collections::VecDeque;
+
+use backend::schematic::components::PlacedComponent;
+use backend::schematic::nets::{GridPos, WireCell};
+
+use super::components::{ComponentId, ComponentSystem};
+use super::wiring::WireSystem;
+
+/// Most edits kept for undo
+const MAX_EDITS: usize = 500;
+/// Most wire and component changes kept across all edits, so huge pastes can't grow the
+/// history without bound
+const MAX_CHANGES: usize = 200_000;
+
+/// Wire cell at `pos` going from `before` to `after`. `None` is an empty cell.
+#[derive(Debug, Clone)]
+pub struct WireChange {
+    pub pos: GridPos,
+    pub before: Option<WireCell>,
+    pub after: Option<WireCell>,
+}
+
+/// Component `id` going from `before` to `after`. `None` means it doesn't exist.
+#[derive(Debug, Clone)]
+pub struct ComponentChange {
+    pub id: ComponentId,
+    pub before: Option<PlacedComponent>,
+    pub after: Option<PlacedComponent>,
+}
+
+/// One undoable step, made of every change caused by a single user action
+#[derive(Debug, Clone, Default)]
+pub struct Edit {
+    pub wires: Vec<WireChange>,
+    pub components: Vec<ComponentChange>,
+}
+
+impl Edit {
+    pub fn is_empty(&self) -> bool {
+        self.wires.is_empty() && self.components.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.wires.len() + self.components.len()
+    }
+
+    /// Edit that undoes this one
+    pub fn inverse(&self) -> Edit {
+        Edit {
+            wires: self
+                .wires
+                .iter()
+                .rev()
+                .map(|change| WireChange {
+                    pos: change.pos,
+                    before: change.after,
+                    after: change.before,
+                })
+                .collect(),
+            components: self
+                .components
+                .iter()
+                .rev()
+                .map(|change| ComponentChange {
+                    id: change.id,
+                    before: change.after.clone(),
+                    after: change.before.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Move the canvas to the `after` state of every change
+    pub fn apply(&self, wires: &mut WireSystem, components: &mut ComponentSystem) {
+        if !self.wires.is_empty() {
+            wires.apply_changes(&self.wires);
+        }
+        if !self.components.is_empty() {
+            components.apply_changes(&self.components);
+        }
+    }
+}
+
+/// Undo and redo stacks of canvas edits
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    changes: usize, // total length of the edits in `undo`
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an edit that has already been applied. Clears the redo stack.
+    pub fn push(&mut self, edit: Edit) {
+        if edit.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.changes += edit.len();
+        self.undo.push_back(edit);
+
+        // Always keep the newest edit, however large
+        while self.undo.len() > 1 && (self.undo.len() > MAX_EDITS || self.changes > MAX_CHANGES) {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.changes -= oldest.len();
+            }
+        }
+    }
+
+    pub fn undo(&mut self, wires: &mut WireSystem, components: &mut ComponentSystem) -> bool {
+        let Some(edit) = self.undo.pop_back() else {
+            return false;
+        };
+        self.changes -= edit.len();
+        edit.inverse().apply(wires, components);
+        self.redo.push(edit);
+        true
+    }
+
+    pub fn redo(&mut self, wires: &mut WireSystem, components: &mut ComponentSystem) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        edit.apply(wires, components);
+        self.changes += edit.len();
+        self.undo.push_back(edit);
+        true
+    }
+
+    /// Forget every edit, e.g. after opening a file
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.changes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use backend::schematic::components::ComponentKind;
+    use egui_macroquad::macroquad::prelude::vec2;
+
+    use super::*;
+
+    const HORIZONTAL: WireCell = WireCell {
+        north: false,
+        east: true,
+        south: false,
+        west: true,
+        junction: false,
+    };
+
+    fn canvas() -> (WireSystem, ComponentSystem) {
+        (WireSystem::headless(), ComponentSystem::new())
+    }
+
+    /// Every wire cell, sorted, and every component
+    fn snapshot(
+        wires: &WireSystem,
+        components: &ComponentSystem,
+    ) -> (Vec<(GridPos, WireCell)>, Vec<PlacedComponent>) {
+        let mut cells: Vec<_> = wires.cells().collect();
+        cells.sort_by_key(|(pos, _)| *pos);
+        (cells, components.components().cloned().collect())
+    }
+
+    /// Edit adding an empty wire cell at `(x, 0)`, without applying it
+    fn single(x: i32) -> Edit {
+        Edit {
+            wires: vec![WireChange {
+                pos: (x, 0),
+                before: None,
+                after: Some(WireCell::default()),
+            }],
+            ..Edit::default()
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let (mut wires, mut components) = canvas();
+        let mut history = History::new();
+        let empty = snapshot(&wires, &components);
+
+        let edit = Edit {
+            wires: wires.set_cells(&[((0, 0), Some(HORIZONTAL)), ((1, 0), Some(HORIZONTAL))]),
+            components: vec![components.add(PlacedComponent::new(ComponentKind::Input, (3, 0)))],
+        };
+        history.push(edit);
+        let edited = snapshot(&wires, &components);
+
+        assert!(history.undo(&mut wires, &mut components));
+        assert_eq!(snapshot(&wires, &components), empty);
+        assert!(!history.undo(&mut wires, &mut components));
+        assert!(history.redo(&mut wires, &mut components));
+        assert_eq!(snapshot(&wires, &components), edited);
+        assert!(!history.redo(&mut wires, &mut components));
+        assert!(history.undo(&mut wires, &mut components));
+        assert_eq!(snapshot(&wires, &components), empty);
+    }
+
+    #[test]
+    fn new_edits_drop_redo() {
+        let (mut wires, mut components) = canvas();
+        let mut history = History::new();
+        history.push(Edit {
+            wires: wires.set_cells(&[((0, 0), Some(HORIZONTAL))]),
+            ..Edit::default()
+        });
+        assert!(history.undo(&mut wires, &mut components));
+        history.push(Edit {
+            wires: wires.set_cells(&[((5, 5), Some(HORIZONTAL))]),
+            ..Edit::default()
+        });
+        assert!(!history.redo(&mut wires, &mut components));
+        assert_eq!(wires.cell((0, 0)), None);
+
+        // Empty edits change nothing, so they keep redo
+        assert!(history.undo(&mut wires, &mut components));
+        history.push(Edit::default());
+        assert!(history.redo(&mut wires, &mut components));
+    }
+
+    #[test]
+    fn oldest_edits_are_evicted() {
+        let mut history = History::new();
+        for x in 0..MAX_EDITS as i32 + 10 {
+            history.push(single(x));
+        }
+        assert_eq!(history.undo.len(), MAX_EDITS);
+        assert_eq!(history.undo[0].wires[0].pos, (10, 0));
+        assert_eq!(history.changes, MAX_EDITS);
+
+        // The newest edit stays however large, pushing out everything before it
+        let huge = Edit {
+            wires: (0..MAX_CHANGES as i32 + 1)
+                .flat_map(|x| single(x).wires)
+                .collect(),
+            ..Edit::default()
+        };
+        history.push(huge);
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.changes, MAX_CHANGES + 1);
+        history.push(single(-1));
+        assert_eq!(history.undo.len(), 1);
+        assert_eq!(history.changes, 1);
+    }
+
+    #[test]
+    fn l_shaped_drags_are_one_edit() {
+        let (mut wires, mut components) = canvas();
+        let mut history = History::new();
+        wires.draw_wire_path(vec2(0.0, 0.0), vec2(2.0, 2.0));
+        let edits = wires.take_edits();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].wires.len(), 5);
+        for edit in edits {
+            history.push(edit);
+        }
+        // Down from the start, then across to the end
+        let corner = wires.cell((0, 2)).unwrap();
+        assert!(corner.north && corner.east && !corner.south && !corner.west);
+
+        assert!(history.undo(&mut wires, &mut components));
+        assert_eq!(wires.cells().count(), 0);
+        assert!(!history.undo(&mut wires, &mut components));
+    }
+}
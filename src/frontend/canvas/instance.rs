@@ -0,0 +1,91 @@
+use backend::circuit::NetId;
+use backend::schematic::components::ComponentKind;
+use backend::schematic::{CompiledInstance, Library};
+use backend::value::Value;
+use egui_macroquad::macroquad::prelude::*;
+
+use super::camera::GridCamera;
+use super::components::ComponentSystem;
+use super::wiring::WireSystem;
+
+/// Read-only view of one subcircuit instance inside the running circuit, showing the
+/// definition's drawing with that instance's signals
+pub struct InstanceView {
+    path: Vec<usize>, // component indices from the main circuit down to this instance
+    name: String,
+    pub wires: WireSystem,
+    pub components: ComponentSystem,
+    return_view: (Vec2, f32), // camera position and zoom to restore on leaving
+}
+
+impl InstanceView {
+    /// View of the subcircuit instance at component `index` of `components`, which is the
+    /// drawing of the instance at `parent_path`. `None` if there is no instance there.
+    pub fn enter(
+        parent_path: &[usize],
+        components: &ComponentSystem,
+        index: usize,
+        library: &Library,
+        camera: &mut GridCamera,
+    ) -> Option<Self> {
+        let instance = components.components().nth(index)?;
+        if instance.kind != ComponentKind::Subcircuit {
+            return None;
+        }
+        let name = instance.subcircuit.clone()?;
+        let definition = library.get(&name)?;
+
+        let mut wires = WireSystem::new();
+        wires.load(&definition.wires);
+        let mut inner = ComponentSystem::new();
+        inner.load(definition.components.clone());
+
+        let return_view = (camera.get_position(), camera.get_zoom());
+        let cells: Vec<Vec2> = definition
+            .wires
+            .keys()
+            .chain(definition.components.iter().map(|c| &c.position))
+            .map(|&(x, y)| vec2(x as f32, y as f32))
+            .collect();
+        if !cells.is_empty() {
+            let center = cells.iter().copied().sum::<Vec2>() / cells.len() as f32;
+            camera.set_view(center, camera.get_zoom());
+        }
+
+        let mut path = parent_path.to_vec();
+        path.push(index);
+        Some(Self {
+            path,
+            name,
+            wires,
+            components: inner,
+            return_view,
+        })
+    }
+
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// Library circuit this is an instance of
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Put the camera back where it was before entering
+    pub fn leave(self, camera: &mut GridCamera) {
+        camera.set_view(self.return_view.0, self.return_view.1);
+    }
+
+    /// Show the signals of `instance`, which must have been compiled from this view's
+    /// definition
+    pub fn apply_signals<'a>(
+        &mut self,
+        instance: &CompiledInstance,
+        value_of: impl Fn(NetId) -> Option<&'a Value>,
+    ) {
+        self.wires.apply_signals(&instance.wire_nets, |net| {
+            instance.net_ids.get(net).and_then(|&id| value_of(id))
+        });
+    }
+}
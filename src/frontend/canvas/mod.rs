@@ -4,5 +4,10 @@
 // - Providing event hooks for mouse events (move, click, etc.)
 
 pub mod camera;
+pub mod components;
+pub mod eraser;
 pub mod grid;
+pub mod history;
+pub mod instance;
+pub mod selection;
 pub mod wiring;
@@ -0,0 +1,703 @@
+use std::collections::{BTreeSet, HashSet};
+
+use backend::schematic::{Library, Schematic};
+use backend::schematic::components::PlacedComponent;
+use backend::schematic::file::{EditorState, ProjectFile};
+use backend::schematic::nets::{Channel, Direction, GridPos, WireCell};
+use egui_macroquad::macroquad::miniquad;
+use egui_macroquad::macroquad::prelude::*;
+
+use super::camera::GridCamera;
+use super::components::{ComponentId, ComponentSystem};
+use super::history::Edit;
+use super::wiring::WireSystem;
+use crate::subcircuits::SubcircuitLibrary;
+
+const SELECTION_COLOR: Color = Color::new(0.3, 0.8, 1.0, 0.8);
+
+enum Drag {
+    /// Selecting everything inside the rectangle from `start` to the mouse
+    Box { start: Vec2 },
+    /// Moving the selection, grabbed at cell `start`
+    Move { start: GridPos },
+}
+
+/// Selects wires and components, and moves, rotates, flips, deletes and copies the selection
+///
+/// Copies go to the system clipboard in the project file format, with positions relative to
+/// the selection's top-left corner and the definitions of the subcircuits used, so they can be
+/// pasted into another project.
+pub struct SelectTool {
+    cells: HashSet<GridPos>,
+    components: BTreeSet<ComponentId>,
+    drag: Option<Drag>,
+}
+
+impl SelectTool {
+    pub fn new() -> Self {
+        Self {
+            cells: HashSet::new(),
+            components: BTreeSet::new(),
+            drag: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.components.clear();
+        self.drag = None;
+    }
+
+    /// The selected component, if exactly one is selected
+    pub fn single_component(&self) -> Option<ComponentId> {
+        match self.components.len() {
+            1 => self.components.first().copied(),
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.components.is_empty()
+    }
+
+    fn mouse_world(camera: &GridCamera) -> Vec2 {
+        camera.screen_to_world(Vec2::new(mouse_position().0, mouse_position().1))
+    }
+
+    fn cell_at(position: Vec2) -> GridPos {
+        (position.x.floor() as i32, position.y.floor() as i32)
+    }
+
+    /// Forget anything that no longer exists, e.g. after an undo
+    fn prune(&mut self, wires: &WireSystem, components: &ComponentSystem) {
+        self.cells.retain(|pos| wires.cell(*pos).is_some());
+        self.components.retain(|id| components.get(*id).is_some());
+    }
+
+    fn is_selected(&self, pos: GridPos, components: &ComponentSystem) -> bool {
+        self.cells.contains(&pos)
+            || components
+                .component_at(pos)
+                .is_some_and(|id| self.components.contains(&id))
+    }
+
+    /// The selected wires and components, copied out of the canvas
+    fn contents(&self, wires: &WireSystem, components: &ComponentSystem) -> Schematic {
+        Schematic {
+            wires: self
+                .cells
+                .iter()
+                .filter_map(|pos| Some((*pos, wires.cell(*pos)?)))
+                .collect(),
+            components: self
+                .components
+                .iter()
+                .filter_map(|id| components.get(*id).cloned())
+                .collect(),
+        }
+    }
+
+    /// Remove the selection from the canvas, clearing wire bits on neighbours that led into it
+    fn remove(&self, wires: &mut WireSystem, components: &mut ComponentSystem) -> Edit {
+        let cells: Vec<(GridPos, Channel)> = self
+            .cells
+            .iter()
+            .map(|pos| (*pos, Channel::Whole))
+            .collect();
+        Edit {
+            wires: wires.erase(&cells),
+            components: self
+                .components
+                .iter()
+                .filter_map(|id| components.set(*id, None))
+                .collect(),
+        }
+    }
+
+    /// Add wires and components to the canvas, merging wires into any already there, and
+    /// select them. Components keep the ids in `ids`, or get new ones.
+    fn insert(
+        &mut self,
+        contents: Schematic,
+        ids: Option<Vec<ComponentId>>,
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+        edit: &mut Edit,
+    ) {
+        let cells: Vec<(GridPos, Option<WireCell>)> = contents
+            .wires
+            .iter()
+            .map(|(pos, cell)| (*pos, Some(merge_cells(wires.cell(*pos), *cell))))
+            .collect();
+        edit.wires.extend(wires.set_cells(&cells));
+
+        self.clear();
+        self.cells.extend(contents.wires.keys());
+        for (i, component) in contents.components.into_iter().enumerate() {
+            let change = match &ids {
+                Some(ids) => components.set(ids[i], Some(component)),
+                None => Some(components.add(component)),
+            };
+            if let Some(change) = change {
+                self.components.insert(change.id);
+                edit.components.push(change);
+            }
+        }
+        if let Some(ids) = ids {
+            self.components.extend(ids);
+        }
+    }
+
+    /// Replace the selection with a transformed copy of itself
+    fn transform(
+        &mut self,
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+        transform: impl Fn(Schematic) -> Schematic,
+    ) -> Option<Edit> {
+        if self.is_empty() {
+            return None;
+        }
+        let contents = self.contents(wires, components);
+        let ids: Vec<ComponentId> = self.components.iter().copied().collect();
+        let mut edit = self.remove(wires, components);
+        self.insert(transform(contents), Some(ids), wires, components, &mut edit);
+        Some(edit)
+    }
+
+    fn copy(&self, wires: &WireSystem, components: &ComponentSystem, library: &Library) {
+        if let Some(text) = self.copy_text(wires, components, library) {
+            miniquad::window::clipboard_set(&text);
+        }
+    }
+
+    /// The selection as a project file, with the definitions from `library` of the subcircuits
+    /// in it, if anything is selected
+    fn copy_text(
+        &self,
+        wires: &WireSystem,
+        components: &ComponentSystem,
+        library: &Library,
+    ) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let contents = self.contents(wires, components);
+        let ((min_x, min_y), _) = bounds(&contents);
+        let contents = translate(contents, (-min_x, -min_y));
+        let definitions = used_definitions(&contents, library);
+        let file = ProjectFile::new(&contents, &definitions, EditorState::default());
+        Some(file.to_json())
+    }
+
+    /// Paste the clipboard with its top-left corner at `at`
+    fn paste(
+        &mut self,
+        at: GridPos,
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+        library: &mut SubcircuitLibrary,
+    ) -> Option<Edit> {
+        let text = miniquad::window::clipboard_get()?;
+        self.paste_text(&text, at, wires, components, library)
+    }
+
+    /// Paste a project file copied by `copy_text`, selecting what was pasted
+    ///
+    /// Subcircuit definitions the library lacks are added to it, which undo leaves in place.
+    /// Where the library already has a circuit of the same name, pasted instances take its
+    /// pins instead.
+    fn paste_text(
+        &mut self,
+        text: &str,
+        at: GridPos,
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+        library: &mut SubcircuitLibrary,
+    ) -> Option<Edit> {
+        let file = ProjectFile::from_json(text).ok()?;
+        library.merge(file.library());
+        let mut contents = file.schematic();
+        contents.match_library(library.circuits());
+        let mut edit = Edit::default();
+        self.insert(translate(contents, at), None, wires, components, &mut edit);
+        Some(edit)
+    }
+
+    /// Handle mouse and keyboard input. Returns an edit whenever the canvas was changed.
+    pub fn handle_input(
+        &mut self,
+        camera: &GridCamera,
+        wires: &mut WireSystem,
+        components: &mut ComponentSystem,
+        library: &mut SubcircuitLibrary,
+        keyboard: bool, // false while a text field has focus
+    ) -> Option<Edit> {
+        self.prune(wires, components);
+        let position = Self::mouse_world(camera);
+        let hovered = Self::cell_at(position);
+        let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if shift {
+                self.toggle(hovered, wires, components);
+            } else if self.is_selected(hovered, components) {
+                self.drag = Some(Drag::Move { start: hovered });
+            } else {
+                self.clear();
+                self.drag = Some(Drag::Box { start: position });
+            }
+        }
+
+        if !is_mouse_button_down(MouseButton::Left)
+            && let Some(drag) = self.drag.take()
+        {
+            match drag {
+                Drag::Box { start } => self.select_box(start, position, wires, components),
+                Drag::Move { start } => {
+                    let offset = (hovered.0 - start.0, hovered.1 - start.1);
+                    if offset != (0, 0) {
+                        return self.transform(wires, components, |c| translate(c, offset));
+                    }
+                }
+            }
+        }
+
+        if !keyboard || self.drag.is_some() {
+            return None;
+        }
+        if ctrl {
+            if is_key_pressed(KeyCode::C) {
+                self.copy(wires, components, library.circuits());
+            } else if is_key_pressed(KeyCode::X) {
+                self.copy(wires, components, library.circuits());
+                let edit = self.remove(wires, components);
+                self.clear();
+                return Some(edit);
+            } else if is_key_pressed(KeyCode::V) {
+                return self.paste(hovered, wires, components, library);
+            } else if is_key_pressed(KeyCode::A) {
+                self.cells = wires.cells().map(|(pos, _)| pos).collect();
+                self.components = components.iter().map(|(id, _)| id).collect();
+            }
+        } else if is_key_pressed(KeyCode::Delete) || is_key_pressed(KeyCode::Backspace) {
+            let edit = self.remove(wires, components);
+            self.clear();
+            return Some(edit);
+        } else if is_key_pressed(KeyCode::R) {
+            return self.transform(wires, components, rotate);
+        } else if is_key_pressed(KeyCode::F) {
+            return self.transform(wires, components, flip);
+        } else if is_key_pressed(KeyCode::Escape) {
+            self.clear();
+        }
+        None
+    }
+
+    /// Add or remove the wire or component under the mouse
+    fn toggle(&mut self, pos: GridPos, wires: &WireSystem, components: &ComponentSystem) {
+        if wires.cell(pos).is_some() {
+            if !self.cells.remove(&pos) {
+                self.cells.insert(pos);
+            }
+        } else if let Some(id) = components.component_at(pos)
+            && !self.components.remove(&id)
+        {
+            self.components.insert(id);
+        }
+    }
+
+    /// Select every wire cell inside the box and every component touching it
+    fn select_box(
+        &mut self,
+        start: Vec2,
+        end: Vec2,
+        wires: &WireSystem,
+        components: &ComponentSystem,
+    ) {
+        let (min, max) = (start.min(end), start.max(end));
+        let (min, max) = (Self::cell_at(min), Self::cell_at(max));
+        let inside = |(x, y): GridPos| min.0 <= x && x <= max.0 && min.1 <= y && y <= max.1;
+
+        self.cells = wires
+            .cells()
+            .map(|(pos, _)| pos)
+            .filter(|pos| inside(*pos))
+            .collect();
+        self.components = components
+            .iter()
+            .filter(|(_, component)| {
+                let (width, height) = component.size();
+                let (x, y) = component.position;
+                x <= max.0 && min.0 < x + width && y <= max.1 && min.1 < y + height
+            })
+            .map(|(id, _)| id)
+            .collect();
+    }
+
+    pub fn draw(&self, camera: &GridCamera, components: &ComponentSystem) {
+        let thickness = camera.get_pixel_thickness() * 2.0;
+        let position = Self::mouse_world(camera);
+
+        let offset = match self.drag {
+            Some(Drag::Move { start }) => {
+                let hovered = Self::cell_at(position);
+                vec2((hovered.0 - start.0) as f32, (hovered.1 - start.1) as f32)
+            }
+            _ => Vec2::ZERO,
+        };
+        for (x, y) in &self.cells {
+            let corner = vec2(*x as f32, *y as f32) + offset;
+            draw_rectangle_lines(corner.x, corner.y, 1.0, 1.0, thickness, SELECTION_COLOR);
+        }
+        for component in self.components.iter().filter_map(|id| components.get(*id)) {
+            let (width, height) = component.size();
+            let corner = vec2(component.position.0 as f32, component.position.1 as f32) + offset;
+            draw_rectangle_lines(
+                corner.x,
+                corner.y,
+                width as f32,
+                height as f32,
+                thickness,
+                SELECTION_COLOR,
+            );
+        }
+
+        if let Some(Drag::Box { start }) = self.drag {
+            let (min, max) = (start.min(position), start.max(position));
+            let size = max - min;
+            draw_rectangle(min.x, min.y, size.x, size.y, Color::new(0.3, 0.8, 1.0, 0.1));
+            draw_rectangle_lines(min.x, min.y, size.x, size.y, thickness, SELECTION_COLOR);
+        }
+    }
+}
+
+/// Union of the connections of two overlapping cells
+fn merge_cells(existing: Option<WireCell>, cell: WireCell) -> WireCell {
+    let Some(existing) = existing else {
+        return cell;
+    };
+    WireCell {
+        north: existing.north || cell.north,
+        east: existing.east || cell.east,
+        south: existing.south || cell.south,
+        west: existing.west || cell.west,
+        junction: existing.junction || cell.junction,
+    }
+}
+
+/// Definitions in `library` of the subcircuits in `contents`, and of those inside them
+fn used_definitions(contents: &Schematic, library: &Library) -> Library {
+    let mut used = Library::new();
+    let mut pending: Vec<&Schematic> = vec![contents];
+    while let Some(schematic) = pending.pop() {
+        let names = schematic
+            .components
+            .iter()
+            .filter_map(|c| c.subcircuit.as_ref());
+        for name in names {
+            if let Some(definition) = library.get(name)
+                && !used.contains_key(name)
+            {
+                used.insert(name.clone(), definition.clone());
+                pending.push(definition);
+            }
+        }
+    }
+    used
+}
+
+/// Smallest and largest cells covered by wires and component bodies
+fn bounds(contents: &Schematic) -> (GridPos, GridPos) {
+    let corners = contents.wires.keys().flat_map(|pos| [*pos, *pos]).chain(
+        contents.components.iter().flat_map(|component| {
+            let (width, height) = component.size();
+            let (x, y) = component.position;
+            [(x, y), (x + width - 1, y + height - 1)]
+        }),
+    );
+    corners.fold(
+        ((i32::MAX, i32::MAX), (i32::MIN, i32::MIN)),
+        |(min, max), (x, y)| ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y))),
+    )
+}
+
+fn translate(contents: Schematic, (dx, dy): GridPos) -> Schematic {
+    Schematic {
+        wires: contents
+            .wires
+            .into_iter()
+            .map(|((x, y), cell)| ((x + dx, y + dy), cell))
+            .collect(),
+        components: contents
+            .components
+            .into_iter()
+            .map(|mut component| {
+                component.position = (component.position.0 + dx, component.position.1 + dy);
+                component
+            })
+            .collect(),
+    }
+}
+
+/// Turn a quarter clockwise about the centre of the contents
+///
+/// When the centre falls between cells the result is half a cell off, rounding up and left
+/// for wide contents and down and right for tall ones, so four turns land back in place.
+fn rotate(contents: Schematic) -> Schematic {
+    let (min, max) = bounds(&contents);
+    // Twice the centre's coordinates, to stay exact
+    let (sx, sy) = (min.0 + max.0, min.1 + max.1);
+    let round = if max.0 - min.0 > max.1 - min.1 { 0 } else { 1 };
+    let dx = (sx + sy + round).div_euclid(2);
+    let dy = (sy - sx + round).div_euclid(2);
+    let turn = |(x, y): GridPos| (dx - y, dy + x);
+
+    Schematic {
+        wires: contents
+            .wires
+            .into_iter()
+            .map(|(pos, cell)| {
+                let cell = WireCell {
+                    north: cell.west,
+                    east: cell.north,
+                    south: cell.east,
+                    west: cell.south,
+                    junction: cell.junction,
+                };
+                (turn(pos), cell)
+            })
+            .collect(),
+        components: contents
+            .components
+            .into_iter()
+            .map(|mut component| {
+                let (_, height) = component.size();
+                let (x, y) = component.position;
+                // The body's bottom-left corner becomes its top-left
+                component.position = turn((x, y + height - 1));
+                component.facing = match component.facing {
+                    Direction::North => Direction::East,
+                    Direction::East => Direction::South,
+                    Direction::South => Direction::West,
+                    Direction::West => Direction::North,
+                };
+                component
+            })
+            .collect(),
+    }
+}
+
+/// Mirror left to right. Components turn to face the other way, since they have no mirrored
+/// form of their own.
+fn flip(contents: Schematic) -> Schematic {
+    let (min, max) = bounds(&contents);
+    let mirror = |x: i32| min.0 + max.0 - x;
+
+    Schematic {
+        wires: contents
+            .wires
+            .into_iter()
+            .map(|((x, y), cell)| {
+                let cell = WireCell {
+                    east: cell.west,
+                    west: cell.east,
+                    ..cell
+                };
+                ((mirror(x), y), cell)
+            })
+            .collect(),
+        components: contents
+            .components
+            .into_iter()
+            .map(|component| {
+                let (width, _) = component.size();
+                let (x, y) = component.position;
+                PlacedComponent {
+                    position: (mirror(x + width - 1), y),
+                    facing: match component.facing {
+                        Direction::East => Direction::West,
+                        Direction::West => Direction::East,
+                        facing => facing,
+                    },
+                    ..component
+                }
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use backend::schematic::components::ComponentKind;
+    use backend::schematic::nets::extract_nets;
+
+    use super::*;
+
+    /// Cell connecting the directions among "nesw"
+    fn cell(connections: &str) -> WireCell {
+        WireCell {
+            north: connections.contains('n'),
+            east: connections.contains('e'),
+            south: connections.contains('s'),
+            west: connections.contains('w'),
+            junction: false,
+        }
+    }
+
+    /// Two wires crossing at an overpass, one of them turning a corner, with an AND gate
+    /// beside them when `gate` is set. The gate makes the contents wide enough for their
+    /// centre to fall between cells.
+    fn contents(gate: bool) -> Schematic {
+        let wires = [
+            ((0, 1), "e"),
+            ((1, 1), "nesw"),
+            ((2, 1), "w"),
+            ((1, 0), "s"),
+            ((1, 2), "ne"),
+            ((2, 2), "w"),
+        ];
+        Schematic {
+            wires: wires
+                .into_iter()
+                .map(|(pos, connections)| (pos, cell(connections)))
+                .collect(),
+            components: match gate {
+                true => vec![PlacedComponent::new(ComponentKind::AndGate, (3, 0))],
+                false => Vec::new(),
+            },
+        }
+    }
+
+    fn assert_same(a: &Schematic, b: &Schematic) {
+        assert_eq!(a.wires, b.wires);
+        assert_eq!(a.components, b.components);
+    }
+
+    /// Number of cells in each net, sorted
+    fn net_sizes(contents: &Schematic) -> Vec<usize> {
+        let mut sizes: Vec<usize> = extract_nets(&contents.wires)
+            .nets
+            .iter()
+            .map(|net| net.cells.len())
+            .collect();
+        sizes.sort();
+        sizes
+    }
+
+    #[test]
+    fn four_rotations_are_the_identity() {
+        for gate in [false, true] {
+            let turned = (0..4).fold(contents(gate), |contents, _| rotate(contents));
+            assert_same(&turned, &contents(gate));
+        }
+    }
+
+    #[test]
+    fn two_flips_are_the_identity() {
+        for gate in [false, true] {
+            assert_same(&flip(flip(contents(gate))), &contents(gate));
+        }
+    }
+
+    #[test]
+    fn connections_turn_with_their_cells() {
+        let turned = rotate(contents(false));
+        // The wire west of the overpass now comes in from the north, its end pointing south
+        assert_eq!(turned.wires[&(1, 0)], cell("s"));
+        assert_eq!(turned.wires[&(1, 1)], cell("nesw"));
+        // The corner below it turns from north and east to east and south
+        assert_eq!(turned.wires[&(0, 1)], cell("es"));
+        assert_eq!(turned.wires[&(0, 2)], cell("n"));
+        assert_eq!(net_sizes(&turned), net_sizes(&contents(false)));
+        assert_eq!(net_sizes(&flip(contents(false))), vec![3, 4]);
+
+        let gate = &rotate(contents(true)).components[0];
+        assert_eq!(gate.facing, Direction::South);
+    }
+
+    #[test]
+    fn pasted_copies_keep_their_nets() {
+        let mut wires = WireSystem::headless();
+        let mut components = ComponentSystem::new();
+        let original = contents(true);
+        wires.load(&original.wires);
+        components.load(original.components.clone());
+
+        let mut select = SelectTool::new();
+        select.cells = wires.cells().map(|(pos, _)| pos).collect();
+        select.components = components.iter().map(|(id, _)| id).collect();
+        let text = select
+            .copy_text(&wires, &components, &Library::new())
+            .unwrap();
+
+        let mut wires = WireSystem::headless();
+        let mut components = ComponentSystem::new();
+        let mut library = SubcircuitLibrary::new();
+        let edit = select
+            .paste_text(&text, (10, 20), &mut wires, &mut components, &mut library)
+            .unwrap();
+        assert_eq!(edit.wires.len(), original.wires.len());
+        assert_eq!(select.cells.len(), original.wires.len());
+
+        let pasted = Schematic {
+            components: components.components().cloned().collect(),
+            ..wires.schematic()
+        };
+        assert_eq!(net_sizes(&pasted), net_sizes(&original));
+        assert_same(&pasted, &translate(original, (10, 20)));
+    }
+
+    /// Copy `contents` out of a project with `library`, and paste it into one with `target`
+    fn copy_between(contents: Schematic, library: &Library, target: &mut SubcircuitLibrary) {
+        let mut components = ComponentSystem::new();
+        components.load(contents.components);
+        let mut select = SelectTool::new();
+        select.components = components.iter().map(|(id, _)| id).collect();
+        let text = select
+            .copy_text(&WireSystem::headless(), &components, library)
+            .unwrap();
+        let mut components = ComponentSystem::new();
+        select
+            .paste_text(
+                &text,
+                (0, 0),
+                &mut WireSystem::headless(),
+                &mut components,
+                target,
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn copies_bring_their_subcircuits() {
+        let inner = Schematic {
+            components: vec![PlacedComponent::new(ComponentKind::Input, (0, 0))],
+            ..Schematic::default()
+        };
+        let mut library = Library::new();
+        library.insert("inner".to_string(), inner.clone());
+        let outer = Schematic {
+            components: vec![PlacedComponent::subcircuit("inner", &inner, (0, 0))],
+            ..Schematic::default()
+        };
+        library.insert("outer".to_string(), outer.clone());
+        library.insert("unused".to_string(), Schematic::default());
+        let contents = Schematic {
+            components: vec![PlacedComponent::subcircuit("outer", &outer, (0, 0))],
+            ..Schematic::default()
+        };
+
+        let mut target = SubcircuitLibrary::new();
+        copy_between(contents.clone(), &library, &mut target);
+        assert_eq!(target.names(), vec!["inner", "outer"]);
+
+        // Circuits already in the project are kept
+        let mut target = SubcircuitLibrary::new();
+        target.load(Library::from([("inner".to_string(), Schematic::default())]));
+        copy_between(contents, &library, &mut target);
+        assert_eq!(target.names(), vec!["inner", "outer"]);
+        assert!(target.circuits()["inner"].components.is_empty());
+    }
+}
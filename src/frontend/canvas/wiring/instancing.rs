@@ -20,19 +20,22 @@ const GL_VERTEX_ARRAY_BINDING: GLenum = 0x85B5;
 impl InstancedWireRenderer {
     pub fn new(max_instances: usize) -> Self {
         let scale = 8;
-        let width = scale * WireVariant::NUM_VARIANTS as u32;
-        let height = scale;
+        let columns = WireVariant::ATLAS_COLUMNS;
+        let rows = WireVariant::ATLAS_ROWS;
+        let width = scale * columns as u32;
+        let height = scale * rows as u32;
         let target = render_target(width, height);
         target.texture.set_filter(FilterMode::Nearest);
         set_camera(&Camera2D {
-            zoom: vec2(2. / WireVariant::NUM_VARIANTS as f32, 2.),
-            target: vec2(0.5 * WireVariant::NUM_VARIANTS as f32 , 0.5),
+            zoom: vec2(2. / columns as f32, 2. / rows as f32),
+            target: vec2(0.5 * columns as f32, 0.5 * rows as f32),
             render_target: Some(target.clone()),
             ..Default::default()
         });
 
         for i in 0..WireVariant::NUM_VARIANTS {
-            let wire = Wire::new(Vec2::new(i as f32, 0.), WireVariant(i));
+            let cell = Vec2::new((i % columns) as f32, (i / columns) as f32);
+            let wire = Wire::new(cell, WireVariant(i));
             wire.draw();
         }
 
@@ -174,6 +177,13 @@ impl InstancedWireRenderer {
             let loc = glGetUniformLocation(self.shader_id, b"u_texture\0".as_ptr() as *const _);
             glUniform1i(loc, 0);
 
+            let loc = glGetUniformLocation(self.shader_id, c"atlas_size".as_ptr());
+            glUniform2f(
+                loc,
+                WireVariant::ATLAS_COLUMNS as f32,
+                WireVariant::ATLAS_ROWS as f32,
+            );
+
 
             // Draw
             glBindVertexArray(self.vao);
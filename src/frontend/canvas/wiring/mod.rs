@@ -1,3 +1,6 @@
+use backend::schematic::Schematic;
+use backend::schematic::nets::{Channel, Direction, GridPos, NetMap, WireCell, extract_nets};
+use backend::value::Value;
 use egui_macroquad::macroquad::prelude::*;
 use std::collections::HashMap;
 
@@ -5,6 +8,7 @@ use instancing::InstancedWireRenderer;
 use wire::{Wire, WireVariant};
 
 use super::camera::GridCamera;
+use super::history::{Edit, WireChange};
 
 mod instancing;
 mod wire;
@@ -19,7 +23,10 @@ pub struct WireSystem {
     wires: HashMap<(i32, i32), Wire>,
     draw_state: WireDrawState,
     draw_vertical_first: bool,
-    instanced_renderer: InstancedWireRenderer,
+    instanced_renderer: Option<InstancedWireRenderer>, // none without a GL context, in tests
+    nets: NetMap, // rebuilt whenever wires change
+    revision: u64, // bumped on every edit
+    edits: Vec<Edit>, // made by the user since the last `take_edits`
 }
 
 impl WireSystem {
@@ -28,7 +35,10 @@ impl WireSystem {
             wires: HashMap::new(),
             draw_state: WireDrawState::Idle,
             draw_vertical_first: true,
-            instanced_renderer: InstancedWireRenderer::new(1e6 as usize),
+            instanced_renderer: Some(InstancedWireRenderer::new(1e6 as usize)),
+            nets: NetMap::default(),
+            revision: 0,
+            edits: Vec::new(),
         };
 
         for i in 0..16 {
@@ -37,9 +47,200 @@ impl WireSystem {
             let wire = Wire::new(Vec2::new(i as f32, 0 as f32), variant);
             a.wires.insert(grid_key, wire);
         }
+        a.rebuild_nets();
         a
     }
 
+    /// Wire system with no wires that draws nothing, needing no GL context
+    #[cfg(test)]
+    pub(super) fn headless() -> Self {
+        Self {
+            wires: HashMap::new(),
+            draw_state: WireDrawState::Idle,
+            draw_vertical_first: true,
+            instanced_renderer: None,
+            nets: NetMap::default(),
+            revision: 0,
+            edits: Vec::new(),
+        }
+    }
+
+    /// Changes every time the wires are edited
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Connectivity of the drawn wires, with no components
+    pub fn schematic(&self) -> Schematic {
+        Schematic {
+            wires: self
+                .wires
+                .iter()
+                .map(|(pos, wire)| (*pos, wire.variant.to_cell()))
+                .collect(),
+            components: Vec::new(),
+        }
+    }
+
+    /// Replace every wire with the given cells
+    pub fn load(&mut self, cells: &HashMap<GridPos, WireCell>) {
+        self.wires = cells
+            .iter()
+            .map(|(pos, cell)| {
+                let position = Vec2::new(pos.0 as f32, pos.1 as f32);
+                (*pos, Wire::new(position, WireVariant::from_cell(cell)))
+            })
+            .collect();
+        self.draw_state = WireDrawState::Idle;
+        self.rebuild_nets();
+    }
+
+    /// Edits made by user input since the last call, for the undo history
+    pub fn take_edits(&mut self) -> Vec<Edit> {
+        std::mem::take(&mut self.edits)
+    }
+
+    /// Set each changed cell to its `after` state
+    pub fn apply_changes(&mut self, changes: &[WireChange]) {
+        for change in changes {
+            self.set_cell(change.pos, change.after);
+        }
+        self.rebuild_nets();
+    }
+
+    fn set_cell(&mut self, pos: GridPos, cell: Option<WireCell>) {
+        match cell {
+            Some(cell) => {
+                let position = Vec2::new(pos.0 as f32, pos.1 as f32);
+                self.wires
+                    .insert(pos, Wire::new(position, WireVariant::from_cell(&cell)));
+            }
+            None => {
+                self.wires.remove(&pos);
+            }
+        }
+    }
+
+    pub fn cell(&self, pos: GridPos) -> Option<WireCell> {
+        self.wires.get(&pos).map(|wire| wire.variant.to_cell())
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (GridPos, WireCell)> + '_ {
+        self.wires
+            .iter()
+            .map(|(pos, wire)| (*pos, wire.variant.to_cell()))
+    }
+
+    /// Set cells to new states (`None` removes the cell) and return the changes made
+    pub fn set_cells(&mut self, cells: &[(GridPos, Option<WireCell>)]) -> Vec<WireChange> {
+        let changes: Vec<WireChange> = cells
+            .iter()
+            .filter_map(|&(pos, after)| {
+                let before = self.cell(pos);
+                (before != after).then_some(WireChange { pos, before, after })
+            })
+            .collect();
+        if !changes.is_empty() {
+            self.apply_changes(&changes);
+        }
+        changes
+    }
+
+    /// Apply cell changes made by the user as one undoable edit
+    fn edit_cells(&mut self, cells: Vec<(GridPos, WireCell)>) {
+        let cells: Vec<_> = cells.into_iter().map(|(pos, cell)| (pos, Some(cell))).collect();
+        let wires = self.set_cells(&cells);
+        if !wires.is_empty() {
+            self.edits.push(Edit {
+                wires,
+                ..Edit::default()
+            });
+        }
+    }
+
+    /// Every cell of the nets passing through `pos`
+    pub fn net_cells_at(&self, pos: GridPos) -> Vec<(GridPos, Channel)> {
+        self.nets
+            .nets_at(pos)
+            .flat_map(|net| self.nets.nets[net].cells.iter().copied())
+            .collect()
+    }
+
+    /// Remove the given channels of cells and return the changes made
+    ///
+    /// A `Whole` channel removes the cell; the other channels only remove one wire of an
+    /// overpass. Neighbours lose their connection towards anything removed, except overpasses,
+    /// which keep a dangling end so the crossing doesn't turn into a T joining both wires.
+    pub fn erase(&mut self, cells: &[(GridPos, Channel)]) -> Vec<WireChange> {
+        let mut after: HashMap<GridPos, Option<WireCell>> = HashMap::new();
+        let current = |after: &HashMap<GridPos, Option<WireCell>>, pos: GridPos| {
+            match after.get(&pos) {
+                Some(cell) => *cell,
+                None => self.cell(pos),
+            }
+        };
+
+        for &(pos, channel) in cells {
+            let Some(mut cell) = current(&after, pos) else {
+                continue;
+            };
+            let directions = match channel {
+                Channel::Whole => &Direction::ALL[..],
+                Channel::Vertical => &[Direction::North, Direction::South][..],
+                Channel::Horizontal => &[Direction::East, Direction::West][..],
+            };
+            for &direction in directions {
+                if !cell.connects(direction) {
+                    continue;
+                }
+                set_connection(&mut cell, direction, false);
+
+                let neighbour_pos = direction.step(pos);
+                if let Some(mut neighbour) = current(&after, neighbour_pos)
+                    && !neighbour.is_overpass()
+                {
+                    set_connection(&mut neighbour, direction.opposite(), false);
+                    after.insert(neighbour_pos, Some(neighbour));
+                }
+            }
+            cell.junction = false;
+            let cell = if channel == Channel::Whole { None } else { Some(cell) };
+            after.insert(pos, cell);
+        }
+
+        let mut changes: Vec<WireChange> = after
+            .into_iter()
+            .filter_map(|(pos, after)| {
+                let before = self.cell(pos);
+                (before != after).then_some(WireChange { pos, before, after })
+            })
+            .collect();
+        changes.sort_by_key(|change| (change.pos.1, change.pos.0));
+        if !changes.is_empty() {
+            self.apply_changes(&changes);
+        }
+        changes
+    }
+
+    fn rebuild_nets(&mut self) {
+        self.nets = extract_nets(&self.schematic().wires);
+        self.revision += 1;
+    }
+
+    /// Color every wire by the value of its net. `nets` must come from the current wires.
+    pub fn apply_signals<'a>(&mut self, nets: &NetMap, value_of: impl Fn(usize) -> Option<&'a Value>) {
+        for (index, net) in nets.nets.iter().enumerate() {
+            let value = value_of(index);
+            for (pos, channel) in &net.cells {
+                if let Some(wire) = self.wires.get_mut(pos) {
+                    // Overpasses show the horizontal wire's signal in COLOR_B
+                    let primary = *channel != Channel::Horizontal;
+                    wire.variant.set_signal(primary, value);
+                }
+            }
+        }
+    }
+
     pub fn handle_input(&mut self, camera: &GridCamera) {
         // Get mouse position and snap to grid
         let mouse_screen = Vec2::new(mouse_position().0, mouse_position().1);
@@ -63,7 +264,8 @@ impl WireSystem {
         }
     }
 
-    fn draw_wire_path(&mut self, start: Vec2, end: Vec2) {
+    /// Draw an L-shaped wire from `start` to `end` as one edit
+    pub(super) fn draw_wire_path(&mut self, start: Vec2, end: Vec2) {
         if start == end {
             self.place_single_wire(start);
             return;
@@ -146,7 +348,8 @@ impl WireSystem {
             path_positions.push(end_i);
         }
 
-        // Place wires with correct connections
+        // Place wires with correct connections, merged into any wire already there
+        let mut cells = Vec::with_capacity(path_positions.len());
         for (i, &pos) in path_positions.iter().enumerate() {
             let prev_pos = if i > 0 {
                 Some(path_positions[i - 1])
@@ -159,23 +362,19 @@ impl WireSystem {
                 None
             };
 
-            let variant = self.calculate_wire_variant(pos, prev_pos, next_pos);
-            if let Some(existing_wire) = self.wires.get_mut(&pos) {
-                // Merge the connections
-                existing_wire.variant = existing_wire.variant.merge_with(&variant);
-            } else {
-                // Place new wire
-                let wire = Wire::new(Vec2::new(pos.0 as f32, pos.1 as f32), variant);
-                self.wires.insert(pos, wire);
+            let mut variant = self.calculate_wire_variant(pos, prev_pos, next_pos);
+            if let Some(existing_wire) = self.wires.get(&pos) {
+                variant = existing_wire.variant.merge_with(&variant);
             }
+            cells.push((pos, variant.to_cell()));
         }
+        self.edit_cells(cells);
     }
 
     fn place_single_wire(&mut self, position: Vec2) {
         let grid_key = (position.x as i32, position.y as i32);
         let variant = WireVariant::new(false, false, false, false, false); // No connections
-        let wire = Wire::new(position, variant);
-        self.wires.insert(grid_key, wire);
+        self.edit_cells(vec![(grid_key, variant.to_cell())]);
     }
 
     fn calculate_wire_variant(
@@ -238,8 +437,9 @@ impl WireSystem {
                 wire_connections.push((wire.position, wire.variant.0 as f32));
             }
         }
-        self.instanced_renderer
-            .instanced_draw(&wire_connections, camera);
+        if let Some(renderer) = &self.instanced_renderer {
+            renderer.instanced_draw(&wire_connections, camera);
+        }
     }
 
     pub fn draw_preview(&self, camera: &GridCamera) {
@@ -329,3 +529,122 @@ impl WireSystem {
         );
     }
 }
+
+fn set_connection(cell: &mut WireCell, direction: Direction, connected: bool) {
+    match direction {
+        Direction::North => cell.north = connected,
+        Direction::East => cell.east = connected,
+        Direction::South => cell.south = connected,
+        Direction::West => cell.west = connected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cell connecting the directions among "nesw", with a junction for "j"
+    fn cell(connections: &str) -> WireCell {
+        WireCell {
+            north: connections.contains('n'),
+            east: connections.contains('e'),
+            south: connections.contains('s'),
+            west: connections.contains('w'),
+            junction: connections.contains('j'),
+        }
+    }
+
+    fn wires(cells: &[(GridPos, &str)]) -> WireSystem {
+        let mut wires = WireSystem::headless();
+        let cells = cells
+            .iter()
+            .map(|&(pos, connections)| (pos, cell(connections)));
+        wires.load(&cells.collect());
+        wires
+    }
+
+    /// Overpass at (1, 0) with a wire reaching it from every side
+    fn crossing() -> WireSystem {
+        wires(&[
+            ((0, 0), "e"),
+            ((1, 0), "nesw"),
+            ((2, 0), "w"),
+            ((1, -1), "s"),
+            ((1, 1), "n"),
+        ])
+    }
+
+    #[test]
+    fn erasing_clears_neighbours_towards_the_cell() {
+        let mut wires = wires(&[
+            ((0, 0), "nesw"),
+            ((0, -1), "s"),
+            ((1, 0), "we"),
+            ((0, 1), "n"),
+            ((-1, 0), "e"),
+            ((2, 0), "w"),
+        ]);
+        let changes = wires.erase(&[((0, 0), Channel::Whole)]);
+        assert_eq!(changes.len(), 5);
+        assert_eq!(wires.cell((0, 0)), None);
+        for pos in [(0, -1), (0, 1), (-1, 0)] {
+            assert_eq!(wires.cell(pos), Some(cell("")), "{pos:?}");
+        }
+        assert_eq!(wires.cell((1, 0)), Some(cell("e")));
+        assert_eq!(wires.cell((2, 0)), Some(cell("w")));
+    }
+
+    #[test]
+    fn neighbouring_overpasses_keep_a_dangling_end() {
+        let mut wires = crossing();
+        let changes = wires.erase(&[((0, 0), Channel::Whole)]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(wires.cell((1, 0)), Some(cell("nesw")));
+
+        // Still two separate wires crossing
+        assert_eq!(wires.net_cells_at((1, -1)).len(), 3);
+        assert_eq!(wires.net_cells_at((2, 0)).len(), 2);
+    }
+
+    #[test]
+    fn erasing_one_channel_leaves_the_other_wire() {
+        let mut wires = crossing();
+        wires.erase(&[((1, 0), Channel::Vertical)]);
+        assert_eq!(wires.cell((1, 0)), Some(cell("ew")));
+        assert_eq!(wires.cell((1, -1)), Some(cell("")));
+        assert_eq!(wires.cell((1, 1)), Some(cell("")));
+        assert_eq!(wires.cell((0, 0)), Some(cell("e")));
+        assert_eq!(wires.cell((2, 0)), Some(cell("w")));
+
+        let mut wires = crossing();
+        wires.erase(&[((1, 0), Channel::Horizontal)]);
+        assert_eq!(wires.cell((1, 0)), Some(cell("ns")));
+        assert_eq!(wires.cell((0, 0)), Some(cell("")));
+        assert_eq!(wires.cell((2, 0)), Some(cell("")));
+        assert_eq!(wires.cell((1, -1)), Some(cell("s")));
+    }
+
+    #[test]
+    fn whole_nets_erase_through_overpasses() {
+        let mut wires = crossing();
+        let mut net = wires.net_cells_at((0, 0));
+        net.sort_by_key(|(pos, _)| *pos);
+        assert_eq!(
+            net,
+            vec![
+                ((0, 0), Channel::Whole),
+                ((1, 0), Channel::Horizontal),
+                ((2, 0), Channel::Whole),
+            ]
+        );
+        // Both nets pass through the overpass
+        assert_eq!(wires.net_cells_at((1, 0)).len(), 6);
+
+        wires.erase(&net);
+        assert_eq!(wires.cell((0, 0)), None);
+        assert_eq!(wires.cell((2, 0)), None);
+        assert_eq!(wires.cell((1, 0)), Some(cell("ns")));
+        assert_eq!(wires.cell((1, -1)), Some(cell("s")));
+        assert_eq!(wires.cell((1, 1)), Some(cell("n")));
+    }
+}
@@ -4,14 +4,16 @@ attribute vec2 a_offset;
 attribute float a_rotation;
 varying vec2 v_uv;
 uniform mat4 projection;
+uniform vec2 atlas_size; // columns and rows of the variant atlas
 
 void main() {
     vec2 position = a_position + a_offset;
 
-    float x_scale = 1. / pow(2., 10.);
+    float columns = atlas_size.x;
+    float rows = atlas_size.y;
+    float column = mod(a_rotation, columns);
+    float row = floor(a_rotation / columns);
 
     gl_Position = projection * vec4(position, 0., 1.);
-    v_uv = a_position;
-    v_uv.x *= x_scale;
-    v_uv.x += a_rotation * x_scale;
+    v_uv = vec2((a_position.x + column) / columns, (a_position.y + row) / rows);
 }
@@ -1,3 +1,5 @@
+use backend::schematic::nets::WireCell;
+use backend::value::{BitState, Value};
 use egui_macroquad::macroquad::prelude::*;
 
 pub struct WireVariant(pub u16);
@@ -25,7 +27,18 @@ impl WireVariant {
     const COLOR_B_1: u16 = 1 << 8;
     const COLOR_B_2: u16 = 1 << 9;
 
-    pub const NUM_VARIANTS: u16 = 1 << 10;
+    /// Set when the wire carries a multi-bit bus. BUS_A and BUS_B pair with COLOR_A and COLOR_B.
+    const BUS_A: u16 = 1 << 10;
+    const BUS_B: u16 = 1 << 11;
+
+    const SIGNAL_A: u16 = Self::COLOR_A_1 | Self::COLOR_A_2 | Self::BUS_A;
+    const SIGNAL_B: u16 = Self::COLOR_B_1 | Self::COLOR_B_2 | Self::BUS_B;
+
+    pub const NUM_VARIANTS: u16 = 1 << 12;
+
+    /// Variants are laid out in the texture atlas as a grid with this many columns
+    pub const ATLAS_COLUMNS: u16 = 64;
+    pub const ATLAS_ROWS: u16 = Self::NUM_VARIANTS / Self::ATLAS_COLUMNS;
 
     pub fn new(north: bool, east: bool, south: bool, west: bool, junction: bool) -> Self {
         let mut value = 0u16;
@@ -75,11 +88,59 @@ impl WireVariant {
             == (Self::NORTH | Self::EAST | Self::SOUTH | Self::WEST)
             && (self.0 & Self::JUNCTION_BIT == 0)
     }
+    /// Connectivity of this variant, as used by net extraction
+    pub fn to_cell(&self) -> WireCell {
+        WireCell {
+            north: self.has_north(),
+            east: self.has_east(),
+            south: self.has_south(),
+            west: self.has_west(),
+            junction: self.0 & Self::JUNCTION_BIT != 0,
+        }
+    }
+    pub fn from_cell(cell: &WireCell) -> Self {
+        Self::new(cell.north, cell.east, cell.south, cell.west, cell.junction)
+    }
     pub fn vertical_on_top(&self) -> bool {
         self.0 & Self::DEPTH == 0
     }
-    fn get_one_color(color_1: u16, color_2: u16) -> Color {
-        if color_1 == 0 && color_2 == 0 {
+    /// Encode the signal carried by the wire. `primary` selects COLOR_A (the only color of a
+    /// regular wire, or the vertical wire of an overpass) over COLOR_B (the horizontal wire).
+    /// Wires without a value are shown as floating.
+    pub fn set_signal(&mut self, primary: bool, value: Option<&Value>) {
+        let (color_1, color_2, bus) = match value {
+            None => (true, false, false),
+            Some(value) if value.width() == 1 => match value.get_bit_state(0) {
+                BitState::Logic0 => (false, false, false),
+                BitState::Logic1 => (false, true, false),
+                BitState::HighZ => (true, false, false),
+                BitState::Unknown => (true, true, false),
+            },
+            Some(value) if value.has_unknown() => (true, true, true),
+            Some(value) if value.is_high_z() => (true, false, true),
+            Some(_) => (false, false, true),
+        };
+
+        let (mask, bits) = if primary {
+            (Self::SIGNAL_A, [Self::COLOR_A_1, Self::COLOR_A_2, Self::BUS_A])
+        } else {
+            (Self::SIGNAL_B, [Self::COLOR_B_1, Self::COLOR_B_2, Self::BUS_B])
+        };
+        self.0 &= !mask;
+        for (bit, set) in bits.into_iter().zip([color_1, color_2, bus]) {
+            if set {
+                self.0 |= bit;
+            }
+        }
+    }
+    pub fn is_bus(&self) -> (bool, bool) {
+        (self.0 & Self::BUS_A != 0, self.0 & Self::BUS_B != 0)
+    }
+    fn get_one_color(color_1: u16, color_2: u16, bus: u16) -> Color {
+        if bus != 0 && color_1 == 0 {
+            // BUS WITH A DEFINITE VALUE
+            LIGHTGRAY
+        } else if color_1 == 0 && color_2 == 0 {
             // ZERO
             DARKGREEN
         } else if color_1 == 0 && color_2 != 0 {
@@ -97,8 +158,16 @@ impl WireVariant {
     }
     pub fn get_colors(&self) -> (Color, Color) {
         (
-            Self::get_one_color(self.0 & Self::COLOR_A_1, self.0 & Self::COLOR_A_2),
-            Self::get_one_color(self.0 & Self::COLOR_B_1, self.0 & Self::COLOR_B_2),
+            Self::get_one_color(
+                self.0 & Self::COLOR_A_1,
+                self.0 & Self::COLOR_A_2,
+                self.0 & Self::BUS_A,
+            ),
+            Self::get_one_color(
+                self.0 & Self::COLOR_B_1,
+                self.0 & Self::COLOR_B_2,
+                self.0 & Self::BUS_B,
+            ),
         )
     }
 }
@@ -131,13 +200,18 @@ impl Wire {
     }
 
     pub fn draw(&self) {
-        let width: f32 = 0.2;
+        // Buses are drawn thicker than single-bit wires
+        let (bus_a, bus_b) = self.variant.is_bus();
+        let width: f32 = if bus_a { 0.3 } else { 0.2 };
+        let width_b: f32 = if bus_b { 0.3 } else { 0.2 };
         // let width: f32 = 1.0;
         let pos = self.position;
         let scale = 1.0;
 
         let center = (scale - width * scale) / 2.0;
         let size = width * scale;
+        let center_b = (scale - width_b * scale) / 2.0;
+        let size_b = width_b * scale;
         let (color_a, color_b) = self.variant.get_colors();
 
         let is_overpass = self.variant.is_overpass();
@@ -148,10 +222,11 @@ impl Wire {
             // let side = center + size / 2.0;
             if self.variant.vertical_on_top() {
                 draw_rectangle(pos.x + center, pos.y, size, scale, color_a);
-                draw_rectangle(pos.x, pos.y + center, scale / 2. - gap, size, color_b);
-                draw_rectangle(pos.x + scale / 2. + gap, pos.y + center, scale / 2. - gap, size, color_b);
+                draw_rectangle(pos.x, pos.y + center_b, scale / 2. - gap, size_b, color_b);
+                draw_rectangle(pos.x + scale / 2. + gap, pos.y + center_b, scale / 2. - gap, size_b, color_b);
             } else {
-                draw_rectangle(pos.x, pos.y + center, scale, size, color_b);
+                let gap = size_b;
+                draw_rectangle(pos.x, pos.y + center_b, scale, size_b, color_b);
                 draw_rectangle(pos.x + center, pos.y, size, scale / 2. - gap, color_a);
                 draw_rectangle(pos.x + center, pos.y + scale / 2. + gap, size, scale / 2. - gap, color_a);
             }
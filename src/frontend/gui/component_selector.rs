@@ -1,9 +1,9 @@
 use crate::App;
 use crate::gui::component_utils::{
-    CircuitComponentType, DrawInstruction, pos2_with_rect,
+    CircuitComponentType, ComponentGraphics, paint_draw_instructions,
 };
 use egui_macroquad::egui::{Color32, Response, Sense, Stroke, Ui};
-use epaint::{CubicBezierShape, Pos2};
+use epaint::Pos2;
 
 impl App {
     pub fn circuit_component_button(
@@ -20,35 +20,17 @@ impl App {
             let mut inner_rect = inside_margin;
             inner_rect.max.x = inner_rect.min.x + inner_rect.max.y - inner_rect.min.y;
 
-            for instruction in component_type.get_draw_instructions() {
-                match instruction {
-                    DrawInstruction::Line([a, b]) => {
-                        painter.line_segment(
-                            [pos2_with_rect(a, inner_rect), pos2_with_rect(b, inner_rect)],
-                            Stroke::new(2.0, Color32::WHITE),
-                        );
-                    }
-                    DrawInstruction::CubicBezierCurve([a, c1, c2, b]) => {
-                        let shape = CubicBezierShape::from_points_stroke(
-                            [
-                                pos2_with_rect(a, inner_rect),
-                                pos2_with_rect(c1, inner_rect),
-                                pos2_with_rect(c2, inner_rect),
-                                pos2_with_rect(b, inner_rect),
-                            ],
-                            false,
-                            Color32::TRANSPARENT,
-                            Stroke::new(2.0, Color32::WHITE),
-                        );
-                        painter.add(shape);
-                    }
-                }
-            }
+            paint_draw_instructions(
+                &painter,
+                component_type.get_draw_instructions(),
+                inner_rect,
+                Stroke::new(2.0, Color32::WHITE),
+            );
 
             painter.text(
                 Pos2::new(inner_rect.right() + 10.0, rect.center().y),
                 egui::Align2::LEFT_CENTER,
-                component_type.get_label(),
+                component_type.label(),
                 egui::TextStyle::Body.resolve(ui.style()),
                 Color32::WHITE,
             );
@@ -1,8 +1,10 @@
-use epaint::Pos2;
+use egui_macroquad::egui::{Color32, Painter, Stroke};
+use epaint::{CubicBezierShape, Pos2};
 
 pub enum DrawInstruction {
     Line([Pos2; 2]),
     CubicBezierCurve([Pos2; 4]),
+    Circle(Pos2, f32), // centre and radius
 }
 
 pub fn pos2_with_rect(pos: &Pos2, rect: egui::Rect) -> Pos2 {
@@ -12,84 +14,571 @@ pub fn pos2_with_rect(pos: &Pos2, rect: egui::Rect) -> Pos2 {
     }
 }
 
-#[derive(PartialEq, Eq, Copy, Clone)]
-pub enum CircuitComponentType {
-    AndGate,
-    OrGate,
-    NandGate,
-    NorGate,
-    XorGate,
-    XnorGate,
-    NotGate,
+/// Paint a component's geometry scaled to fill `rect`
+pub fn paint_draw_instructions(
+    painter: &Painter,
+    instructions: &[DrawInstruction],
+    rect: egui::Rect,
+    stroke: Stroke,
+) {
+    for instruction in instructions {
+        match instruction {
+            DrawInstruction::Line([a, b]) => {
+                painter.line_segment([pos2_with_rect(a, rect), pos2_with_rect(b, rect)], stroke);
+            }
+            DrawInstruction::CubicBezierCurve([a, c1, c2, b]) => {
+                let shape = CubicBezierShape::from_points_stroke(
+                    [
+                        pos2_with_rect(a, rect),
+                        pos2_with_rect(c1, rect),
+                        pos2_with_rect(c2, rect),
+                        pos2_with_rect(b, rect),
+                    ],
+                    false,
+                    Color32::TRANSPARENT,
+                    stroke,
+                );
+                painter.add(shape);
+            }
+            DrawInstruction::Circle(center, radius) => {
+                painter.circle_stroke(pos2_with_rect(center, rect), radius * rect.width(), stroke);
+            }
+        }
+    }
+}
+
+pub use backend::schematic::components::ComponentKind as CircuitComponentType;
+
+/// Geometry of a component in a unit square, shared by the GUI buttons and the canvas
+pub trait ComponentGraphics {
+    fn get_draw_instructions(&self) -> &'static [DrawInstruction];
+
+    /// Names written inside the body next to the input and output pins, in pin order
+    fn get_pin_labels(&self) -> (&'static [&'static str], &'static [&'static str]);
 }
 
-impl CircuitComponentType {
-    pub fn get_label(&self) -> &'static str {
+impl ComponentGraphics for CircuitComponentType {
+    fn get_draw_instructions(&self) -> &'static [DrawInstruction] {
         match self {
-            Self::AndGate => "AND Gate",
-            Self::OrGate => "OR Gate",
-            Self::NandGate => "NAND Gate",
-            Self::NorGate => "NOR Gate",
-            Self::XorGate => "XOR Gate",
-            Self::XnorGate => "XNOR Gate",
-            Self::NotGate => "NOT Gate",
+            Self::AndGate => &AND_GATE_DRAW_INSTRUCTIONS,
+            Self::OrGate => &OR_GATE_DRAW_INSTRUCTIONS,
+            Self::NandGate => &NAND_GATE_DRAW_INSTRUCTIONS,
+            Self::NorGate => &NOR_GATE_DRAW_INSTRUCTIONS,
+            Self::XorGate => &XOR_GATE_DRAW_INSTRUCTIONS,
+            Self::XnorGate => &XNOR_GATE_DRAW_INSTRUCTIONS,
+            Self::NotGate => &NOT_GATE_DRAW_INSTRUCTIONS,
+            Self::Input => &INPUT_PIN_DRAW_INSTRUCTIONS,
+            Self::Output => &OUTPUT_PIN_DRAW_INSTRUCTIONS,
+            Self::Clock => &CLOCK_DRAW_INSTRUCTIONS,
+            Self::Constant => &CONSTANT_DRAW_INSTRUCTIONS,
+            Self::ControlledBuffer => &CONTROLLED_BUFFER_DRAW_INSTRUCTIONS,
+            Self::ControlledInverter => &CONTROLLED_INVERTER_DRAW_INSTRUCTIONS,
+            Self::TransmissionGate => &TRANSMISSION_GATE_DRAW_INSTRUCTIONS,
+            Self::PullUp => &PULL_UP_DRAW_INSTRUCTIONS,
+            Self::PullDown => &PULL_DOWN_DRAW_INSTRUCTIONS,
+            Self::DFlipFlop | Self::TFlipFlop | Self::Register => &FLIP_FLOP_DRAW_INSTRUCTIONS,
+            Self::JKFlipFlop | Self::SRFlipFlop => &TWO_INPUT_FLIP_FLOP_DRAW_INSTRUCTIONS,
+            Self::Rom | Self::Ram => &MEMORY_ARRAY_DRAW_INSTRUCTIONS,
+            Self::Multiplexer => &MULTIPLEXER_DRAW_INSTRUCTIONS,
+            Self::Demultiplexer | Self::Decoder => &DEMULTIPLEXER_DRAW_INSTRUCTIONS,
+            Self::Adder => &ADDER_DRAW_INSTRUCTIONS,
+            Self::Subtractor => &SUBTRACTOR_DRAW_INSTRUCTIONS,
+            Self::Multiplier => &MULTIPLIER_DRAW_INSTRUCTIONS,
+            Self::Divider => &DIVIDER_DRAW_INSTRUCTIONS,
+            Self::Negator => &NEGATOR_DRAW_INSTRUCTIONS,
+            Self::Comparator => &COMPARATOR_DRAW_INSTRUCTIONS,
+            Self::Shifter => &SHIFTER_DRAW_INSTRUCTIONS,
+            Self::BitCounter => &BIT_COUNTER_DRAW_INSTRUCTIONS,
+            Self::Splitter => &SPLITTER_DRAW_INSTRUCTIONS,
+            Self::Joiner => &JOINER_DRAW_INSTRUCTIONS,
+            Self::Probe => &PROBE_DRAW_INSTRUCTIONS,
+            Self::Tunnel => &TUNNEL_DRAW_INSTRUCTIONS,
+            Self::PriorityEncoder | Self::BitSelector | Self::Subcircuit => {
+                &SUBCIRCUIT_DRAW_INSTRUCTIONS
+            }
         }
     }
 
-    const UNIMPLEMENTED_DRAW_INSTRUCTIONS: [DrawInstruction; 0] = [];
-    const AND_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = {
-        const BOX_WIDTH: f32 = 0.5;
-        const OFFSET_Y: f32 = 0.1;
-        [
-            DrawInstruction::Line([Pos2::new(0.0, OFFSET_Y), Pos2::new(0.0, 1.0 - OFFSET_Y)]),
-            DrawInstruction::Line([Pos2::new(0.0, OFFSET_Y), Pos2::new(0.5, OFFSET_Y)]),
-            DrawInstruction::Line([
-                Pos2::new(0.0, 1.0 - OFFSET_Y),
-                Pos2::new(BOX_WIDTH, 1.0 - OFFSET_Y),
-            ]),
-            DrawInstruction::CubicBezierCurve([
-                Pos2::new(BOX_WIDTH, 1.0 - OFFSET_Y),
-                Pos2::new(1.0, 1.0 - OFFSET_Y),
-                Pos2::new(1.0, OFFSET_Y),
-                Pos2::new(BOX_WIDTH, OFFSET_Y),
-            ]),
-        ]
-    };
-    const OR_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 3] = {
-        const OFFSET_Y: f32 = 0.1;
-        const ARC_START_X: f32 = 0.0;
-        const ARC_END_X: f32 = 1.0;
-        [
-            DrawInstruction::CubicBezierCurve([
-                Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
-                Pos2::new(ARC_START_X + 0.3, 1.0 - OFFSET_Y),
-                Pos2::new(ARC_START_X + 0.3, OFFSET_Y),
-                Pos2::new(ARC_START_X, OFFSET_Y),
-            ]),
-            DrawInstruction::CubicBezierCurve([
-                Pos2::new(ARC_START_X, OFFSET_Y),
-                Pos2::new(0.55, OFFSET_Y),
-                Pos2::new(0.8, OFFSET_Y),
-                Pos2::new(ARC_END_X, 0.5),
-            ]),
-            DrawInstruction::CubicBezierCurve([
-                Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
-                Pos2::new(0.55, 1.0 - OFFSET_Y),
-                Pos2::new(0.8, 1.0 - OFFSET_Y),
-                Pos2::new(ARC_END_X, 0.5),
-            ]),
-        ]
-    };
-
-    pub fn get_draw_instructions(&self) -> &'static [DrawInstruction] {
+    fn get_pin_labels(&self) -> (&'static [&'static str], &'static [&'static str]) {
+        // The clock is marked by the triangle instead of a name
+        const OUTPUTS: &[&str] = &["Q", "Q'"];
         match self {
-            Self::AndGate => &Self::AND_GATE_DRAW_INSTRUCTIONS,
-            Self::OrGate => &Self::OR_GATE_DRAW_INSTRUCTIONS,
-            Self::NandGate => &Self::UNIMPLEMENTED_DRAW_INSTRUCTIONS,
-            Self::NorGate => &Self::UNIMPLEMENTED_DRAW_INSTRUCTIONS,
-            Self::XorGate => &Self::UNIMPLEMENTED_DRAW_INSTRUCTIONS,
-            Self::XnorGate => &Self::UNIMPLEMENTED_DRAW_INSTRUCTIONS,
-            Self::NotGate => &Self::UNIMPLEMENTED_DRAW_INSTRUCTIONS,
+            Self::DFlipFlop => (&["D", "", "en", "R", "S"], OUTPUTS),
+            Self::TFlipFlop => (&["T", "", "en", "R", "S"], OUTPUTS),
+            Self::JKFlipFlop => (&["J", "K", "", "en", "R", "S"], OUTPUTS),
+            Self::SRFlipFlop => (&["S", "R", "", "en", "R", "S"], OUTPUTS),
+            Self::Register => (&["D", "", "en", "R", "S"], &["Q"]),
+            Self::Rom => (&["A"], &["D"]),
+            Self::Ram => (&["A", "D", "we", "clk"], &["D"]),
+            Self::BitSelector => (&["D", "S"], &[]),
+            Self::Adder | Self::Subtractor => (&["", "", "c"], &["", "c"]),
+            Self::Multiplier => (&["", ""], &["", "hi"]),
+            Self::Divider => (&["", ""], &["", "r"]),
+            Self::Comparator => (&["", ""], &["<", "=", ">"]),
+            Self::Shifter => (&["", "d"], &[]),
+            _ => (&[], &[]),
         }
     }
 }
+
+const AND_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = {
+    const BOX_WIDTH: f32 = 0.5;
+    const OFFSET_Y: f32 = 0.1;
+    [
+        DrawInstruction::Line([Pos2::new(0.0, OFFSET_Y), Pos2::new(0.0, 1.0 - OFFSET_Y)]),
+        DrawInstruction::Line([Pos2::new(0.0, OFFSET_Y), Pos2::new(0.5, OFFSET_Y)]),
+        DrawInstruction::Line([
+            Pos2::new(0.0, 1.0 - OFFSET_Y),
+            Pos2::new(BOX_WIDTH, 1.0 - OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(BOX_WIDTH, 1.0 - OFFSET_Y),
+            Pos2::new(1.0, 1.0 - OFFSET_Y),
+            Pos2::new(1.0, OFFSET_Y),
+            Pos2::new(BOX_WIDTH, OFFSET_Y),
+        ]),
+    ]
+};
+const OR_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 3] = {
+    const OFFSET_Y: f32 = 0.1;
+    const ARC_START_X: f32 = 0.0;
+    const ARC_END_X: f32 = 1.0;
+    [
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_START_X + 0.3, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_START_X + 0.3, OFFSET_Y),
+            Pos2::new(ARC_START_X, OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, OFFSET_Y),
+            Pos2::new(0.55, OFFSET_Y),
+            Pos2::new(0.8, OFFSET_Y),
+            Pos2::new(ARC_END_X, 0.5),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
+            Pos2::new(0.55, 1.0 - OFFSET_Y),
+            Pos2::new(0.8, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_END_X, 0.5),
+        ]),
+    ]
+};
+
+// Negated gates are squeezed to leave room for the output bubble
+const BUBBLE_RADIUS: f32 = 0.07;
+
+const NAND_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 5] = {
+    const BOX_WIDTH: f32 = 0.4;
+    const OFFSET_Y: f32 = 0.1;
+    [
+        DrawInstruction::Line([Pos2::new(0.0, OFFSET_Y), Pos2::new(0.0, 1.0 - OFFSET_Y)]),
+        DrawInstruction::Line([Pos2::new(0.0, OFFSET_Y), Pos2::new(BOX_WIDTH, OFFSET_Y)]),
+        DrawInstruction::Line([
+            Pos2::new(0.0, 1.0 - OFFSET_Y),
+            Pos2::new(BOX_WIDTH, 1.0 - OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(BOX_WIDTH, 1.0 - OFFSET_Y),
+            Pos2::new(0.9, 1.0 - OFFSET_Y),
+            Pos2::new(0.9, OFFSET_Y),
+            Pos2::new(BOX_WIDTH, OFFSET_Y),
+        ]),
+        DrawInstruction::Circle(Pos2::new(0.775 + BUBBLE_RADIUS, 0.5), BUBBLE_RADIUS),
+    ]
+};
+
+const NOR_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = {
+    const OFFSET_Y: f32 = 0.1;
+    const ARC_END_X: f32 = 0.86;
+    [
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(0.0, 1.0 - OFFSET_Y),
+            Pos2::new(0.3, 1.0 - OFFSET_Y),
+            Pos2::new(0.3, OFFSET_Y),
+            Pos2::new(0.0, OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(0.0, OFFSET_Y),
+            Pos2::new(0.45, OFFSET_Y),
+            Pos2::new(0.7, OFFSET_Y),
+            Pos2::new(ARC_END_X, 0.5),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(0.0, 1.0 - OFFSET_Y),
+            Pos2::new(0.45, 1.0 - OFFSET_Y),
+            Pos2::new(0.7, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_END_X, 0.5),
+        ]),
+        DrawInstruction::Circle(Pos2::new(ARC_END_X + BUBBLE_RADIUS, 0.5), BUBBLE_RADIUS),
+    ]
+};
+
+// XOR gates are an OR body shifted right, behind an extra input arc
+const XOR_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = {
+    const OFFSET_Y: f32 = 0.1;
+    const ARC_START_X: f32 = 0.12;
+    [
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(0.0, 1.0 - OFFSET_Y),
+            Pos2::new(0.3, 1.0 - OFFSET_Y),
+            Pos2::new(0.3, OFFSET_Y),
+            Pos2::new(0.0, OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_START_X + 0.3, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_START_X + 0.3, OFFSET_Y),
+            Pos2::new(ARC_START_X, OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, OFFSET_Y),
+            Pos2::new(0.6, OFFSET_Y),
+            Pos2::new(0.82, OFFSET_Y),
+            Pos2::new(1.0, 0.5),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
+            Pos2::new(0.6, 1.0 - OFFSET_Y),
+            Pos2::new(0.82, 1.0 - OFFSET_Y),
+            Pos2::new(1.0, 0.5),
+        ]),
+    ]
+};
+
+const XNOR_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 5] = {
+    const OFFSET_Y: f32 = 0.1;
+    const ARC_START_X: f32 = 0.12;
+    const ARC_END_X: f32 = 0.86;
+    [
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(0.0, 1.0 - OFFSET_Y),
+            Pos2::new(0.3, 1.0 - OFFSET_Y),
+            Pos2::new(0.3, OFFSET_Y),
+            Pos2::new(0.0, OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_START_X + 0.3, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_START_X + 0.3, OFFSET_Y),
+            Pos2::new(ARC_START_X, OFFSET_Y),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, OFFSET_Y),
+            Pos2::new(0.5, OFFSET_Y),
+            Pos2::new(0.7, OFFSET_Y),
+            Pos2::new(ARC_END_X, 0.5),
+        ]),
+        DrawInstruction::CubicBezierCurve([
+            Pos2::new(ARC_START_X, 1.0 - OFFSET_Y),
+            Pos2::new(0.5, 1.0 - OFFSET_Y),
+            Pos2::new(0.7, 1.0 - OFFSET_Y),
+            Pos2::new(ARC_END_X, 0.5),
+        ]),
+        DrawInstruction::Circle(Pos2::new(ARC_END_X + BUBBLE_RADIUS, 0.5), BUBBLE_RADIUS),
+    ]
+};
+
+const NOT_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = {
+    const OFFSET_Y: f32 = 0.2;
+    const TIP_X: f32 = 0.86;
+    [
+        DrawInstruction::Line([Pos2::new(0.1, OFFSET_Y), Pos2::new(0.1, 1.0 - OFFSET_Y)]),
+        DrawInstruction::Line([Pos2::new(0.1, OFFSET_Y), Pos2::new(TIP_X, 0.5)]),
+        DrawInstruction::Line([Pos2::new(0.1, 1.0 - OFFSET_Y), Pos2::new(TIP_X, 0.5)]),
+        DrawInstruction::Circle(Pos2::new(TIP_X + BUBBLE_RADIUS, 0.5), BUBBLE_RADIUS),
+    ]
+};
+
+const INPUT_PIN_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = {
+    const MIN: f32 = 0.1;
+    const MAX: f32 = 0.9;
+    [
+        DrawInstruction::Line([Pos2::new(MIN, MIN), Pos2::new(MAX, MIN)]),
+        DrawInstruction::Line([Pos2::new(MAX, MIN), Pos2::new(MAX, MAX)]),
+        DrawInstruction::Line([Pos2::new(MAX, MAX), Pos2::new(MIN, MAX)]),
+        DrawInstruction::Line([Pos2::new(MIN, MAX), Pos2::new(MIN, MIN)]),
+    ]
+};
+
+// Box with bevelled corners, stretching with the number of bits shown
+const OUTPUT_PIN_DRAW_INSTRUCTIONS: [DrawInstruction; 8] = {
+    const MIN: f32 = 0.1;
+    const MAX: f32 = 0.9;
+    const BEVEL: f32 = 0.15;
+    [
+        DrawInstruction::Line([Pos2::new(MIN + BEVEL, MIN), Pos2::new(MAX - BEVEL, MIN)]),
+        DrawInstruction::Line([Pos2::new(MAX - BEVEL, MIN), Pos2::new(MAX, MIN + BEVEL)]),
+        DrawInstruction::Line([Pos2::new(MAX, MIN + BEVEL), Pos2::new(MAX, MAX - BEVEL)]),
+        DrawInstruction::Line([Pos2::new(MAX, MAX - BEVEL), Pos2::new(MAX - BEVEL, MAX)]),
+        DrawInstruction::Line([Pos2::new(MAX - BEVEL, MAX), Pos2::new(MIN + BEVEL, MAX)]),
+        DrawInstruction::Line([Pos2::new(MIN + BEVEL, MAX), Pos2::new(MIN, MAX - BEVEL)]),
+        DrawInstruction::Line([Pos2::new(MIN, MAX - BEVEL), Pos2::new(MIN, MIN + BEVEL)]),
+        DrawInstruction::Line([Pos2::new(MIN, MIN + BEVEL), Pos2::new(MIN + BEVEL, MIN)]),
+    ]
+};
+
+// Box with a doubled back edge; the value is written inside
+const CONSTANT_DRAW_INSTRUCTIONS: [DrawInstruction; 5] = {
+    const MIN: f32 = 0.1;
+    const MAX: f32 = 0.9;
+    [
+        DrawInstruction::Line([Pos2::new(MIN, MIN), Pos2::new(MAX, MIN)]),
+        DrawInstruction::Line([Pos2::new(MAX, MIN), Pos2::new(MAX, MAX)]),
+        DrawInstruction::Line([Pos2::new(MAX, MAX), Pos2::new(MIN, MAX)]),
+        DrawInstruction::Line([Pos2::new(MIN, MAX), Pos2::new(MIN, MIN)]),
+        DrawInstruction::Line([Pos2::new(0.2, MIN), Pos2::new(0.2, MAX)]),
+    ]
+};
+
+const CLOCK_DRAW_INSTRUCTIONS: [DrawInstruction; 9] = {
+    const MIN: f32 = 0.1;
+    const MAX: f32 = 0.9;
+    // One period of a square wave inside the box
+    const LOW: f32 = 0.65;
+    const HIGH: f32 = 0.35;
+    [
+        DrawInstruction::Line([Pos2::new(MIN, MIN), Pos2::new(MAX, MIN)]),
+        DrawInstruction::Line([Pos2::new(MAX, MIN), Pos2::new(MAX, MAX)]),
+        DrawInstruction::Line([Pos2::new(MAX, MAX), Pos2::new(MIN, MAX)]),
+        DrawInstruction::Line([Pos2::new(MIN, MAX), Pos2::new(MIN, MIN)]),
+        DrawInstruction::Line([Pos2::new(0.2, LOW), Pos2::new(0.35, LOW)]),
+        DrawInstruction::Line([Pos2::new(0.35, LOW), Pos2::new(0.35, HIGH)]),
+        DrawInstruction::Line([Pos2::new(0.35, HIGH), Pos2::new(0.65, HIGH)]),
+        DrawInstruction::Line([Pos2::new(0.65, HIGH), Pos2::new(0.65, LOW)]),
+        DrawInstruction::Line([Pos2::new(0.65, LOW), Pos2::new(0.8, LOW)]),
+    ]
+};
+
+// Buffer triangles with the enable coming up from below to the lower edge
+const CONTROLLED_BUFFER_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(0.1, 0.2), Pos2::new(0.1, 0.8)]),
+    DrawInstruction::Line([Pos2::new(0.1, 0.2), Pos2::new(0.86, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.1, 0.8), Pos2::new(0.86, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.5, 1.0), Pos2::new(0.5, 0.64)]),
+];
+
+const CONTROLLED_INVERTER_DRAW_INSTRUCTIONS: [DrawInstruction; 5] = [
+    DrawInstruction::Line([Pos2::new(0.1, 0.2), Pos2::new(0.1, 0.8)]),
+    DrawInstruction::Line([Pos2::new(0.1, 0.2), Pos2::new(0.86, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.1, 0.8), Pos2::new(0.86, 0.5)]),
+    DrawInstruction::Circle(Pos2::new(0.86 + BUBBLE_RADIUS, 0.5), BUBBLE_RADIUS),
+    DrawInstruction::Line([Pos2::new(0.5, 1.0), Pos2::new(0.5, 0.64)]),
+];
+
+// Two opposing triangles, with the gate below and the inverted gate above
+const TRANSMISSION_GATE_DRAW_INSTRUCTIONS: [DrawInstruction; 9] = [
+    DrawInstruction::Line([Pos2::new(0.1, 0.2), Pos2::new(0.1, 0.8)]),
+    DrawInstruction::Line([Pos2::new(0.1, 0.2), Pos2::new(0.9, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.1, 0.8), Pos2::new(0.9, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.9, 0.2), Pos2::new(0.9, 0.8)]),
+    DrawInstruction::Line([Pos2::new(0.9, 0.2), Pos2::new(0.1, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.9, 0.8), Pos2::new(0.1, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.5, 1.0), Pos2::new(0.5, 0.65)]),
+    DrawInstruction::Line([
+        Pos2::new(0.5, 0.0),
+        Pos2::new(0.5, 0.35 - 2.0 * BUBBLE_RADIUS),
+    ]),
+    DrawInstruction::Circle(Pos2::new(0.5, 0.35 - BUBBLE_RADIUS), BUBBLE_RADIUS),
+];
+
+/// Resistor zigzag from a rail or ground symbol on the west to the pin on the east
+const fn pull_resistor(rail: [DrawInstruction; 3]) -> [DrawInstruction; 10] {
+    let [a, b, c] = rail;
+    [
+        a,
+        b,
+        c,
+        DrawInstruction::Line([Pos2::new(0.15, 0.5), Pos2::new(0.3, 0.5)]),
+        DrawInstruction::Line([Pos2::new(0.3, 0.5), Pos2::new(0.375, 0.25)]),
+        DrawInstruction::Line([Pos2::new(0.375, 0.25), Pos2::new(0.475, 0.75)]),
+        DrawInstruction::Line([Pos2::new(0.475, 0.75), Pos2::new(0.575, 0.25)]),
+        DrawInstruction::Line([Pos2::new(0.575, 0.25), Pos2::new(0.675, 0.75)]),
+        DrawInstruction::Line([Pos2::new(0.675, 0.75), Pos2::new(0.75, 0.5)]),
+        DrawInstruction::Line([Pos2::new(0.75, 0.5), Pos2::new(1.0, 0.5)]),
+    ]
+}
+
+const PULL_UP_DRAW_INSTRUCTIONS: [DrawInstruction; 10] = pull_resistor([
+    DrawInstruction::Line([Pos2::new(0.15, 0.1), Pos2::new(0.15, 0.9)]),
+    DrawInstruction::Line([Pos2::new(0.15, 0.1), Pos2::new(0.05, 0.3)]),
+    DrawInstruction::Line([Pos2::new(0.15, 0.1), Pos2::new(0.25, 0.3)]),
+]);
+
+const PULL_DOWN_DRAW_INSTRUCTIONS: [DrawInstruction; 10] = pull_resistor([
+    DrawInstruction::Line([Pos2::new(0.15, 0.1), Pos2::new(0.15, 0.9)]),
+    DrawInstruction::Line([Pos2::new(0.09, 0.25), Pos2::new(0.09, 0.75)]),
+    DrawInstruction::Line([Pos2::new(0.03, 0.4), Pos2::new(0.03, 0.6)]),
+]);
+
+/// Box with the clock triangle on the west edge, centred on the clock pin's row `clock_y`
+const fn clocked_box(clock_y: f32) -> [DrawInstruction; 6] {
+    [
+        DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+        DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+        DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+        DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+        DrawInstruction::Line([Pos2::new(0.0, clock_y - 0.1), Pos2::new(0.2, clock_y)]),
+        DrawInstruction::Line([Pos2::new(0.2, clock_y), Pos2::new(0.0, clock_y + 0.1)]),
+    ]
+}
+
+// Flip-flop bodies are five cells tall, with the clock on the fourth or third row
+const FLIP_FLOP_DRAW_INSTRUCTIONS: [DrawInstruction; 6] = clocked_box(0.7);
+const TWO_INPUT_FLIP_FLOP_DRAW_INSTRUCTIONS: [DrawInstruction; 6] = clocked_box(0.5);
+
+/// Box with a grid of words in the middle
+const MEMORY_ARRAY_DRAW_INSTRUCTIONS: [DrawInstruction; 9] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.3), Pos2::new(0.65, 0.3)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.5), Pos2::new(0.65, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.7), Pos2::new(0.65, 0.7)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.3), Pos2::new(0.35, 0.7)]),
+    DrawInstruction::Line([Pos2::new(0.65, 0.3), Pos2::new(0.65, 0.7)]),
+];
+
+// Plexers narrow towards the side with fewer pins
+const MULTIPLEXER_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.2)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.2), Pos2::new(1.0, 0.8)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.8), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+];
+
+const DEMULTIPLEXER_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.2), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 0.8)]),
+    DrawInstruction::Line([Pos2::new(0.0, 0.8), Pos2::new(0.0, 0.2)]),
+];
+
+// Arithmetic components are boxes marked with their operator
+const ADDER_DRAW_INSTRUCTIONS: [DrawInstruction; 6] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.5), Pos2::new(0.65, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.5, 0.35), Pos2::new(0.5, 0.65)]),
+];
+
+const SUBTRACTOR_DRAW_INSTRUCTIONS: [DrawInstruction; 5] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.5), Pos2::new(0.65, 0.5)]),
+];
+
+const MULTIPLIER_DRAW_INSTRUCTIONS: [DrawInstruction; 6] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.38, 0.38), Pos2::new(0.62, 0.62)]),
+    DrawInstruction::Line([Pos2::new(0.62, 0.38), Pos2::new(0.38, 0.62)]),
+];
+
+const DIVIDER_DRAW_INSTRUCTIONS: [DrawInstruction; 7] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.35, 0.5), Pos2::new(0.65, 0.5)]),
+    DrawInstruction::Circle(Pos2::new(0.5, 0.37), 0.03),
+    DrawInstruction::Circle(Pos2::new(0.5, 0.63), 0.03),
+];
+
+// A minus sign before an operand circle
+const NEGATOR_DRAW_INSTRUCTIONS: [DrawInstruction; 6] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.25, 0.5), Pos2::new(0.45, 0.5)]),
+    DrawInstruction::Circle(Pos2::new(0.63, 0.5), 0.1),
+];
+
+const COMPARATOR_DRAW_INSTRUCTIONS: [DrawInstruction; 6] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.3, 0.45), Pos2::new(0.55, 0.45)]),
+    DrawInstruction::Line([Pos2::new(0.3, 0.55), Pos2::new(0.55, 0.55)]),
+];
+
+// Two chevrons pointing the way bits move
+const SHIFTER_DRAW_INSTRUCTIONS: [DrawInstruction; 8] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.45, 0.38), Pos2::new(0.33, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.33, 0.5), Pos2::new(0.45, 0.62)]),
+    DrawInstruction::Line([Pos2::new(0.62, 0.38), Pos2::new(0.5, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.5, 0.5), Pos2::new(0.62, 0.62)]),
+];
+
+const BIT_COUNTER_DRAW_INSTRUCTIONS: [DrawInstruction; 8] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.44, 0.35), Pos2::new(0.4, 0.65)]),
+    DrawInstruction::Line([Pos2::new(0.6, 0.35), Pos2::new(0.56, 0.65)]),
+    DrawInstruction::Line([Pos2::new(0.33, 0.44), Pos2::new(0.67, 0.44)]),
+    DrawInstruction::Line([Pos2::new(0.33, 0.56), Pos2::new(0.67, 0.56)]),
+];
+
+// The bus fans out to a spine along the side the fan pins meet
+const SPLITTER_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.5), Pos2::new(0.6, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.6, 0.5), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.6, 0.5), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+];
+
+const JOINER_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(1.0, 0.5), Pos2::new(0.4, 0.5)]),
+    DrawInstruction::Line([Pos2::new(0.4, 0.5), Pos2::new(0.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(0.4, 0.5), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(0.0, 1.0)]),
+];
+
+// Rounded ends around the value, with the pin on the west side
+const PROBE_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(0.15, 0.1), Pos2::new(0.85, 0.1)]),
+    DrawInstruction::CubicBezierCurve([
+        Pos2::new(0.85, 0.1),
+        Pos2::new(1.0, 0.1),
+        Pos2::new(1.0, 0.9),
+        Pos2::new(0.85, 0.9),
+    ]),
+    DrawInstruction::Line([Pos2::new(0.85, 0.9), Pos2::new(0.15, 0.9)]),
+    DrawInstruction::CubicBezierCurve([
+        Pos2::new(0.15, 0.9),
+        Pos2::new(0.0, 0.9),
+        Pos2::new(0.0, 0.1),
+        Pos2::new(0.15, 0.1),
+    ]),
+];
+
+// Tag pointing at the pin on the west side, with the name written inside
+const TUNNEL_DRAW_INSTRUCTIONS: [DrawInstruction; 5] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.5), Pos2::new(0.2, 0.1)]),
+    DrawInstruction::Line([Pos2::new(0.2, 0.1), Pos2::new(1.0, 0.1)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.1), Pos2::new(1.0, 0.9)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.9), Pos2::new(0.2, 0.9)]),
+    DrawInstruction::Line([Pos2::new(0.2, 0.9), Pos2::new(0.0, 0.5)]),
+];
+
+const SUBCIRCUIT_DRAW_INSTRUCTIONS: [DrawInstruction; 4] = [
+    DrawInstruction::Line([Pos2::new(0.0, 0.0), Pos2::new(1.0, 0.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 0.0), Pos2::new(1.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(1.0, 1.0), Pos2::new(0.0, 1.0)]),
+    DrawInstruction::Line([Pos2::new(0.0, 1.0), Pos2::new(0.0, 0.0)]),
+];
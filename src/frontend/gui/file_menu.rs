@@ -0,0 +1,141 @@
+use std::path::PathBuf;
+
+use crate::App;
+use crate::project::MAX_RECENT_FILES;
+use egui_macroquad::egui::{Align2, Context, Key, TextEdit, Ui, Window};
+
+/// File operation requested from the menu, carried out by the main loop
+pub enum FileRequest {
+    Save(PathBuf),
+    Open(PathBuf),
+    /// Load a memory image into the inspected ROM or RAM
+    LoadImage(PathBuf),
+}
+
+/// What the path entered in the prompt window will be used for
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PathPrompt {
+    Open,
+    SaveAs,
+    LoadImage,
+}
+
+impl App {
+    pub fn take_file_request(&mut self) -> Option<FileRequest> {
+        self.file_request.take()
+    }
+
+    pub fn recent_files(&self) -> &[PathBuf] {
+        &self.recent_files
+    }
+
+    pub fn set_recent_files(&mut self, files: Vec<PathBuf>) {
+        self.recent_files = files;
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Record that `path` was just saved or opened, making it the current document
+    pub fn file_succeeded(&mut self, path: PathBuf) {
+        self.recent_files.retain(|recent| *recent != path);
+        self.recent_files.insert(0, path.clone());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+        self.status = Some(format!("{}", path.display()));
+        self.document_path = Some(path);
+    }
+
+    /// Show a message in the status bar
+    pub fn set_status(&mut self, message: impl Into<String>) {
+        self.status = Some(message.into());
+    }
+
+    pub(super) fn open_prompt(&mut self, prompt: PathPrompt) {
+        let text = match prompt {
+            PathPrompt::LoadImage => String::new(),
+            _ => self
+                .document_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        };
+        self.path_prompt = Some((prompt, text));
+    }
+
+    pub(super) fn file_menu(&mut self, ui: &mut Ui) {
+        if ui.button("Open…").clicked() {
+            self.open_prompt(PathPrompt::Open);
+            ui.close_menu();
+        }
+        ui.menu_button("Open Recent", |ui| {
+            if self.recent_files.is_empty() {
+                ui.label("No recent files");
+            }
+            for path in &self.recent_files {
+                if ui.button(path.display().to_string()).clicked() {
+                    self.file_request = Some(FileRequest::Open(path.clone()));
+                    ui.close_menu();
+                }
+            }
+        });
+        ui.separator();
+        if ui.button("Save").clicked() {
+            match &self.document_path {
+                Some(path) => self.file_request = Some(FileRequest::Save(path.clone())),
+                None => self.open_prompt(PathPrompt::SaveAs),
+            }
+            ui.close_menu();
+        }
+        if ui.button("Save As…").clicked() {
+            self.open_prompt(PathPrompt::SaveAs);
+            ui.close_menu();
+        }
+    }
+
+    /// Window asking for the path to open or save to
+    pub(super) fn path_prompt_window(&mut self, ctx: &Context) {
+        let Some((prompt, mut text)) = self.path_prompt.take() else {
+            return;
+        };
+        let title = match prompt {
+            PathPrompt::Open => "Open",
+            PathPrompt::SaveAs => "Save As",
+            PathPrompt::LoadImage => "Load Image",
+        };
+        let hint = match prompt {
+            PathPrompt::LoadImage => "image.hex",
+            _ => "circuit.json",
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        Window::new(title)
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut text)
+                        .hint_text(hint)
+                        .desired_width(300.0),
+                );
+                response.request_focus();
+                let entered = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                ui.horizontal(|ui| {
+                    confirmed = ui.button(title).clicked() || entered;
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+
+        if confirmed && !text.trim().is_empty() {
+            let path = PathBuf::from(text.trim());
+            self.file_request = Some(match prompt {
+                PathPrompt::Open => FileRequest::Open(path),
+                PathPrompt::SaveAs => FileRequest::Save(path),
+                PathPrompt::LoadImage => FileRequest::LoadImage(path),
+            });
+        } else if open && !cancelled {
+            self.path_prompt = Some((prompt, text));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::ops::Range;
+
+use backend::component::io::Radix;
+use backend::schematic::components::{ComponentKind, PlacedComponent};
+use backend::value::Value;
+use egui_macroquad::egui::{Button, Grid, Key, Label, RichText, Sense, TextEdit, Ui};
+
+use crate::App;
+
+/// Words on each row of the hex editor
+const ROW_WORDS: u64 = 8;
+/// Words on each page of the hex editor
+const PAGE_WORDS: u64 = ROW_WORDS * 16;
+
+impl App {
+    /// Addresses on the hex editor's current page, empty unless a ROM or RAM is inspected
+    pub fn memory_page_addresses(&self) -> Range<u64> {
+        match self
+            .inspected_component
+            .as_ref()
+            .and_then(PlacedComponent::memory)
+        {
+            Some(memory) => {
+                let capacity = memory.capacity() as u64;
+                let start = (self.memory_page * PAGE_WORDS).min(capacity);
+                start..(start + PAGE_WORDS).min(capacity)
+            }
+            _ => 0..0,
+        }
+    }
+
+    /// Current values of the words at `memory_page_addresses`
+    pub fn set_memory_words(&mut self, words: Vec<Value>) {
+        self.memory_words = words;
+    }
+
+    /// Word the user typed into the hex editor for a RAM, as an address and its new value
+    pub fn take_memory_write(&mut self) -> Option<(u64, Value)> {
+        self.memory_write.take()
+    }
+
+    /// Page through the words of a ROM or RAM and edit them. ROM edits change the image
+    /// stored with the component in `edited`; RAM edits change the running memory.
+    pub(super) fn memory_editor(&mut self, ui: &mut Ui, edited: &mut PlacedComponent) {
+        let Some(memory) = edited.memory() else {
+            return;
+        };
+        let capacity = memory.capacity() as u64;
+        let pages = capacity.div_ceil(PAGE_WORDS);
+        self.memory_page = self.memory_page.min(pages - 1);
+        let addresses = self.memory_page_addresses();
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.memory_page > 0, Button::new("⏴"))
+                .clicked()
+            {
+                self.memory_page -= 1;
+            }
+            ui.monospace(format!("{:04X}–{:04X}", addresses.start, addresses.end - 1));
+            if ui
+                .add_enabled(self.memory_page + 1 < pages, Button::new("⏵"))
+                .clicked()
+            {
+                self.memory_page += 1;
+            }
+        });
+
+        let digits = (edited.bit_width as usize).div_ceil(4);
+        let mut committed = None;
+        Grid::new("memory_words")
+            .spacing([6.0, 2.0])
+            .show(ui, |ui| {
+                let rows = addresses.clone().step_by(ROW_WORDS as usize);
+                for row in rows {
+                    ui.monospace(RichText::new(format!("{:04X}", row)).weak());
+                    for address in row..(row + ROW_WORDS).min(addresses.end) {
+                        let index = (address - addresses.start) as usize;
+                        let Some(word) = self.memory_words.get(index) else {
+                            continue;
+                        };
+                        match &mut self.word_edit {
+                            Some((editing, text)) if *editing == address => {
+                                let response = ui.add(
+                                    TextEdit::singleline(text)
+                                        .char_limit(digits)
+                                        .desired_width(digits as f32 * 8.0)
+                                        .font(egui::TextStyle::Monospace),
+                                );
+                                response.request_focus();
+                                if response.lost_focus() {
+                                    if !ui.input(|i| i.key_pressed(Key::Escape)) {
+                                        committed = Some((address, text.clone()));
+                                    }
+                                    self.word_edit = None;
+                                }
+                            }
+                            _ => {
+                                let label =
+                                    Label::new(RichText::new(Radix::Hex.format(word)).monospace())
+                                        .sense(Sense::click());
+                                if ui.add(label).on_hover_text("Click to edit").clicked() {
+                                    self.word_edit = Some((address, Radix::Hex.format(word)));
+                                }
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+
+        let Some((address, text)) = committed else {
+            return;
+        };
+        let Ok(word) = u64::from_str_radix(text.trim(), 16) else {
+            return;
+        };
+        let word = word & (u64::MAX >> (64 - edited.bit_width as u32));
+        if edited.kind == ComponentKind::Rom
+            && let Some(memory) = edited.memory_mut()
+        {
+            let contents = &mut memory.contents;
+            let index = address as usize;
+            if contents.len() <= index {
+                contents.resize(index + 1, 0);
+            }
+            contents[index] = word;
+            while contents.last() == Some(&0) {
+                contents.pop();
+            }
+        } else {
+            self.memory_write = Some((address, Value::from_u64(word, edited.bit_width)));
+        }
+    }
+}
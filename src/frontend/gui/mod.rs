@@ -1,17 +1,64 @@
+use std::path::PathBuf;
+
+use backend::schematic::components::PlacedComponent;
+use backend::value::Value;
 use component_utils::CircuitComponentType;
 use egui_macroquad::egui;
 use egui_macroquad::macroquad::prelude::*;
+use file_menu::{FileRequest, PathPrompt};
+use subcircuits::SubcircuitRequest;
 
 mod component_selector;
-mod component_utils;
+pub mod component_utils;
+pub mod file_menu;
+mod memory_editor;
+mod properties;
+pub mod subcircuits;
+mod time_control;
 mod toolbar;
 
+/// What clicking on the canvas does when no component is selected for placement
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Wire,
+    Select,
+    Erase,
+    /// Toggles the bits of input pins while the circuit runs
+    Poke,
+}
+
 pub struct App {
     expanded: bool,
     selected_component: Option<CircuitComponentType>,
+    tool: Tool,
     hotbar_selections: [Option<CircuitComponentType>; Self::NUM_HOTBAR_BUTTONS],
     hovered_hotbar_button: Option<usize>,
     dragged_component: Option<CircuitComponentType>,
+    pointer_over_ui: bool,
+    keyboard_captured: bool,
+    document_path: Option<PathBuf>,
+    recent_files: Vec<PathBuf>,
+    path_prompt: Option<(PathPrompt, String)>,
+    file_request: Option<FileRequest>,
+    status: Option<String>,
+    subcircuit_names: Vec<String>,
+    selected_subcircuit: Option<String>,
+    subcircuit_prompt: Option<String>,
+    subcircuit_request: Option<SubcircuitRequest>,
+    instance_path: Vec<String>,
+    inspected_component: Option<PlacedComponent>,
+    label_text: String, // label being typed in the properties panel
+    component_edit: Option<(PlacedComponent, PlacedComponent)>,
+    tunnel_rename: Option<(String, String)>, // old and new name of every tunnel to rename
+    running: bool,
+    unlimited_rate: bool,
+    ticks_per_second: f64,
+    step_requested: bool,
+    tick_count: u64,
+    memory_page: u64,
+    memory_words: Vec<Value>,         // words on the hex editor's page
+    word_edit: Option<(u64, String)>, // address and text of the word being typed
+    memory_write: Option<(u64, Value)>,
 }
 
 impl App {
@@ -22,9 +69,35 @@ impl App {
         Self {
             expanded: true,
             selected_component: None,
+            tool: Tool::Wire,
             hotbar_selections: [None; Self::NUM_HOTBAR_BUTTONS],
             hovered_hotbar_button: None,
             dragged_component: None,
+            pointer_over_ui: false,
+            keyboard_captured: false,
+            document_path: None,
+            recent_files: Vec::new(),
+            path_prompt: None,
+            file_request: None,
+            status: None,
+            subcircuit_names: Vec::new(),
+            selected_subcircuit: None,
+            subcircuit_prompt: None,
+            subcircuit_request: None,
+            instance_path: Vec::new(),
+            inspected_component: None,
+            label_text: String::new(),
+            component_edit: None,
+            tunnel_rename: None,
+            running: false,
+            unlimited_rate: false,
+            ticks_per_second: 10.0,
+            step_requested: false,
+            tick_count: 0,
+            memory_page: 0,
+            memory_words: Vec::new(),
+            word_edit: None,
+            memory_write: None,
         }
     }
 
@@ -34,6 +107,38 @@ impl App {
 
     pub fn set_selected_component(&mut self, component: Option<CircuitComponentType>) {
         self.selected_component = component;
+        self.selected_subcircuit = None;
+    }
+
+    pub fn get_tool(&self) -> Tool {
+        self.tool
+    }
+
+    /// Switch tools, putting away any component selected for placement
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+        self.set_selected_component(None);
+    }
+
+    pub fn get_hotbar_selections(&self) -> &[Option<CircuitComponentType>] {
+        &self.hotbar_selections
+    }
+
+    /// Fill the hotbar from the start of `selections`, clearing any remaining slots
+    pub fn set_hotbar_selections(&mut self, selections: &[Option<CircuitComponentType>]) {
+        for (i, slot) in self.hotbar_selections.iter_mut().enumerate() {
+            *slot = selections.get(i).copied().flatten();
+        }
+    }
+
+    /// True if the mouse was over a panel last frame, so the canvas should ignore it
+    pub fn is_pointer_over_ui(&self) -> bool {
+        self.pointer_over_ui
+    }
+
+    /// True if a text field had focus last frame, so canvas shortcuts should be ignored
+    pub fn is_keyboard_captured(&self) -> bool {
+        self.keyboard_captured
     }
 
     pub fn update(&mut self, ctx: &egui::Context) {
@@ -43,11 +148,8 @@ impl App {
 
         TopBottomPanel::top("menu").show(ctx, |ui| {
             menu::bar(ui, |ui| {
-                ui.menu_button("File", |ui| {
-                    if ui.button("Open").clicked() {
-                        // …
-                    }
-                });
+                ui.menu_button("File", |ui| self.file_menu(ui));
+                ui.menu_button("Circuit", |ui| self.subcircuit_menu(ui));
             });
         });
 
@@ -62,6 +164,12 @@ impl App {
             ui.horizontal(|ui| {
                 ui.label(format!("Build: {}", build));
                 ui.label(format!("Version: {}", version));
+                ui.separator();
+                ui.label(format!("Tick: {}", self.tick_count));
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.label(status);
+                }
             });
         });
 
@@ -72,16 +180,7 @@ impl App {
             .show_animated(ctx, self.expanded, |ui| {
                 ui.label("Components");
                 CollapsingHeader::new("Gates").show(ui, |ui| {
-                    let gates = [
-                        CircuitComponentType::AndGate,
-                        CircuitComponentType::OrGate,
-                        CircuitComponentType::NandGate,
-                        CircuitComponentType::NorGate,
-                        CircuitComponentType::XorGate,
-                        CircuitComponentType::XnorGate,
-                        CircuitComponentType::NotGate,
-                    ];
-                    for gate in gates {
+                    for gate in CircuitComponentType::GATES {
                         self.circuit_component_button(
                             ui,
                             egui::Vec2::new(ui.available_size().x, 60.0),
@@ -89,6 +188,70 @@ impl App {
                         );
                     }
                 });
+                CollapsingHeader::new("Pins").show(ui, |ui| {
+                    for pin in CircuitComponentType::PINS {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            pin,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Sources").show(ui, |ui| {
+                    for source in CircuitComponentType::SOURCES {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            source,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Bus").show(ui, |ui| {
+                    for driver in CircuitComponentType::BUS {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            driver,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Wiring").show(ui, |ui| {
+                    for wiring in CircuitComponentType::WIRING {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            wiring,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Memory").show(ui, |ui| {
+                    for memory in CircuitComponentType::MEMORY {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            memory,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Plexers").show(ui, |ui| {
+                    for plexer in CircuitComponentType::PLEXERS {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            plexer,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Arithmetic").show(ui, |ui| {
+                    for arithmetic in CircuitComponentType::ARITHMETIC {
+                        self.circuit_component_button(
+                            ui,
+                            egui::Vec2::new(ui.available_size().x, 60.0),
+                            arithmetic,
+                        );
+                    }
+                });
+                CollapsingHeader::new("Subcircuits").show(ui, |ui| self.subcircuit_section(ui));
             });
 
         SidePanel::left("toggle_button_panel")
@@ -116,11 +279,12 @@ impl App {
                 );
             });
 
-        SidePanel::right("Right").show(ctx, |ui| {
-            ui.label("test1");
-        });
+        SidePanel::right("Right").show(ctx, |ui| self.properties_panel(ui));
 
         self.render_toolbar(ctx);
+        self.path_prompt_window(ctx);
+        self.subcircuit_prompt_window(ctx);
+        self.instance_bar(ctx);
 
         // Ensure that each component can only be in one hotbar slot at a time
         if let Some(hovered_index) = self.hovered_hotbar_button
@@ -134,5 +298,8 @@ impl App {
             }
             self.hotbar_selections[hovered_index] = self.dragged_component;
         }
+
+        self.pointer_over_ui = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+        self.keyboard_captured = ctx.wants_keyboard_input();
     }
 }
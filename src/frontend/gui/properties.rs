@@ -0,0 +1,246 @@
+use backend::component::arithmetic::ShiftKind;
+use backend::component::gates::Gate;
+use backend::component::io::Radix;
+use backend::component::memory::MAX_ADDRESS_WIDTH;
+use backend::component::plexers::MAX_SELECT_WIDTH;
+use backend::node::Edge;
+use backend::schematic::components::{ComponentKind, ComponentSettings, PlacedComponent};
+use egui_macroquad::egui::{Button, ComboBox, DragValue, Grid, RichText, TextEdit, Ui};
+
+use crate::App;
+use crate::gui::file_menu::PathPrompt;
+
+impl App {
+    /// Component shown in the properties panel, normally the one selected on the canvas
+    pub fn set_inspected_component(&mut self, component: Option<PlacedComponent>) {
+        if component != self.inspected_component {
+            self.label_text = component
+                .as_ref()
+                .map(|component| component.label.clone())
+                .unwrap_or_default();
+            self.inspected_component = component;
+        }
+    }
+
+    /// Name of the tunnels to rename, and the name to give them, once the user asks to rename
+    /// them all
+    pub fn take_tunnel_rename(&mut self) -> Option<(String, String)> {
+        self.tunnel_rename.take()
+    }
+
+    /// The inspected component before and after the changes made in the properties panel,
+    /// once the user commits them
+    pub fn take_component_edit(&mut self) -> Option<(PlacedComponent, PlacedComponent)> {
+        self.component_edit.take()
+    }
+
+    pub(super) fn properties_panel(&mut self, ui: &mut Ui) {
+        ui.heading("Properties");
+        let Some(component) = self.inspected_component.clone() else {
+            ui.label("Select a single component to edit it");
+            return;
+        };
+        let mut edited = component.clone();
+        ui.label(component.kind.label());
+        if let Some(name) = &component.subcircuit {
+            ui.label(format!("Instance of '{name}'"));
+        }
+        ui.separator();
+
+        ui.label("Label");
+        let response =
+            ui.add(TextEdit::singleline(&mut self.label_text).desired_width(f32::INFINITY));
+        // Commit the label once editing finishes (Enter or clicking away), so typing is a
+        // single undo step
+        if response.lost_focus() {
+            edited.label = self.label_text.trim().to_string();
+        }
+        if component.kind == ComponentKind::Tunnel {
+            let name = self.label_text.trim();
+            let rename = ui
+                .add_enabled(
+                    !component.label.is_empty() && name != component.label,
+                    Button::new("Rename All"),
+                )
+                .on_hover_text("Give the new name to every tunnel with this one's name");
+            if rename.clicked() {
+                // Renaming covers this tunnel too, so it is not edited on its own
+                self.tunnel_rename = Some((component.label.clone(), name.to_string()));
+                return;
+            }
+        }
+
+        // Subcircuit pins take their widths from the definition; flip-flops, clocks, decoders
+        // and priority encoders are single bits
+        let fixed_width = matches!(
+            component.kind,
+            ComponentKind::Subcircuit
+                | ComponentKind::Clock
+                | ComponentKind::Decoder
+                | ComponentKind::PriorityEncoder
+        ) || component.kind.flip_flop_kind().is_some();
+        if !fixed_width {
+            ui.horizontal(|ui| {
+                ui.label("Bit width");
+                ui.add(DragValue::new(&mut edited.bit_width).range(1..=Gate::MAX_WIDTH));
+            });
+        }
+        if component.kind.gate_kind().is_some() && component.kind != ComponentKind::NotGate {
+            ui.horizontal(|ui| {
+                ui.label("Inputs");
+                ui.add(
+                    DragValue::new(&mut edited.input_count)
+                        .range(Gate::MIN_INPUTS..=Gate::MAX_INPUTS),
+                );
+            });
+        }
+        let bit_width = edited.bit_width;
+        match &mut edited.settings {
+            ComponentSettings::ThreeState(three_state) => {
+                ui.checkbox(three_state, "Three-state")
+                    .on_hover_text("Clicking a 1 bit lets it float before it goes back to 0");
+            }
+            ComponentSettings::Value(value) => {
+                // Bits past the width are dropped, so the value keeps up with width changes
+                let max = u64::MAX >> (64 - bit_width.min(64));
+                *value = (*value).min(max);
+                ui.horizontal(|ui| {
+                    ui.label("Value");
+                    ui.add(
+                        DragValue::new(value)
+                            .range(0..=max)
+                            .hexadecimal(1, false, true)
+                            .prefix("0x"),
+                    );
+                });
+            }
+            ComponentSettings::Radix(selected) => {
+                ui.horizontal(|ui| {
+                    ui.label("Radix");
+                    ComboBox::from_id_salt("probe_radix")
+                        .selected_text(selected.label())
+                        .show_ui(ui, |ui| {
+                            for radix in Radix::ALL {
+                                ui.selectable_value(selected, radix, radix.label());
+                            }
+                        });
+                });
+            }
+            ComponentSettings::Signed(signed) => {
+                ui.checkbox(signed, "Signed");
+            }
+            ComponentSettings::Shift(shift) => {
+                ui.horizontal(|ui| {
+                    ui.label("Shift");
+                    ComboBox::from_id_salt("shift_kind")
+                        .selected_text(shift.label())
+                        .show_ui(ui, |ui| {
+                            for kind in ShiftKind::ALL {
+                                ui.selectable_value(shift, kind, kind.label());
+                            }
+                        });
+                });
+            }
+            ComponentSettings::Trigger(trigger) => {
+                ui.horizontal(|ui| {
+                    ui.label("Trigger");
+                    ui.selectable_value(trigger, Edge::Rising, "Rising edge");
+                    ui.selectable_value(trigger, Edge::Falling, "Falling edge");
+                });
+            }
+            ComponentSettings::Timing(timing) => {
+                ui.horizontal(|ui| {
+                    ui.label("High ticks");
+                    ui.add(DragValue::new(&mut timing.high_ticks).range(1..=10_000));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Low ticks");
+                    ui.add(DragValue::new(&mut timing.low_ticks).range(1..=10_000));
+                });
+            }
+            _ => {}
+        }
+        if component.kind.is_plexer() {
+            let mut select_width = edited.select_width();
+            ui.horizontal(|ui| {
+                ui.label("Select bits");
+                ui.add(DragValue::new(&mut select_width).range(1..=MAX_SELECT_WIDTH));
+            });
+            edited.set_select_width(select_width);
+        }
+        if component.kind.is_splitter() {
+            let mut fans = edited.fan_count();
+            ui.horizontal(|ui| {
+                ui.label("Fans");
+                ui.add(DragValue::new(&mut fans).range(1..=edited.bit_width));
+            });
+            if fans != edited.fan_count() {
+                edited.set_fan_count(fans);
+            }
+            ui.separator();
+            ui.label("Bit mapping");
+            bit_map_editor(ui, &mut edited);
+        }
+
+        if let Some(memory) = edited.memory_mut() {
+            ui.horizontal(|ui| {
+                ui.label("Address bits");
+                ui.add(DragValue::new(&mut memory.address_width).range(1..=MAX_ADDRESS_WIDTH));
+            });
+            let capacity = memory.capacity();
+            memory.contents.truncate(capacity);
+            if component.kind == ComponentKind::Ram {
+                ui.checkbox(&mut memory.shared_bus, "Shared data bus");
+                ui.checkbox(&mut memory.clocked, "Write on clock edge");
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .button("Load Image…")
+                    .on_hover_text("Logisim v2.0 raw or Intel HEX file")
+                    .clicked()
+                {
+                    self.open_prompt(PathPrompt::LoadImage);
+                }
+                if !memory.contents.is_empty() && ui.button("Clear Image").clicked() {
+                    memory.contents.clear();
+                }
+            });
+            if component.kind == ComponentKind::Ram {
+                edited.input_count = if memory.clocked { 4 } else { 3 };
+            }
+            ui.separator();
+            ui.label("Contents");
+            self.memory_editor(ui, &mut edited);
+        }
+
+        if edited != component {
+            self.component_edit = Some((component, edited));
+        }
+    }
+}
+
+/// Grid with a row per bus bit of a splitter or joiner and a column per fan, for picking the
+/// fan each bit goes to
+fn bit_map_editor(ui: &mut Ui, edited: &mut PlacedComponent) {
+    let mut fan_map = edited.fan_map();
+    Grid::new("bit_map").spacing([4.0, 2.0]).show(ui, |ui| {
+        ui.label(RichText::new("Bit").weak());
+        for fan in 0..edited.fan_count() {
+            ui.label(RichText::new(fan.to_string()).weak());
+        }
+        ui.label(RichText::new("None").weak());
+        ui.end_row();
+
+        for (bit, fan_of_bit) in fan_map.iter_mut().enumerate() {
+            ui.monospace(bit.to_string());
+            for fan in 0..edited.fan_count() {
+                ui.radio_value(fan_of_bit, Some(fan), "");
+            }
+            ui.radio_value(fan_of_bit, None, "");
+            ui.end_row();
+        }
+    });
+    if fan_map != edited.fan_map() {
+        edited.settings = ComponentSettings::BitFans(fan_map);
+    }
+}
@@ -0,0 +1,174 @@
+use backend::schematic::Library;
+use backend::schematic::components::PlacedComponent;
+
+use crate::App;
+use crate::gui::component_utils::{
+    CircuitComponentType, ComponentGraphics, paint_draw_instructions,
+};
+use egui_macroquad::egui::{Align2, Color32, Context, Key, Sense, Stroke, TextEdit, Ui, Window};
+use epaint::Pos2;
+
+/// Subcircuit operation requested from the GUI, carried out by the main loop
+pub enum SubcircuitRequest {
+    /// Save the main circuit into the library under this name
+    Save(String),
+    /// Stop viewing the innermost subcircuit instance
+    Leave,
+}
+
+impl App {
+    pub fn take_subcircuit_request(&mut self) -> Option<SubcircuitRequest> {
+        self.subcircuit_request.take()
+    }
+
+    /// Names listed in the Subcircuits section, to be set whenever the library changes
+    pub fn set_subcircuit_names(&mut self, names: Vec<String>) {
+        if let Some(selected) = &self.selected_subcircuit
+            && !names.contains(selected)
+        {
+            self.set_selected_component(None);
+        }
+        self.subcircuit_names = names;
+    }
+
+    /// Component to place on a canvas click, if one is selected: a fresh instance of the
+    /// selected kind, or of the selected library circuit
+    pub fn placement_template(&self, library: &Library) -> Option<PlacedComponent> {
+        let kind = self.selected_component?;
+        if kind != CircuitComponentType::Subcircuit {
+            return Some(PlacedComponent::new(kind, (0, 0)));
+        }
+        let name = self.selected_subcircuit.as_ref()?;
+        Some(PlacedComponent::subcircuit(
+            name,
+            library.get(name)?,
+            (0, 0),
+        ))
+    }
+
+    /// Names of the subcircuit instances being viewed, outermost first. Empty when editing
+    /// the main circuit.
+    pub fn set_instance_path(&mut self, path: Vec<String>) {
+        self.instance_path = path;
+    }
+
+    pub(super) fn subcircuit_menu(&mut self, ui: &mut Ui) {
+        let viewing = !self.instance_path.is_empty();
+        if ui
+            .add_enabled(!viewing, egui::Button::new("Save as Subcircuit…"))
+            .clicked()
+        {
+            self.subcircuit_prompt = Some(String::new());
+            ui.close_menu();
+        }
+        if ui
+            .add_enabled(viewing, egui::Button::new("Leave Instance"))
+            .clicked()
+        {
+            self.subcircuit_request = Some(SubcircuitRequest::Leave);
+            ui.close_menu();
+        }
+    }
+
+    /// Buttons placing an instance of each library circuit
+    pub(super) fn subcircuit_section(&mut self, ui: &mut Ui) {
+        if self.subcircuit_names.is_empty() {
+            ui.label("Use Circuit > Save as Subcircuit to add one");
+        }
+        for name in self.subcircuit_names.clone() {
+            let size = egui::Vec2::new(ui.available_size().x, 60.0);
+            let (rect, response) = ui.allocate_exact_size(size, Sense::click());
+            if !ui.is_rect_visible(rect) {
+                continue;
+            }
+            let painter = ui.painter_at(rect);
+            let mut inner_rect = rect.shrink(10.0);
+            inner_rect.max.x = inner_rect.min.x + inner_rect.height();
+            paint_draw_instructions(
+                &painter,
+                CircuitComponentType::Subcircuit.get_draw_instructions(),
+                inner_rect,
+                Stroke::new(2.0, Color32::WHITE),
+            );
+            painter.text(
+                Pos2::new(inner_rect.right() + 10.0, rect.center().y),
+                Align2::LEFT_CENTER,
+                &name,
+                egui::TextStyle::Body.resolve(ui.style()),
+                Color32::WHITE,
+            );
+
+            if response.clicked() {
+                self.set_selected_component(Some(CircuitComponentType::Subcircuit));
+                self.selected_subcircuit = Some(name.clone());
+            }
+            if self.selected_subcircuit.as_ref() == Some(&name) {
+                painter.rect_filled(rect, 4.0, Color32::from_white_alpha(20));
+            } else if response.hovered() {
+                painter.rect_filled(rect, 4.0, Color32::from_white_alpha(5));
+            }
+        }
+    }
+
+    /// Bar naming the instance being viewed, with a way back out
+    pub(super) fn instance_bar(&mut self, ctx: &Context) {
+        if self.instance_path.is_empty() {
+            return;
+        }
+        Window::new("Viewing instance")
+            .collapsible(false)
+            .resizable(false)
+            .title_bar(false)
+            .anchor(Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("⏴ Back").on_hover_text("Escape").clicked() {
+                        self.subcircuit_request = Some(SubcircuitRequest::Leave);
+                    }
+                    ui.label(format!("main › {}", self.instance_path.join(" › ")));
+                });
+            });
+    }
+
+    /// Window asking for the name to save the main circuit under
+    pub(super) fn subcircuit_prompt_window(&mut self, ctx: &Context) {
+        let Some(mut text) = self.subcircuit_prompt.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut confirmed = false;
+        let mut cancelled = false;
+        Window::new("Save as Subcircuit")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    TextEdit::singleline(&mut text)
+                        .hint_text("Name")
+                        .desired_width(300.0),
+                );
+                response.request_focus();
+                let entered = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if self
+                    .subcircuit_names
+                    .iter()
+                    .any(|name| *name == text.trim())
+                {
+                    ui.label("Replaces the existing subcircuit of this name");
+                }
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("Save").clicked() || entered;
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+
+        if confirmed && !text.trim().is_empty() {
+            self.subcircuit_request = Some(SubcircuitRequest::Save(text.trim().to_string()));
+        } else if open && !cancelled {
+            self.subcircuit_prompt = Some(text);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use egui_macroquad::egui::{Button, DragValue, Ui};
+
+use crate::App;
+use crate::simulation::TickRate;
+
+impl App {
+    /// True while simulation time should advance on its own
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    pub fn tick_rate(&self) -> TickRate {
+        if self.unlimited_rate {
+            TickRate::Unlimited
+        } else {
+            TickRate::PerSecond(self.ticks_per_second)
+        }
+    }
+
+    /// True once after the user asks for a single tick
+    pub fn take_step_request(&mut self) -> bool {
+        std::mem::take(&mut self.step_requested)
+    }
+
+    /// Tick count shown in the status bar
+    pub fn set_tick_count(&mut self, ticks: u64) {
+        self.tick_count = ticks;
+    }
+
+    pub(super) fn time_controls(&mut self, ui: &mut Ui) {
+        let (label, hint) = if self.running {
+            ("⏸ Pause", "Stop advancing simulation time")
+        } else {
+            ("▶ Run", "Advance simulation time at the chosen rate")
+        };
+        if ui.button(label).on_hover_text(hint).clicked() {
+            self.running = !self.running;
+        }
+        if ui
+            .add_enabled(!self.running, Button::new("Step"))
+            .on_hover_text("Advance by a single tick")
+            .clicked()
+        {
+            self.step_requested = true;
+        }
+        ui.add_enabled(
+            !self.unlimited_rate,
+            DragValue::new(&mut self.ticks_per_second)
+                .range(0.1..=10_000.0)
+                .speed(1.0)
+                .suffix(" ticks/s"),
+        );
+        ui.toggle_value(&mut self.unlimited_rate, "Max")
+            .on_hover_text("Tick as fast as possible, independent of the frame rate");
+    }
+}
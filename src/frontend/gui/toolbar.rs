@@ -1,29 +1,42 @@
 use crate::App;
-use crate::gui::component_utils::{DrawInstruction, pos2_with_rect};
+use crate::gui::Tool;
+use crate::gui::component_utils::{ComponentGraphics, paint_draw_instructions};
 use egui_macroquad::egui::{
     Color32, Key, Response, Sense, Stroke, StrokeKind, TopBottomPanel, Ui, Vec2, menu,
 };
-use epaint::CubicBezierShape;
 
 impl App {
     fn handle_hotbar_keys(&mut self, ctx: &egui::Context) {
         ctx.input(|i| {
             if i.key_down(Key::Num1) {
-                self.selected_component = self.hotbar_selections[0];
+                self.set_selected_component(self.hotbar_selections[0]);
             }
             if i.key_down(Key::Num2) {
-                self.selected_component = self.hotbar_selections[1];
+                self.set_selected_component(self.hotbar_selections[1]);
             }
             if i.key_down(Key::Num3) {
-                self.selected_component = self.hotbar_selections[2];
+                self.set_selected_component(self.hotbar_selections[2]);
             }
             if i.key_down(Key::Num4) {
-                self.selected_component = self.hotbar_selections[3];
+                self.set_selected_component(self.hotbar_selections[3]);
             }
             if i.key_down(Key::Num5) {
-                self.selected_component = self.hotbar_selections[4];
+                self.set_selected_component(self.hotbar_selections[4]);
             }
         });
+        if !ctx.wants_keyboard_input() && !ctx.input(|i| i.modifiers.command) {
+            for (key, tool) in [
+                (Key::E, Tool::Erase),
+                (Key::Q, Tool::Select),
+                (Key::P, Tool::Poke),
+            ] {
+                if ctx.input(|i| i.key_pressed(key)) {
+                    // Pressing a tool's key again goes back to drawing wires
+                    let tool = if self.get_tool() == tool { Tool::Wire } else { tool };
+                    self.set_tool(tool);
+                }
+            }
+        }
     }
 
     fn hotbar_button(&mut self, size: egui::Vec2, ui: &mut Ui, index: usize) -> Response {
@@ -37,30 +50,12 @@ impl App {
             let button_contents = self.hotbar_selections[index];
 
             if let Some(selected_component) = button_contents {
-                for instruction in selected_component.get_draw_instructions() {
-                    match instruction {
-                        DrawInstruction::Line([a, b]) => {
-                            painter.line_segment(
-                                [pos2_with_rect(a, inner_rect), pos2_with_rect(b, inner_rect)],
-                                Stroke::new(2.0, Color32::WHITE),
-                            );
-                        }
-                        DrawInstruction::CubicBezierCurve([a, c1, c2, b]) => {
-                            let shape = CubicBezierShape::from_points_stroke(
-                                [
-                                    pos2_with_rect(a, inner_rect),
-                                    pos2_with_rect(c1, inner_rect),
-                                    pos2_with_rect(c2, inner_rect),
-                                    pos2_with_rect(b, inner_rect),
-                                ],
-                                false,
-                                Color32::TRANSPARENT,
-                                Stroke::new(2.0, Color32::WHITE),
-                            );
-                            painter.add(shape);
-                        }
-                    }
-                }
+                paint_draw_instructions(
+                    &painter,
+                    selected_component.get_draw_instructions(),
+                    inner_rect,
+                    Stroke::new(2.0, Color32::WHITE),
+                );
             }
 
             if response.clicked() {
@@ -105,6 +100,21 @@ impl App {
                 for i in 0..Self::NUM_HOTBAR_BUTTONS {
                     self.hotbar_button(Vec2::splat(40.0), ui, i);
                 }
+                ui.separator();
+                let tools = [
+                    (Tool::Wire, "Wire", "Click to start, shift-click to draw"),
+                    (Tool::Select, "Select", "Q: drag to box-select, shift-click to add"),
+                    (Tool::Erase, "Erase", "E: erase wires and components, shift-click a net"),
+                    (Tool::Poke, "Poke", "P: click the bits of input pins to toggle them"),
+                ];
+                for (tool, label, hint) in tools {
+                    let active = self.get_tool() == tool && self.selected_component.is_none();
+                    if ui.selectable_label(active, label).on_hover_text(hint).clicked() {
+                        self.set_tool(tool);
+                    }
+                }
+                ui.separator();
+                self.time_controls(ui);
             });
         });
         self.handle_hotbar_keys(ctx);
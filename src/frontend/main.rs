@@ -1,15 +1,32 @@
+use backend::schematic::Schematic;
+use backend::schematic::components::ComponentSettings;
 use egui_macroquad::macroquad::prelude::*;
 
 use crate::canvas::camera::GridCamera;
+use crate::canvas::components::ComponentSystem;
+use crate::canvas::eraser::EraseTool;
+use crate::canvas::history::{Edit, History};
+use crate::canvas::instance::InstanceView;
+use crate::canvas::selection::SelectTool;
 use crate::canvas::grid::GridDrawer;
 use crate::canvas::wiring::WireSystem;
-use crate::gui::App;
+use crate::gui::{App, Tool};
+use crate::gui::file_menu::FileRequest;
+use crate::gui::subcircuits::SubcircuitRequest;
+use crate::simulation::LiveSimulation;
+use crate::subcircuits::SubcircuitLibrary;
 
 mod profiler;
 mod canvas;
 mod gui;
+mod project;
+mod simulation;
+mod subcircuits;
 mod util;
 
+/// Longest gap between the clicks of a double click, in seconds
+const DOUBLE_CLICK_SECONDS: f64 = 0.35;
+
 #[macroquad::main("circuitsim")]
 async fn main() {
     let args: Vec<String> = std::env::args().collect();
@@ -26,7 +43,17 @@ async fn main() {
     let mut camera = GridCamera::new();
     let gd = GridDrawer::new(crate::canvas::grid::GridDrawOptions::Instanced, vec4(0.3, 0.3, 0.3, 0.3));
     let mut gui = App::new();
+    gui.set_recent_files(project::load_recent_files());
     let mut ws = WireSystem::new();
+    let mut cs = ComponentSystem::new();
+    let mut history = History::new();
+    let mut eraser = EraseTool::new();
+    let mut selector = SelectTool::new();
+    let mut live = LiveSimulation::new();
+    let mut library = SubcircuitLibrary::new();
+    // Subcircuit instances drilled into, outermost first. The canvas shows the last one.
+    let mut views: Vec<InstanceView> = Vec::new();
+    let mut last_click: Option<(f64, (i32, i32))> = None;
 
     request_new_screen_size(1280.0, 720.0);
     next_frame().await;
@@ -35,11 +62,246 @@ async fn main() {
         profile_scope!("frame");
         let dt = get_frame_time();
 
-        {
+        // Component shown in the properties panel
+        let inspected = {
             profile_scope!("logic");
             camera.handle_input(dt);
             camera.update(dt);
-            ws.handle_input(&camera);
+
+            let mouse = camera.screen_to_world(Vec2::from(mouse_position()));
+            let hovered = (mouse.x.floor() as i32, mouse.y.floor() as i32);
+            let mut double_click = false;
+            if is_mouse_button_pressed(MouseButton::Left) && !gui.is_pointer_over_ui() {
+                let now = get_time();
+                double_click = matches!(last_click, Some((time, cell))
+                    if now - time < DOUBLE_CLICK_SECONDS && cell == hovered);
+                last_click = (!double_click).then_some((now, hovered));
+            }
+
+            // Double-clicking a subcircuit instance shows its insides
+            let template = gui.placement_template(library.circuits());
+            let selecting = template.is_none() && gui.get_tool() == Tool::Select;
+            if double_click && (!views.is_empty() || selecting) {
+                let (path, components) = match views.last() {
+                    Some(view) => (view.path(), &view.components),
+                    None => (&[][..], &cs),
+                };
+                let view = components.index_at(hovered).and_then(|index| {
+                    InstanceView::enter(path, components, index, library.circuits(), &mut camera)
+                });
+                if let Some(view) = view {
+                    views.push(view);
+                    selector.clear();
+                }
+            }
+
+            match (template.as_ref(), gui.get_tool()) {
+                (Some(_), _) if is_key_pressed(KeyCode::Escape) => {
+                    gui.set_selected_component(None)
+                }
+                // Instances are read-only; Escape goes back out
+                _ if !views.is_empty() => {
+                    if is_key_pressed(KeyCode::Escape)
+                        && let Some(view) = views.pop()
+                    {
+                        view.leave(&mut camera);
+                    }
+                }
+                (None, Tool::Erase | Tool::Poke) if is_key_pressed(KeyCode::Escape) => {
+                    gui.set_tool(Tool::Wire)
+                }
+                _ if gui.is_pointer_over_ui() => {}
+                (Some(selected), _) => cs.handle_input(&camera, selected),
+                (None, Tool::Select) => {
+                    let keyboard = !gui.is_keyboard_captured();
+                    let revision = library.revision();
+                    if let Some(edit) =
+                        selector.handle_input(&camera, &mut ws, &mut cs, &mut library, keyboard)
+                    {
+                        history.push(edit);
+                    }
+                    // Pastes bring along the subcircuits they use
+                    if library.revision() != revision {
+                        gui.set_subcircuit_names(library.names());
+                    }
+                }
+                (None, Tool::Erase) => {
+                    if let Some(edit) = eraser.handle_input(&camera, &mut ws, &mut cs) {
+                        history.push(edit);
+                    }
+                }
+                (None, Tool::Wire) => ws.handle_input(&camera),
+                // Clicking a bit of an input pin toggles it in the running simulation
+                (None, Tool::Poke) => {
+                    if is_mouse_button_pressed(MouseButton::Left)
+                        && let Some(index) = cs.index_at(hovered)
+                        && let Some(component) = cs.components().nth(index)
+                        && let ComponentSettings::ThreeState(three_state) = component.settings
+                        && let Some(bit) = component.bit_at(hovered)
+                    {
+                        live.toggle_input(index, bit, three_state);
+                    }
+                }
+            }
+
+            for edit in ws.take_edits().into_iter().chain(cs.take_edits()) {
+                history.push(edit);
+            }
+            let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+            let shift = is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift);
+            if ctrl && !gui.is_keyboard_captured() {
+                if is_key_pressed(KeyCode::Z) && !shift {
+                    history.undo(&mut ws, &mut cs);
+                } else if is_key_pressed(KeyCode::Z) || is_key_pressed(KeyCode::Y) {
+                    history.redo(&mut ws, &mut cs);
+                }
+            }
+
+            // The properties panel edits the component selected on the main canvas
+            let inspected = selector.single_component().filter(|_| views.is_empty());
+            // Edits are dropped if the selection changed while they were being made
+            if let Some((before, after)) = gui.take_component_edit()
+                && let Some(id) = inspected
+                && cs.get(id) == Some(&before)
+                && let Some(change) = cs.set(id, Some(after))
+            {
+                history.push(Edit {
+                    components: vec![change],
+                    ..Edit::default()
+                });
+            }
+            if let Some((from, to)) = gui.take_tunnel_rename() {
+                let changes = cs.rename_tunnels(&from, &to);
+                if !changes.is_empty() {
+                    history.push(Edit {
+                        components: changes,
+                        ..Edit::default()
+                    });
+                }
+            }
+            let inspected = inspected.filter(|_| gui.get_tool() == Tool::Select);
+            gui.set_inspected_component(inspected.and_then(|id| cs.get(id).cloned()));
+            inspected
+        };
+
+        match gui.take_subcircuit_request() {
+            Some(SubcircuitRequest::Save(name)) => {
+                let schematic = Schematic {
+                    components: cs.components().cloned().collect(),
+                    ..ws.schematic()
+                };
+                library.save(&name, schematic);
+                history.push(Edit {
+                    components: cs.match_library(library.circuits()),
+                    ..Edit::default()
+                });
+                gui.set_subcircuit_names(library.names());
+                gui.set_status(format!("Saved subcircuit '{}'", name));
+            }
+            Some(SubcircuitRequest::Leave) => {
+                if let Some(view) = views.pop() {
+                    view.leave(&mut camera);
+                }
+            }
+            None => {}
+        }
+
+        let document = match gui.take_file_request() {
+            Some(FileRequest::Save(path)) => {
+                let result = project::save(&path, &ws, &cs, &library, &camera, &gui);
+                Some((path, result))
+            }
+            Some(FileRequest::Open(path)) => {
+                let result =
+                    project::open(&path, &mut ws, &mut cs, &mut library, &mut camera, &mut gui);
+                if result.is_ok() {
+                    history.clear();
+                    selector.clear();
+                    views.clear();
+                    // Nothing carries over from the last project's simulation
+                    live = LiveSimulation::new();
+                }
+                Some((path, result))
+            }
+            Some(FileRequest::LoadImage(path)) => {
+                if let Some(id) = inspected
+                    && let Some(memory) = cs.get(id)
+                {
+                    match project::load_memory_image(&path, memory) {
+                        Ok(loaded) => {
+                            if let Some(change) = cs.set(id, Some(loaded)) {
+                                history.push(Edit {
+                                    components: vec![change],
+                                    ..Edit::default()
+                                });
+                            }
+                            gui.set_status(format!("Loaded {}", path.display()));
+                        }
+                        Err(err) => gui.set_status(format!("{}: {}", path.display(), err)),
+                    }
+                }
+                None
+            }
+            None => None,
+        };
+        if let Some((path, result)) = document {
+            match result {
+                Ok(()) => {
+                    gui.file_succeeded(path);
+                    project::save_recent_files(gui.recent_files());
+                }
+                Err(err) => gui.set_status(format!("{}: {}", path.display(), err)),
+            }
+        }
+
+        {
+            profile_scope!("simulation");
+            // Words typed into the hex editor for the inspected RAM
+            let memory = inspected.and_then(|id| cs.index_of(id));
+            if let Some((address, value)) = gui.take_memory_write()
+                && let Some(index) = memory
+            {
+                live.write_memory(index, address, value);
+            }
+            if let Err(err) = live.update(&ws, &cs, &library) {
+                gui.set_status(format!("Circuit error: {}", err));
+            } else if let Err(err) = live.settle() {
+                gui.set_status(format!("Circuit does not settle: {}", err));
+            }
+            let ticked = if gui.take_step_request() {
+                live.step()
+            } else if gui.is_running() {
+                live.run(dt, gui.tick_rate()).map(|_| ())
+            } else {
+                Ok(())
+            };
+            if let Err(err) = ticked {
+                gui.set_running(false);
+                gui.set_status(format!("Simulation paused: {}", err));
+            }
+            gui.set_tick_count(live.tick_count());
+            let words = memory.map(|index| live.memory_words(index, gui.memory_page_addresses()));
+            gui.set_memory_words(words.unwrap_or_default());
+            ws.apply_signals(live.wire_nets(), |net| live.wire_net_value(net));
+            // Leave any instance that no longer exists after an edit
+            while let Some(view) = views.last_mut() {
+                match live.instance(view.path()) {
+                    Some(instance) => {
+                        view.apply_signals(instance, |net| live.net_value(net));
+                        break;
+                    }
+                    None => {
+                        if let Some(view) = views.pop() {
+                            view.leave(&mut camera);
+                        }
+                    }
+                }
+            }
+            gui.set_instance_path(views.iter().map(|view| view.name().to_string()).collect());
+        }
+
+        {
+            profile_scope!("gui");
 
             egui_macroquad::ui(|ctx| {
                 gui.update(ctx);
@@ -58,11 +320,38 @@ async fn main() {
             clear_background(Color::new(0.1, 0.1, 0.1, 1.0));
             set_camera(&camera);
             gd.draw_grid(&camera);
-            ws.draw_preview(&camera);
-            ws.draw_wires(&camera);
+            let shown = match views.last() {
+                Some(view) => {
+                    view.wires.draw_wires(&camera);
+                    view.components.draw_components(&camera);
+                    &view.components
+                }
+                None => {
+                    ws.draw_preview(&camera);
+                    ws.draw_wires(&camera);
+                    cs.draw_components(&camera);
+                    let template = gui.placement_template(library.circuits());
+                    match (template, gui.get_tool()) {
+                        (None, Tool::Select) => selector.draw(&camera, &cs),
+                        _ if gui.is_pointer_over_ui() => {}
+                        (Some(selected), _) => cs.draw_preview(&camera, &selected),
+                        (None, Tool::Erase) => eraser.draw_preview(&camera, &ws),
+                        (None, Tool::Wire | Tool::Poke) => {}
+                    }
+                    &cs
+                }
+            };
+
+            if !gui.is_pointer_over_ui() {
+                shown.draw_tunnel_highlights(&camera);
+            }
 
             gl_use_default_material();
             set_default_camera();
+            let path = views.last().map_or(&[][..], |view| view.path());
+            shown.draw_labels(&camera, library.circuits(), |index, component| {
+                live.component_value(path, index, component)
+            });
             egui_macroquad::draw();
         }
 
@@ -0,0 +1,106 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use backend::component::memory::parse_image;
+use backend::schematic::Schematic;
+use backend::schematic::components::PlacedComponent;
+use backend::schematic::file::{EditorState, FileError, ProjectFile};
+use egui_macroquad::macroquad::prelude::*;
+
+use crate::canvas::camera::GridCamera;
+use crate::canvas::components::ComponentSystem;
+use crate::canvas::wiring::WireSystem;
+use crate::gui::App;
+use crate::subcircuits::SubcircuitLibrary;
+
+/// Number of entries kept in File > Open Recent
+pub const MAX_RECENT_FILES: usize = 8;
+
+/// Write the current drawing, its subcircuits and the editor state to `path`
+pub fn save(
+    path: &Path,
+    wires: &WireSystem,
+    components: &ComponentSystem,
+    library: &SubcircuitLibrary,
+    camera: &GridCamera,
+    gui: &App,
+) -> Result<(), FileError> {
+    let schematic = Schematic {
+        components: components.components().cloned().collect(),
+        ..wires.schematic()
+    };
+    let position = camera.get_position();
+    let editor = EditorState {
+        camera: [position.x, position.y],
+        zoom: camera.get_zoom(),
+        hotbar: gui.get_hotbar_selections().to_vec(),
+    };
+    ProjectFile::new(&schematic, library.circuits(), editor).save(path)
+}
+
+/// Replace the current drawing, subcircuits and editor state with the contents of `path`
+pub fn open(
+    path: &Path,
+    wires: &mut WireSystem,
+    components: &mut ComponentSystem,
+    library: &mut SubcircuitLibrary,
+    camera: &mut GridCamera,
+    gui: &mut App,
+) -> Result<(), FileError> {
+    let file = ProjectFile::load(path)?;
+    let schematic = file.schematic();
+    wires.load(&schematic.wires);
+    components.load(schematic.components);
+    library.load(file.library());
+    gui.set_subcircuit_names(library.names());
+    camera.set_view(
+        vec2(file.editor.camera[0], file.editor.camera[1]),
+        file.editor.zoom,
+    );
+    gui.set_hotbar_selections(&file.editor.hotbar);
+    Ok(())
+}
+
+/// File the recent files list is kept in, under the user's config directory
+fn recent_files_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config.join("circuitsim").join("recent_files.json"))
+}
+
+/// Recently used project files, most recent first. Missing or unreadable lists are empty.
+pub fn load_recent_files() -> Vec<PathBuf> {
+    recent_files_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// Persist the recent files list. Failures are ignored, since the list is only a convenience.
+pub fn save_recent_files(files: &[PathBuf]) {
+    let Some(path) = recent_files_path() else {
+        return;
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(text) = serde_json::to_string_pretty(files) {
+        let _ = fs::write(path, text);
+    }
+}
+
+/// `component`, a ROM or RAM, starting with the contents of the memory image at `path`
+pub fn load_memory_image(
+    path: &Path,
+    component: &PlacedComponent,
+) -> Result<PlacedComponent, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    let mut loaded = component.clone();
+    if let Some(memory) = loaded.memory_mut() {
+        memory.contents = parse_image(&text, component.bit_width, memory.capacity())?;
+    }
+    Ok(loaded)
+}
@@ -0,0 +1,484 @@
+use std::ops::Range;
+
+use backend::circuit::{Circuit, NetId};
+use backend::component::io::{VALUE_KEY, toggle_bit};
+use backend::component::memory::{read_word, write_word};
+use backend::node::{NodeId, NodeLogic, NodeState};
+use backend::schematic::components::PlacedComponent;
+use backend::schematic::nets::NetMap;
+use backend::schematic::{CompileError, CompiledInstance, Library, Schematic};
+use backend::simulation::{SimulationError, Simulator};
+use backend::value::Value;
+use egui_macroquad::macroquad::time::get_time;
+
+use crate::canvas::components::ComponentSystem;
+use crate::canvas::wiring::WireSystem;
+use crate::subcircuits::SubcircuitLibrary;
+
+/// Longest a frame spends ticking, in seconds, so fast tick rates cannot stall drawing
+const TICK_BUDGET_SECONDS: f64 = 1.0 / 120.0;
+
+/// How fast simulation time advances while running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TickRate {
+    PerSecond(f64),
+    /// As many ticks as fit in each frame's time budget, whatever the frame rate
+    Unlimited,
+}
+
+/// Backend simulation of the circuit on the canvas, rebuilt whenever the drawing changes
+///
+/// A rebuild keeps the state of every component still at the same index with the same pins:
+/// values poked into input pins, stored bits, clock phases and words written to RAMs. The tick
+/// count carries on too.
+pub struct LiveSimulation {
+    simulator: Simulator,
+    root: CompiledInstance,
+    revision: Option<(u64, u64, u64)>, // wire, component and library revisions last built from
+    owed_ticks: f64,                   // fraction of a tick carried over between frames
+    earlier_ticks: u64,                // ticks run by simulators since replaced
+    held: Option<(Simulator, CompiledInstance)>, // last build, while the drawing fails to compile
+}
+
+impl LiveSimulation {
+    pub fn new() -> Self {
+        Self {
+            simulator: Simulator::new(Circuit::new()),
+            root: CompiledInstance::default(),
+            revision: None,
+            owed_ticks: 0.0,
+            earlier_ticks: 0,
+            held: None,
+        }
+    }
+
+    /// Rebuild if the drawing changed since the last call. A drawing that fails to compile is
+    /// reported once and simulated as an empty circuit.
+    pub fn update(
+        &mut self,
+        wires: &WireSystem,
+        components: &ComponentSystem,
+        library: &SubcircuitLibrary,
+    ) -> Result<(), CompileError> {
+        let revision = (wires.revision(), components.revision(), library.revision());
+        if self.revision == Some(revision) {
+            return Ok(());
+        }
+        self.revision = Some(revision);
+        let schematic = Schematic {
+            components: components.components().cloned().collect(),
+            ..wires.schematic()
+        };
+        self.rebuild(&schematic, library.circuits())
+    }
+
+    /// Build `schematic` afresh, then carry over the state of the last build that compiled
+    fn rebuild(&mut self, schematic: &Schematic, library: &Library) -> Result<(), CompileError> {
+        let (previous, previous_root) = match self.held.take() {
+            Some(held) => held,
+            None => {
+                self.earlier_ticks += self.simulator.tick_count();
+                (
+                    std::mem::replace(&mut self.simulator, Simulator::new(Circuit::new())),
+                    std::mem::take(&mut self.root),
+                )
+            }
+        };
+        match schematic.compile(library) {
+            Ok(mut compiled) => {
+                let circuit = previous.circuit();
+                carry_state(
+                    circuit,
+                    &previous_root,
+                    &mut compiled.circuit,
+                    &compiled.root,
+                );
+                self.simulator = Simulator::new(compiled.circuit);
+                self.root = compiled.root;
+                Ok(())
+            }
+            Err(err) => {
+                self.held = Some((previous, previous_root));
+                Err(err)
+            }
+        }
+    }
+
+    /// Propagate any pending changes. An oscillating circuit is reported once and keeps
+    /// showing whatever values it reached.
+    pub fn settle(&mut self) -> Result<(), SimulationError> {
+        self.simulator.settle().map(|_| ())
+    }
+
+    /// Advance simulation time by one tick
+    pub fn step(&mut self) -> Result<(), SimulationError> {
+        self.simulator.tick().map(|_| ())
+    }
+
+    /// Advance simulation time over a frame lasting `dt` seconds. Ticks that do not fit in
+    /// the frame's budget are dropped rather than piling up. Returns the number of ticks run.
+    pub fn run(&mut self, dt: f32, rate: TickRate) -> Result<u64, SimulationError> {
+        self.run_timed(dt, rate, get_time)
+    }
+
+    /// `run`, reading the time in seconds from `clock`. A tick that fails drops the ticks
+    /// still owed, as running out of time does.
+    fn run_timed(
+        &mut self,
+        dt: f32,
+        rate: TickRate,
+        mut clock: impl FnMut() -> f64,
+    ) -> Result<u64, SimulationError> {
+        let due = match rate {
+            TickRate::PerSecond(rate) => {
+                self.owed_ticks += dt as f64 * rate;
+                self.owed_ticks.floor() as u64
+            }
+            TickRate::Unlimited => u64::MAX,
+        };
+        let deadline = clock() + TICK_BUDGET_SECONDS;
+        let mut ran = 0;
+        while ran < due && clock() < deadline {
+            if let Err(err) = self.simulator.tick() {
+                self.owed_ticks = 0.0;
+                return Err(err);
+            }
+            ran += 1;
+        }
+        self.owed_ticks = if ran < due {
+            0.0
+        } else {
+            self.owed_ticks - ran as f64
+        };
+        Ok(ran)
+    }
+
+    /// Ticks elapsed since the simulation started
+    pub fn tick_count(&self) -> u64 {
+        self.earlier_ticks + self.simulator.tick_count()
+    }
+
+    fn component_node(&self, index: usize) -> Option<NodeId> {
+        self.root.component_nodes.get(index).copied().flatten()
+    }
+
+    /// Words at `addresses` of the ROM or RAM at component `index` of the drawing. Empty if
+    /// the component has no node.
+    pub fn memory_words(&self, index: usize, addresses: Range<u64>) -> Vec<Value> {
+        let circuit = self.simulator.circuit();
+        let Some(node) = self.component_node(index).and_then(|id| circuit.node(id)) else {
+            return Vec::new();
+        };
+        let width = node.logic.output_width(0);
+        addresses
+            .map(|address| read_word(&node.state, address, width))
+            .collect()
+    }
+
+    /// Overwrite a word of the RAM at component `index` of the drawing while it runs
+    pub fn write_memory(&mut self, index: usize, address: u64, value: Value) {
+        let Some(id) = self.component_node(index) else {
+            return;
+        };
+        if let Some(node) = self.simulator.circuit_mut().node_mut(id) {
+            write_word(&mut node.state, address, value);
+            self.simulator.schedule(id);
+        }
+    }
+
+    /// Click bit `bit` of the input pin at component `index` of the drawing while it runs
+    pub fn toggle_input(&mut self, index: usize, bit: u8, three_state: bool) {
+        let Some(id) = self.component_node(index) else {
+            return;
+        };
+        if let Some(node) = self.simulator.circuit_mut().node_mut(id)
+            && let Some(value) = node.state.outputs.first()
+            && bit < value.width()
+        {
+            let value = toggle_bit(value, bit, three_state);
+            node.state.metadata.insert(VALUE_KEY.to_string(), value);
+            self.simulator.schedule(id);
+        }
+    }
+
+    /// Value shown by `component`, at `index` of the instance at `path`: what an input pin
+    /// drives, or what an output pin or probe reads. Pins of a subcircuit instance have no
+    /// node of their own, so they show the net on their pin.
+    pub fn component_value(
+        &self,
+        path: &[usize],
+        index: usize,
+        component: &PlacedComponent,
+    ) -> Option<&Value> {
+        let instance = self.instance(path)?;
+        let node = instance.component_nodes.get(index).copied().flatten();
+        if let Some(node) = node.and_then(|id| self.simulator.circuit().node(id)) {
+            return node.state.outputs.first().or(node.state.inputs.first());
+        }
+        let pins = component
+            .input_pins()
+            .into_iter()
+            .chain(component.output_pins());
+        let net = pins
+            .filter_map(|pin| instance.wire_nets.net_at_cell(pin))
+            .next()?;
+        self.simulator.net_value(*instance.net_ids.get(net)?)
+    }
+
+    /// Nets of the drawing the simulation was built from
+    pub fn wire_nets(&self) -> &NetMap {
+        &self.root.wire_nets
+    }
+
+    /// Settled value of a drawn net
+    pub fn wire_net_value(&self, net: usize) -> Option<&Value> {
+        self.simulator.net_value(*self.root.net_ids.get(net)?)
+    }
+
+    /// Subcircuit instance reached by following component indices down from the main circuit
+    pub fn instance(&self, path: &[usize]) -> Option<&CompiledInstance> {
+        self.root.descendant(path)
+    }
+
+    /// Settled value of a circuit net
+    pub fn net_value(&self, net: NetId) -> Option<&Value> {
+        self.simulator.net_value(net)
+    }
+}
+
+/// True if `a` and `b` are the same kind of logic with the same pins
+fn same_pins(a: &dyn NodeLogic, b: &dyn NodeLogic) -> bool {
+    a.name() == b.name()
+        && a.input_count() == b.input_count()
+        && a.output_count() == b.output_count()
+        && (0..a.input_count()).all(|pin| a.input_width(pin) == b.input_width(pin))
+        && (0..a.output_count()).all(|pin| a.output_width(pin) == b.output_width(pin))
+}
+
+/// Copy the state of each node of `from` to the node at the same component index of `to`,
+/// in the instance and every subcircuit instance, wherever both have the same pins. Outputs
+/// come too, so the nets of a simulator built from `to` start where they were, and so do the
+/// inputs last read on connected pins, so clocked components see no edge that did not happen.
+/// Words only come when the memory started from the same image, so editing the image
+/// replaces them.
+fn carry_state(
+    from: &Circuit,
+    from_instance: &CompiledInstance,
+    to: &mut Circuit,
+    to_instance: &CompiledInstance,
+) {
+    let nodes = from_instance
+        .component_nodes
+        .iter()
+        .zip(&to_instance.component_nodes);
+    for (&old, &new) in nodes {
+        let (Some(old), Some(node)) = (
+            old.and_then(|id| from.node(id)),
+            new.and_then(|id| to.node_mut(id)),
+        ) else {
+            continue;
+        };
+        if !same_pins(old.logic.as_ref(), node.logic.as_ref()) {
+            continue;
+        }
+        node.state.metadata.clone_from(&old.state.metadata);
+        node.state.outputs.clone_from(&old.state.outputs);
+        for (pin, net) in node.input_connections.iter().enumerate() {
+            if net.is_some() {
+                node.state.inputs[pin] = old.state.inputs[pin].clone();
+            }
+        }
+        if NodeState::new(old.logic.as_ref()).words == node.state.words {
+            node.state.words.clone_from(&old.state.words);
+        }
+    }
+    for (index, child) in &to_instance.children {
+        if let Some(old_child) = from_instance.children.get(index) {
+            carry_state(from, old_child, to, child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use backend::schematic::components::ComponentKind;
+    use backend::schematic::nets::{Direction, GridPos};
+
+    use super::*;
+
+    /// Wire running straight from each corner to the next
+    fn wire(schematic: &mut Schematic, corners: &[GridPos]) {
+        for pair in corners.windows(2) {
+            let (mut from, to) = (pair[0], pair[1]);
+            while from != to {
+                let step = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+                let direction = Direction::ALL
+                    .into_iter()
+                    .find(|direction| direction.offset() == step)
+                    .expect("wires run straight");
+                let next = direction.step(from);
+                for (cell, side) in [(from, direction), (next, direction.opposite())] {
+                    let cell = schematic.wires.entry(cell).or_default();
+                    match side {
+                        Direction::North => cell.north = true,
+                        Direction::East => cell.east = true,
+                        Direction::South => cell.south = true,
+                        Direction::West => cell.west = true,
+                    }
+                }
+                from = next;
+            }
+        }
+    }
+
+    /// Input pin wired to an output pin
+    fn feedthrough() -> Schematic {
+        let input = PlacedComponent::new(ComponentKind::Input, (0, 0));
+        let output = PlacedComponent::new(ComponentKind::Output, (6, 0));
+        let mut schematic = Schematic::default();
+        wire(
+            &mut schematic,
+            &[input.output_pins()[0], output.input_pins()[0]],
+        );
+        schematic.components = vec![input, output];
+        schematic
+    }
+
+    /// NAND gate whose output feeds back to one input, oscillating once the input pin wired
+    /// to the other goes high
+    fn enabled_ring() -> Schematic {
+        let nand = PlacedComponent::new(ComponentKind::NandGate, (4, 0));
+        let [enable, feedback] = nand.input_pins()[..] else {
+            panic!("NAND gates have two inputs");
+        };
+        let output = nand.output_pins()[0];
+        let input = PlacedComponent::new(ComponentKind::Input, (enable.0 - 4, enable.1));
+        let mut schematic = Schematic::default();
+        wire(&mut schematic, &[input.output_pins()[0], enable]);
+        let (right, below, left) = (output.0 + 1, feedback.1 + 1, feedback.0 - 1);
+        wire(
+            &mut schematic,
+            &[
+                output,
+                (right, output.1),
+                (right, below),
+                (left, below),
+                (left, feedback.1),
+                feedback,
+            ],
+        );
+        schematic.components = vec![input, nand];
+        schematic
+    }
+
+    /// Clock that moves on by `step` seconds each time it is read
+    fn ticking(step: f64) -> impl FnMut() -> f64 {
+        let mut now = 0.0;
+        move || {
+            now += step;
+            now
+        }
+    }
+
+    fn live(schematic: &Schematic) -> LiveSimulation {
+        let mut live = LiveSimulation::new();
+        live.rebuild(schematic, &Library::new()).unwrap();
+        live.settle().unwrap();
+        live
+    }
+
+    fn shown(live: &LiveSimulation, schematic: &Schematic, index: usize) -> Option<Value> {
+        live.component_value(&[], index, &schematic.components[index])
+            .cloned()
+    }
+
+    #[test]
+    fn edits_keep_poked_inputs_and_ticks() {
+        let mut schematic = feedthrough();
+        let mut live = LiveSimulation::new();
+        live.rebuild(&schematic, &Library::new()).unwrap();
+        live.settle().unwrap();
+        live.toggle_input(0, 0, false);
+        live.step().unwrap();
+        assert_eq!(shown(&live, &schematic, 1), Some(Value::new(1, 1)));
+
+        // A wire joined to nothing
+        wire(&mut schematic, &[(0, 5), (3, 5)]);
+        live.rebuild(&schematic, &Library::new()).unwrap();
+        live.settle().unwrap();
+        assert_eq!(shown(&live, &schematic, 0), Some(Value::new(1, 1)));
+        assert_eq!(shown(&live, &schematic, 1), Some(Value::new(1, 1)));
+        assert_eq!(live.tick_count(), 1);
+
+        // A drawing that does not compile holds on to the state until one does
+        let mut broken = schematic.clone();
+        broken.components[1].bit_width = 4;
+        assert!(live.rebuild(&broken, &Library::new()).is_err());
+        live.rebuild(&schematic, &Library::new()).unwrap();
+        live.settle().unwrap();
+        assert_eq!(shown(&live, &schematic, 1), Some(Value::new(1, 1)));
+    }
+
+    #[test]
+    fn edits_keep_stored_bits() {
+        // Data and clock inputs into a D flip-flop
+        let flip_flop = PlacedComponent::new(ComponentKind::DFlipFlop, (4, 0));
+        let pins = flip_flop.input_pins();
+        let mut schematic = Schematic::default();
+        for pin in &pins[..2] {
+            let input = PlacedComponent::new(ComponentKind::Input, (pin.0 - 4, pin.1));
+            wire(&mut schematic, &[input.output_pins()[0], *pin]);
+            schematic.components.push(input);
+        }
+        schematic.components.push(flip_flop);
+
+        let mut live = LiveSimulation::new();
+        live.rebuild(&schematic, &Library::new()).unwrap();
+        live.settle().unwrap();
+        live.toggle_input(0, 0, false);
+        live.settle().unwrap();
+        live.toggle_input(1, 0, false);
+        live.settle().unwrap();
+        assert_eq!(shown(&live, &schematic, 2), Some(Value::new(1, 1)));
+
+        wire(&mut schematic, &[(0, 9), (3, 9)]);
+        live.rebuild(&schematic, &Library::new()).unwrap();
+        live.settle().unwrap();
+        assert_eq!(shown(&live, &schematic, 2), Some(Value::new(1, 1)));
+    }
+
+    #[test]
+    fn fractions_of_a_tick_carry_between_frames() {
+        let mut live = live(&feedthrough());
+        let rate = TickRate::PerSecond(10.0);
+        assert_eq!(live.run_timed(0.05, rate, || 0.0), Ok(0));
+        assert_eq!(live.run_timed(0.05, rate, || 0.0), Ok(1));
+        assert_eq!(live.run_timed(0.25, rate, || 0.0), Ok(2));
+        assert_eq!(live.tick_count(), 3);
+    }
+
+    #[test]
+    fn ticks_stop_at_the_frame_budget() {
+        let mut live = live(&feedthrough());
+        // A millisecond a tick fits 8 in the budget of a 120th of a second
+        assert_eq!(
+            live.run_timed(1.0, TickRate::Unlimited, ticking(0.001)),
+            Ok(8)
+        );
+
+        // Ticks that did not fit are dropped, not run in later frames
+        let rate = TickRate::PerSecond(1000.0);
+        assert_eq!(live.run_timed(1.0, rate, ticking(0.001)), Ok(8));
+        assert_eq!(live.run_timed(0.0, rate, || 0.0), Ok(0));
+        assert_eq!(live.tick_count(), 16);
+    }
+
+    #[test]
+    fn failed_ticks_drop_the_ticks_owed() {
+        let mut live = live(&enabled_ring());
+        live.toggle_input(0, 0, false);
+        let rate = TickRate::PerSecond(10.0);
+        assert!(live.run_timed(0.35, rate, || 0.0).is_err());
+        assert_eq!(live.owed_ticks, 0.0);
+    }
+}
@@ -0,0 +1,58 @@
+use backend::schematic::{Library, Schematic};
+
+/// Circuits saved for use as subcircuits in the project
+#[derive(Default)]
+pub struct SubcircuitLibrary {
+    circuits: Library,
+    revision: u64, // bumped whenever a circuit is added or replaced
+}
+
+impl SubcircuitLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn circuits(&self) -> &Library {
+        &self.circuits
+    }
+
+    /// Changes every time the library does
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.circuits.keys().cloned().collect()
+    }
+
+    /// Replace every circuit
+    pub fn load(&mut self, circuits: Library) {
+        self.circuits = circuits;
+        self.revision += 1;
+    }
+
+    /// Add the circuits whose names aren't taken, keeping any already here under the same
+    /// name. Returns whether anything was added.
+    pub fn merge(&mut self, circuits: Library) -> bool {
+        let before = self.circuits.len();
+        for (name, schematic) in circuits {
+            self.circuits.entry(name).or_insert(schematic);
+        }
+        let added = self.circuits.len() != before;
+        if added {
+            self.revision += 1;
+        }
+        added
+    }
+
+    /// Add `schematic` under `name`, replacing any circuit already there, and give every
+    /// instance of it elsewhere in the library its new pins
+    pub fn save(&mut self, name: &str, schematic: Schematic) {
+        self.circuits.insert(name.to_string(), schematic);
+        let library = self.circuits.clone();
+        for circuit in self.circuits.values_mut() {
+            circuit.match_library(&library);
+        }
+        self.revision += 1;
+    }
+}